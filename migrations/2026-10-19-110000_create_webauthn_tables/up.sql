@@ -0,0 +1,19 @@
+CREATE TABLE webauthn_credentials (
+    credential_id VARCHAR(1024) PRIMARY KEY,
+    user_id VARCHAR(255) NOT NULL,
+    public_key BYTEA NOT NULL,
+    alg INTEGER NOT NULL,
+    sign_count BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TIMESTAMPTZ
+    );
+
+CREATE INDEX webauthn_credentials_user_id_idx ON webauthn_credentials (user_id);
+
+CREATE TABLE webauthn_challenges (
+    challenge VARCHAR(255) PRIMARY KEY,
+    user_id VARCHAR(255),
+    ceremony VARCHAR(32) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMPTZ NOT NULL
+    );
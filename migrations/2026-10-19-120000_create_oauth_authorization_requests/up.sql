@@ -0,0 +1,7 @@
+CREATE TABLE oauth_authorization_requests (
+    state VARCHAR(255) PRIMARY KEY,
+    code_verifier VARCHAR(255) NOT NULL,
+    nonce VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMPTZ NOT NULL
+    );
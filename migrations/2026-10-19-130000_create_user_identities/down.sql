@@ -0,0 +1,2 @@
+ALTER TABLE oauth_authorization_requests DROP COLUMN connection;
+DROP TABLE user_identities;
@@ -0,0 +1,11 @@
+CREATE TABLE user_identities (
+    provider VARCHAR(255) NOT NULL,
+    provider_user_id VARCHAR(255) NOT NULL,
+    user_id VARCHAR(255) NOT NULL,
+    linked_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (provider, provider_user_id)
+    );
+
+CREATE INDEX user_identities_user_id_idx ON user_identities (user_id);
+
+ALTER TABLE oauth_authorization_requests ADD COLUMN connection VARCHAR(255);
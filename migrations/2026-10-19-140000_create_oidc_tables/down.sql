@@ -0,0 +1,9 @@
+ALTER TABLE oauth_authorization_requests
+    DROP COLUMN client_id,
+    DROP COLUMN redirect_uri,
+    DROP COLUMN client_state,
+    DROP COLUMN client_code_challenge,
+    DROP COLUMN client_nonce,
+    DROP COLUMN scope;
+DROP TABLE refresh_tokens;
+DROP TABLE authorization_codes;
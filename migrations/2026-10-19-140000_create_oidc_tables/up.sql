@@ -0,0 +1,30 @@
+CREATE TABLE authorization_codes (
+    code_hash VARCHAR(255) PRIMARY KEY,
+    client_id VARCHAR(255) NOT NULL,
+    user_id VARCHAR(255) NOT NULL,
+    redirect_uri VARCHAR(2048) NOT NULL,
+    code_challenge VARCHAR(255),
+    nonce VARCHAR(255),
+    scope VARCHAR(1024) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMPTZ NOT NULL
+    );
+
+CREATE TABLE refresh_tokens (
+    token_hash VARCHAR(255) PRIMARY KEY,
+    client_id VARCHAR(255) NOT NULL,
+    user_id VARCHAR(255) NOT NULL,
+    scope VARCHAR(1024) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMPTZ NOT NULL
+    );
+
+CREATE INDEX refresh_tokens_user_id_idx ON refresh_tokens (user_id);
+
+ALTER TABLE oauth_authorization_requests
+    ADD COLUMN client_id VARCHAR(255),
+    ADD COLUMN redirect_uri VARCHAR(2048),
+    ADD COLUMN client_state VARCHAR(1024),
+    ADD COLUMN client_code_challenge VARCHAR(255),
+    ADD COLUMN client_nonce VARCHAR(255),
+    ADD COLUMN scope VARCHAR(1024);
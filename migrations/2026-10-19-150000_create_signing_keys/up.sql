@@ -0,0 +1,12 @@
+CREATE TABLE signing_keys (
+    kid VARCHAR(255) PRIMARY KEY,
+    alg VARCHAR(16) NOT NULL,
+    state VARCHAR(16) NOT NULL,
+    encrypted_private_key BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    activated_at TIMESTAMPTZ,
+    retired_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ
+    );
+
+CREATE UNIQUE INDEX signing_keys_single_active_idx ON signing_keys (state) WHERE state = 'active';
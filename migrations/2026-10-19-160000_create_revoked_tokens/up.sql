@@ -0,0 +1,7 @@
+CREATE TABLE revoked_tokens (
+    token_id VARCHAR(255) PRIMARY KEY,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+
+CREATE INDEX revoked_tokens_expires_at_idx ON revoked_tokens (expires_at);
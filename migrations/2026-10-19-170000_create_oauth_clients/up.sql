@@ -0,0 +1,12 @@
+CREATE TABLE oauth_clients (
+    client_id VARCHAR(255) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    client_secret_hash VARCHAR(64),
+    grant_types TEXT[] NOT NULL,
+    redirect_uris TEXT[] NOT NULL DEFAULT '{}',
+    allowed_scopes TEXT[] NOT NULL DEFAULT '{}',
+    access_token_ttl BIGINT,
+    refresh_token_ttl BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ
+    );
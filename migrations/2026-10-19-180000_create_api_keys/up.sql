@@ -0,0 +1,17 @@
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY,
+    prefix VARCHAR(32) NOT NULL UNIQUE,
+    secret_hash VARCHAR(64) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    user_id VARCHAR(255),
+    client_id VARCHAR(255),
+    scope VARCHAR NOT NULL DEFAULT '',
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    revoked_at TIMESTAMPTZ,
+    CHECK ((user_id IS NULL) <> (client_id IS NULL))
+    );
+
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
+CREATE INDEX api_keys_client_id_idx ON api_keys (client_id);
@@ -0,0 +1,4 @@
+DROP INDEX refresh_tokens_session_id_idx;
+ALTER TABLE refresh_tokens DROP COLUMN session_id;
+ALTER TABLE authorization_codes DROP COLUMN session_id;
+ALTER TABLE sessions DROP COLUMN geo_hint;
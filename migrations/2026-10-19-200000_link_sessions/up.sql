@@ -0,0 +1,9 @@
+ALTER TABLE sessions ADD COLUMN geo_hint VARCHAR(64);
+
+ALTER TABLE authorization_codes
+    ADD COLUMN session_id UUID REFERENCES sessions (id) ON DELETE CASCADE;
+
+ALTER TABLE refresh_tokens
+    ADD COLUMN session_id UUID REFERENCES sessions (id) ON DELETE CASCADE;
+
+CREATE INDEX refresh_tokens_session_id_idx ON refresh_tokens (session_id);
@@ -0,0 +1 @@
+ALTER TABLE oauth_authorization_requests DROP COLUMN link_user_id;
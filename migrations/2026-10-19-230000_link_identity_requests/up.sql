@@ -0,0 +1,2 @@
+-- Set when a signed-in user links a social identity to their account.
+ALTER TABLE oauth_authorization_requests ADD COLUMN link_user_id VARCHAR(255);
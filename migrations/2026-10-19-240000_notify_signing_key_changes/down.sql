@@ -0,0 +1,2 @@
+DROP TRIGGER signing_keys_notify ON signing_keys;
+DROP FUNCTION signing_keys_notify();
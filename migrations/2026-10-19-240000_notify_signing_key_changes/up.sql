@@ -0,0 +1,12 @@
+-- Lets running instances reload their keys as soon as a change is committed,
+-- instead of on their next rotation check.
+CREATE FUNCTION signing_keys_notify() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('signing_keys', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER signing_keys_notify
+    AFTER INSERT OR UPDATE OR DELETE ON signing_keys
+    FOR EACH STATEMENT EXECUTE FUNCTION signing_keys_notify();
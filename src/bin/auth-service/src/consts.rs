@@ -3,3 +3,4 @@ pub static CONTENT_TYPE: &str = "Content-Type";
 pub static APPLICATION_JSON: &str = "application/json";
 pub static AUTHORIZATION: &str = "Authorization";
 pub static ACCESS_TOKEN: &str = "access_token";
+pub static REQUEST_ID_HEADER: &str = "X-Request-Id";
@@ -1,6 +1,8 @@
+use crate::middleware::request_id;
 use actix_web::body::BoxBody;
 use actix_web::http::StatusCode;
 use actix_web::HttpResponse;
+use diesel::result::DatabaseErrorKind;
 use diesel_async::pooled_connection::PoolError;
 use serde::Serialize;
 
@@ -28,10 +30,22 @@ pub enum Error {
     DieselError(#[from] diesel::result::Error),
 
     #[error(transparent)]
-    SetLoggerError(#[from] log::SetLoggerError),
+    PostgresError(#[from] tokio_postgres::Error),
 
     #[error(transparent)]
-    ParseLevelError(#[from] log::ParseLevelError),
+    TracingInitError(#[from] tracing_subscriber::util::TryInitError),
+
+    #[error(transparent)]
+    OtlpExporterError(#[from] opentelemetry_otlp::ExporterBuildError),
+
+    #[error(transparent)]
+    TlsError(#[from] rustls::Error),
+
+    #[error(transparent)]
+    PemError(#[from] rustls::pki_types::pem::Error),
+
+    #[error(transparent)]
+    ClientVerifierError(#[from] rustls::server::VerifierBuilderError),
 
     #[error(transparent)]
     MailboxError(#[from] actix::MailboxError),
@@ -72,53 +86,447 @@ pub enum Error {
     #[error(transparent)]
     Auth0RequestBuildError(#[from] crate::services::auth0::errors::BuildError),
 
+    #[error(transparent)]
+    Auth0(#[from] crate::services::auth0::errors::Auth0Error),
+
+    #[error("Identity provider is unavailable")]
+    IdpUnavailable,
+
     #[error(transparent)]
     UuidError(#[from] uuid::Error),
+
+    #[error("Invalid MFA code")]
+    InvalidMfaCode,
+
+    #[error("MFA challenge expired or not found")]
+    MfaChallengeExpired,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Forbidden")]
+    Forbidden,
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("User not found")]
+    UserNotFound,
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Invalid request body: {0}")]
+    InvalidBody(String),
+
+    #[error("WebAuthn verification failed: {0}")]
+    WebauthnVerification(String),
+
+    #[error("OAuth error {error}: {description}")]
+    OAuth {
+        error: &'static str,
+        description: String,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Error body of the API (RFC 7807). `code` is stable and meant for machines,
+/// `detail` for humans.
 #[derive(Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct ErrorMessageResponse {
-    pub message: String,
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
-impl ErrorMessageResponse {
-    pub fn response_from(status: StatusCode, err: &Error) -> HttpResponse {
-        HttpResponse::build(status).json(Self {
-            message: err.to_string(),
-        })
-    }
+impl ProblemDetails {
+    pub fn response_from(err: &Error) -> HttpResponse {
+        let status = err.status();
 
-    pub fn response_with_message(status: StatusCode, message: String) -> HttpResponse {
-        HttpResponse::build(status).json(Self { message })
+        HttpResponse::build(status)
+            .content_type(PROBLEM_JSON)
+            .json(Self {
+                problem_type: "about:blank".to_string(),
+                title: status.canonical_reason().unwrap_or_default().to_string(),
+                status: status.as_u16(),
+                detail: err.public_detail(),
+                code: err.code().as_str(),
+                request_id: request_id::current(),
+            })
     }
 }
 
-impl actix_web::ResponseError for Error {
-    fn error_response(&self) -> HttpResponse<BoxBody> {
-        log::warn!("{:?}", self);
+/// Error body of the OAuth endpoints (RFC 6749 §5.2).
+#[derive(Serialize, Clone)]
+pub struct OAuthErrorResponse {
+    pub error: String,
+    pub error_description: String,
+}
+
+/// A stable, machine-readable name for an error, sent as `code` in the
+/// problem details. Each code decides the status and the fixed message the
+/// client gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    InvalidRequest,
+    InvalidBody,
+    InvalidCredentials,
+    InvalidToken,
+    InvalidMfaCode,
+    MfaChallengeExpired,
+    WebauthnVerificationFailed,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    UserNotFound,
+    Conflict,
+    UserExists,
+    WeakPassword,
+    TooManyAttempts,
+    MfaRequired,
+    UserBlocked,
+    AccessDenied,
+    IdpError,
+    IdpUnavailable,
+    DatabaseUnavailable,
+    ServiceUnavailable,
+    InternalError,
+    /// An RFC 6749 error code of the OAuth endpoints.
+    OAuth(&'static str),
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Error::Io(_) => ErrorMessageResponse::response_from(StatusCode::BAD_REQUEST, self),
-            Error::Parse(_) => ErrorMessageResponse::response_from(StatusCode::BAD_REQUEST, self),
-            Error::InvalidInput(_) => {
-                ErrorMessageResponse::response_from(StatusCode::BAD_REQUEST, self)
+            ErrorCode::InvalidRequest => "invalid_request",
+            ErrorCode::InvalidBody => "invalid_body",
+            ErrorCode::InvalidCredentials => "invalid_credentials",
+            ErrorCode::InvalidToken => "invalid_token",
+            ErrorCode::InvalidMfaCode => "invalid_mfa_code",
+            ErrorCode::MfaChallengeExpired => "mfa_challenge_expired",
+            ErrorCode::WebauthnVerificationFailed => "webauthn_verification_failed",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::UserNotFound => "user_not_found",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::UserExists => "user_exists",
+            ErrorCode::WeakPassword => "weak_password",
+            ErrorCode::TooManyAttempts => "too_many_attempts",
+            ErrorCode::MfaRequired => "mfa_required",
+            ErrorCode::UserBlocked => "user_blocked",
+            ErrorCode::AccessDenied => "access_denied",
+            ErrorCode::IdpError => "idp_error",
+            ErrorCode::IdpUnavailable => "idp_unavailable",
+            ErrorCode::DatabaseUnavailable => "database_unavailable",
+            ErrorCode::ServiceUnavailable => "service_unavailable",
+            ErrorCode::InternalError => "internal_error",
+            ErrorCode::OAuth(error) => error,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidBody | ErrorCode::WeakPassword => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::InvalidCredentials
+            | ErrorCode::InvalidToken
+            | ErrorCode::InvalidMfaCode
+            | ErrorCode::MfaChallengeExpired
+            | ErrorCode::WebauthnVerificationFailed
+            | ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden
+            | ErrorCode::MfaRequired
+            | ErrorCode::UserBlocked
+            | ErrorCode::AccessDenied => StatusCode::FORBIDDEN,
+            ErrorCode::NotFound | ErrorCode::UserNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::Conflict | ErrorCode::UserExists => StatusCode::CONFLICT,
+            ErrorCode::TooManyAttempts => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::IdpError => StatusCode::BAD_GATEWAY,
+            ErrorCode::IdpUnavailable
+            | ErrorCode::DatabaseUnavailable
+            | ErrorCode::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::OAuth(crate::services::oidc::consts::ERROR_INVALID_CLIENT) => {
+                StatusCode::UNAUTHORIZED
             }
-            Error::PoolError(_) => {
-                ErrorMessageResponse::response_from(StatusCode::INTERNAL_SERVER_ERROR, self)
+            ErrorCode::OAuth(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    /// The fixed message clients get when the error's own is not for them.
+    pub fn public_message(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidRequest => "The request is invalid",
+            ErrorCode::InvalidToken => "The token is invalid",
+            ErrorCode::NotFound => "Not found",
+            ErrorCode::Conflict => "The resource already exists",
+            ErrorCode::IdpError => "The identity provider returned an unexpected response",
+            ErrorCode::IdpUnavailable => "The identity provider is unavailable",
+            ErrorCode::DatabaseUnavailable | ErrorCode::ServiceUnavailable => {
+                "The service is temporarily unavailable"
             }
-            Error::DatabaseError(_) => {
-                ErrorMessageResponse::response_from(StatusCode::INTERNAL_SERVER_ERROR, self)
+            ErrorCode::InternalError => "Internal server error",
+            ErrorCode::InvalidBody
+            | ErrorCode::InvalidCredentials
+            | ErrorCode::InvalidMfaCode
+            | ErrorCode::MfaChallengeExpired
+            | ErrorCode::WebauthnVerificationFailed
+            | ErrorCode::Unauthorized
+            | ErrorCode::Forbidden
+            | ErrorCode::UserNotFound
+            | ErrorCode::UserExists
+            | ErrorCode::WeakPassword
+            | ErrorCode::TooManyAttempts
+            | ErrorCode::MfaRequired
+            | ErrorCode::UserBlocked
+            | ErrorCode::AccessDenied
+            | ErrorCode::OAuth(_) => self.status().canonical_reason().unwrap_or("Bad request"),
+        }
+    }
+}
+
+impl Error {
+    /// The stable, machine-readable name for the error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Parse(_) | Error::InvalidInput(_) => ErrorCode::InvalidRequest,
+            Error::InvalidBody(_) => ErrorCode::InvalidBody,
+            Error::InvalidToken => ErrorCode::InvalidCredentials,
+            Error::JsonWebTokenError(_) | Error::AlcoholicJwtValidationError(_) => {
+                ErrorCode::InvalidToken
             }
-            Error::DieselError(_) => {
-                ErrorMessageResponse::response_from(StatusCode::INTERNAL_SERVER_ERROR, self)
+            Error::InvalidMfaCode => ErrorCode::InvalidMfaCode,
+            Error::MfaChallengeExpired => ErrorCode::MfaChallengeExpired,
+            Error::WebauthnVerification(_) => ErrorCode::WebauthnVerificationFailed,
+            Error::Unauthorized => ErrorCode::Unauthorized,
+            Error::Forbidden => ErrorCode::Forbidden,
+            Error::NotFound(_) | Error::DieselError(diesel::result::Error::NotFound) => {
+                ErrorCode::NotFound
             }
-            Error::SerdeJsonError(_) => ErrorMessageResponse::response_with_message(
+            Error::UserNotFound => ErrorCode::UserNotFound,
+            Error::Conflict(_) => ErrorCode::Conflict,
+            Error::DieselError(diesel::result::Error::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                _,
+            )) => ErrorCode::Conflict,
+            Error::OAuth { error, .. } => ErrorCode::OAuth(error),
+            Error::Auth0(e) => e.code(),
+            Error::IdpUnavailable => ErrorCode::IdpUnavailable,
+            Error::ReqwestError(e) if e.is_timeout() || e.is_connect() => ErrorCode::IdpUnavailable,
+            Error::ReqwestError(_) => ErrorCode::IdpError,
+            Error::PoolError(_) | Error::DatabaseError(_) => ErrorCode::DatabaseUnavailable,
+            Error::MailboxError(_) => ErrorCode::ServiceUnavailable,
+            _ => ErrorCode::InternalError,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.code().status()
+    }
+
+    /// What clients are told about the error. Only messages written for them
+    /// are passed on; errors wrapped from libraries and server-side failures
+    /// stay in the log, as they can carry queries, constraint names, hosts or
+    /// upstream responses.
+    fn public_detail(&self) -> String {
+        match self {
+            _ if self.status().is_server_error() => self.code().public_message().to_string(),
+            Error::InvalidInput(_)
+            | Error::InvalidBody(_)
+            | Error::InvalidToken
+            | Error::InvalidMfaCode
+            | Error::MfaChallengeExpired
+            | Error::WebauthnVerification(_)
+            | Error::Unauthorized
+            | Error::Forbidden
+            | Error::NotFound(_)
+            | Error::UserNotFound
+            | Error::Conflict(_)
+            | Error::Auth0(_) => self.to_string(),
+            _ => self.code().public_message().to_string(),
+        }
+    }
+}
+
+impl actix_web::ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        self.status()
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        if self.status().is_server_error() {
+            tracing::error!("{:?}", self);
+        } else {
+            tracing::warn!("{:?}", self);
+        }
+
+        match self {
+            Error::OAuth { error, description } => {
+                HttpResponse::build(self.status()).json(OAuthErrorResponse {
+                    error: error.to_string(),
+                    error_description: description.clone(),
+                })
+            }
+            _ => ProblemDetails::response_from(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::result::Error as DieselError;
+
+    fn unique_violation() -> Error {
+        Error::DieselError(DieselError::DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            Box::new(
+                "duplicate key value violates unique constraint \"users_email_key\"".to_string(),
+            ),
+        ))
+    }
+
+    fn parse_error() -> Error {
+        "x".parse::<i64>()
+            .map(|n| Error::StringError(format!("parsed {}", n)))
+            .unwrap_or_else(Error::Parse)
+    }
+
+    #[test]
+    fn hides_internal_details() {
+        let cases = [
+            (
+                unique_violation(),
+                StatusCode::CONFLICT,
+                "The resource already exists",
+            ),
+            (
+                Error::DieselError(DieselError::NotFound),
+                StatusCode::NOT_FOUND,
+                "Not found",
+            ),
+            (
+                Error::DieselError(DieselError::RollbackTransaction),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            ),
+            (
+                parse_error(),
+                StatusCode::BAD_REQUEST,
+                "The request is invalid",
+            ),
+            (
+                Error::JsonWebTokenError(jsonwebtoken::errors::ErrorKind::InvalidSignature.into()),
+                StatusCode::UNAUTHORIZED,
+                "The token is invalid",
+            ),
+            (
+                Error::StringError("key for kid-1 is missing".to_string()),
                 StatusCode::INTERNAL_SERVER_ERROR,
-                "Incorrect data, please check your request".to_string(),
+                "Internal server error",
+            ),
+            (
+                Error::MailboxError(actix::MailboxError::Closed),
+                StatusCode::SERVICE_UNAVAILABLE,
+                "The service is temporarily unavailable",
+            ),
+            (
+                Error::Auth0(crate::services::auth0::errors::Auth0Error {
+                    status: 500,
+                    error: "server_error".to_string(),
+                    description: "upstream stack trace".to_string(),
+                }),
+                StatusCode::BAD_GATEWAY,
+                "The identity provider returned an unexpected response",
+            ),
+        ];
+
+        for (err, status, detail) in cases {
+            assert_eq!(err.status(), status, "{:?}", err);
+            assert_eq!(err.public_detail(), detail, "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn passes_on_messages_written_for_clients() {
+        let cases = [
+            (
+                Error::InvalidInput("Missing name".to_string()),
+                "Invalid input: Missing name",
             ),
-            _ => ErrorMessageResponse::response_from(StatusCode::INTERNAL_SERVER_ERROR, self),
+            (
+                Error::Conflict("Client app exists".to_string()),
+                "Conflict: Client app exists",
+            ),
+            (
+                Error::NotFound("API key 1".to_string()),
+                "Not found: API key 1",
+            ),
+            (Error::Forbidden, "Forbidden"),
+            (
+                Error::Auth0(crate::services::auth0::errors::Auth0Error {
+                    status: 400,
+                    error: "invalid_password".to_string(),
+                    description: "Password is too weak".to_string(),
+                }),
+                "Password is too weak",
+            ),
+        ];
+
+        for (err, detail) in cases {
+            assert_eq!(err.public_detail(), detail, "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn maps_auth0_errors_to_codes() {
+        let cases = [
+            (
+                "invalid_password",
+                400,
+                "weak_password",
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            ("user_exists", 400, "user_exists", StatusCode::CONFLICT),
+            (
+                "invalid_grant",
+                403,
+                "invalid_credentials",
+                StatusCode::UNAUTHORIZED,
+            ),
+            ("blocked_user", 401, "user_blocked", StatusCode::FORBIDDEN),
+            (
+                "unknown",
+                429,
+                "too_many_attempts",
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            (
+                "unknown",
+                503,
+                "idp_unavailable",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            ("unknown", 400, "idp_error", StatusCode::BAD_GATEWAY),
+        ];
+
+        for (error, upstream_status, code, status) in cases {
+            let err = Error::Auth0(crate::services::auth0::errors::Auth0Error {
+                status: upstream_status,
+                error: error.to_string(),
+                description: String::new(),
+            });
+            assert_eq!(err.code().as_str(), code, "{}", error);
+            assert_eq!(err.status(), status, "{}", error);
         }
     }
 }
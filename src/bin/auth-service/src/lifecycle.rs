@@ -0,0 +1,89 @@
+use crate::services::actors::messages::Stop;
+use crate::services::audit::audit_service::AuditService;
+use crate::services::db::postgres_db::DbService;
+use crate::services::db::utils::DatabasePool;
+use actix::{Addr, Recipient};
+use std::time::Duration;
+
+/// What has to be wound down once the server stops taking requests, in the
+/// order it happens: background jobs, the audit queue, `DbService` and
+/// finally the pool.
+pub struct Lifecycle {
+    jobs: Vec<Recipient<Stop>>,
+    audit: AuditService,
+    db: Addr<DbService>,
+    pool: DatabasePool,
+    flush_timeout: Duration,
+}
+
+impl Lifecycle {
+    pub(crate) fn new(
+        jobs: Vec<Recipient<Stop>>,
+        audit: AuditService,
+        db: Addr<DbService>,
+        pool: DatabasePool,
+        flush_timeout: u64,
+    ) -> Self {
+        Lifecycle {
+            jobs,
+            audit,
+            db,
+            pool,
+            flush_timeout: Duration::from_secs(flush_timeout),
+        }
+    }
+
+    pub(crate) fn add_job(&mut self, job: Recipient<Stop>) {
+        self.jobs.push(job);
+    }
+
+    pub async fn shutdown(self) {
+        tracing::info!("Stopping background jobs");
+        for job in self.jobs {
+            // A job that has already stopped has nothing left to do.
+            let _ = job.send(Stop).await;
+        }
+
+        tracing::info!("Flushing audit events");
+        if tokio::time::timeout(self.flush_timeout, self.audit.close())
+            .await
+            .is_err()
+        {
+            tracing::error!(
+                "Audit events were not written within {:?}, dropping the rest",
+                self.flush_timeout
+            );
+        }
+
+        // Queued behind any writes still in the mailbox.
+        if let Err(e) = self.db.send(Stop).await {
+            tracing::error!("Failed to stop the database service: {}", e);
+        }
+
+        self.pool.close();
+        tracing::info!("Shutdown complete");
+    }
+}
+
+/// Resolves on SIGTERM or SIGINT, after which the server drains in-flight
+/// requests before stopping.
+#[cfg(unix)]
+pub async fn shutdown_signal() -> std::io::Result<&'static str> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+
+    Ok(tokio::select! {
+        _ = terminate.recv() => "SIGTERM",
+        _ = interrupt.recv() => "SIGINT",
+    })
+}
+
+/// Resolves on Ctrl-C, after which the server drains in-flight requests
+/// before stopping.
+#[cfg(not(unix))]
+pub async fn shutdown_signal() -> std::io::Result<&'static str> {
+    tokio::signal::ctrl_c().await?;
+    Ok("Ctrl-C")
+}
@@ -1,21 +1,58 @@
 pub mod consts;
 pub mod errors;
+pub mod lifecycle;
 pub mod middleware;
 pub mod opts;
 pub mod routes;
 pub mod services;
+pub mod telemetry;
+pub mod tls;
 pub mod utils;
 
-use crate::errors::Result;
+use crate::errors::{Error, Result};
+use crate::lifecycle::{shutdown_signal, Lifecycle};
+use crate::middleware::audit::AuditMiddleware;
+use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::metrics::MetricsMiddleware;
+use crate::middleware::public_paths::PublicPaths;
+use crate::middleware::request_id::RequestIdMiddleware;
+use crate::middleware::trace_context::TraceContextMiddleware;
+use crate::middleware::trusted_proxies::TrustedProxies;
 use crate::opts::app::AppState;
-use crate::opts::cmd_opts::{load_configurations, Opts};
-use crate::routes::configure_routes;
+use crate::opts::cmd_opts::{
+    load_configurations, AdminCommand, ApplicationOpts, Cli, KeysCommand, Opts, TokenOpts,
+};
+use crate::routes::{configure_metrics_routes, configure_routes};
+use crate::services::admin::admin_service::AdminService;
+use crate::services::api_keys::api_key_service::ApiKeyService;
+use crate::services::audit::audit_service::AuditService;
+use crate::services::audit::audit_sink::DbAuditSink;
 use crate::services::auth0::auth0_service::Auth0Service;
 use crate::services::db::postgres_db::DbService;
+use crate::services::db::purge_service::PurgeService;
 use crate::services::db::utils::create_connection_pool;
-use crate::utils::{configure_data, init_logging};
-use actix::Actor;
+use crate::services::health::health_service::HealthService;
+use crate::services::metrics::metrics_collector::MetricsCollector;
+use crate::services::metrics::metrics_service::MetricsService;
+use crate::services::mfa::mfa_service::MfaService;
+use crate::services::oidc::oidc_service::OidcService;
+use crate::services::sessions::session_service::SessionService;
+use crate::services::token::consts::KEY_ENCRYPTION_KEY_LEN;
+use crate::services::token::key_cipher::KeyCipher;
+use crate::services::token::key_rotation::{KeyManager, KeyRotationService};
+use crate::services::token::token_issuer::TokenIssuer;
+use crate::services::webauthn::webauthn_service::WebauthnService;
+use crate::telemetry::init_tracing;
+use crate::tls::{load_tls, record_client_certificate};
+use crate::utils::configure_data;
+use actix::{Actor, Addr};
+use actix_web::http::KeepAlive;
+use actix_web::web::Data;
+use clap::Parser;
 use jsonwebtoken::DecodingKey;
+use rustls::ServerConfig;
+use std::sync::Arc;
+use std::time::Duration;
 use utoipa::OpenApi;
 
 #[derive(OpenApi)]
@@ -23,12 +60,97 @@ use utoipa::OpenApi;
     paths(
         crate::services::actix_requests::requests::login,
         crate::services::actix_requests::requests::register,
-        crate::services::actix_requests::requests::change_password
+        crate::services::actix_requests::requests::change_password,
+        crate::services::actix_requests::mfa_requests::enroll_totp,
+        crate::services::actix_requests::mfa_requests::confirm_totp,
+        crate::services::actix_requests::mfa_requests::regenerate_recovery_codes,
+        crate::services::actix_requests::mfa_requests::verify_mfa_login,
+        crate::services::actix_requests::session_requests::logout,
+        crate::services::actix_requests::session_requests::list_sessions,
+        crate::services::actix_requests::session_requests::revoke_session,
+        crate::services::actix_requests::session_requests::revoke_other_sessions,
+        crate::services::actix_requests::session_requests::list_user_sessions,
+        crate::services::actix_requests::session_requests::revoke_user_sessions,
+        crate::services::actix_requests::audit_requests::list_audit_events,
+        crate::services::actix_requests::audit_requests::export_audit_events,
+        crate::services::actix_requests::webauthn_requests::webauthn_register_options,
+        crate::services::actix_requests::webauthn_requests::webauthn_register_verify,
+        crate::services::actix_requests::webauthn_requests::webauthn_login_options,
+        crate::services::actix_requests::webauthn_requests::webauthn_login_verify,
+        crate::services::actix_requests::passwordless_requests::passwordless_start,
+        crate::services::actix_requests::passwordless_requests::passwordless_verify,
+        crate::services::actix_requests::oauth_requests::authorize,
+        crate::services::actix_requests::oauth_requests::callback,
+        crate::services::actix_requests::oauth_requests::link_identity,
+        crate::services::actix_requests::oidc_requests::openid_configuration,
+        crate::services::actix_requests::oidc_requests::jwks,
+        crate::services::actix_requests::oidc_requests::token,
+        crate::services::actix_requests::oidc_requests::introspect,
+        crate::services::actix_requests::oidc_requests::revoke,
+        crate::services::actix_requests::health_requests::healthz,
+        crate::services::actix_requests::health_requests::readyz,
+        crate::services::actix_requests::client_requests::list_clients,
+        crate::services::actix_requests::client_requests::create_client,
+        crate::services::actix_requests::client_requests::get_client,
+        crate::services::actix_requests::client_requests::update_client,
+        crate::services::actix_requests::client_requests::delete_client,
+        crate::services::actix_requests::client_requests::rotate_client_secret,
+        crate::services::actix_requests::api_key_requests::create_api_key,
+        crate::services::actix_requests::api_key_requests::list_api_keys,
+        crate::services::actix_requests::api_key_requests::revoke_api_key,
+        crate::services::actix_requests::api_key_requests::create_client_api_key,
+        crate::services::actix_requests::api_key_requests::list_client_api_keys,
+        crate::services::actix_requests::api_key_requests::revoke_client_api_key
     ),
     components(
         schemas(crate::services::actix_requests::models::RegisteredUserData),
         schemas(crate::services::actix_requests::models::UserData),
-        schemas(crate::services::actix_requests::models::UpdatePasswordData),
+        schemas(crate::services::actix_requests::models::LoginUserResponse),
+        schemas(crate::services::actix_requests::models::ProfileResponse),
+        schemas(crate::services::actix_requests::models::SessionLoginResponse),
+        schemas(crate::services::actix_requests::models::TotpEnrollmentResponse),
+        schemas(crate::services::actix_requests::models::TotpCodeData),
+        schemas(crate::services::actix_requests::models::RecoveryCodesResponse),
+        schemas(crate::services::actix_requests::models::MfaChallengeResponse),
+        schemas(crate::services::actix_requests::models::MfaVerifyData),
+        schemas(crate::services::actix_requests::models::WebauthnLoginOptionsData),
+        schemas(crate::services::actix_requests::models::WebauthnCredentialResponse),
+        schemas(crate::services::actix_requests::models::LinkIdentityData),
+        schemas(crate::services::actix_requests::models::LinkIdentityResponse),
+        schemas(crate::services::actix_requests::models::LinkedIdentityResponse),
+        schemas(crate::services::actix_requests::models::PasswordlessStartData),
+        schemas(crate::services::actix_requests::models::PasswordlessVerifyData),
+        schemas(crate::services::actix_requests::models::CreateOauthClientData),
+        schemas(crate::services::actix_requests::models::UpdateOauthClientData),
+        schemas(crate::services::actix_requests::models::OauthClientResponse),
+        schemas(crate::services::actix_requests::models::CreateApiKeyData),
+        schemas(crate::services::actix_requests::models::ApiKeyResponse),
+        schemas(crate::services::actix_requests::models::SessionResponse),
+        schemas(crate::services::actix_requests::models::AuditEventResponse),
+        schemas(crate::services::oidc::models::TokenRequest),
+        schemas(crate::services::oidc::models::TokenResponse),
+        schemas(crate::services::oidc::models::DiscoveryDocument),
+        schemas(crate::services::oidc::models::IntrospectionRequest),
+        schemas(crate::services::oidc::models::IntrospectionResponse),
+        schemas(crate::services::oidc::models::RevocationRequest),
+        schemas(crate::services::health::models::LivenessResponse),
+        schemas(crate::services::health::models::ReadinessResponse),
+        schemas(crate::services::health::models::ReadinessComponents),
+        schemas(crate::services::health::models::ComponentHealth),
+        schemas(crate::services::health::models::PoolHealth),
+        schemas(crate::services::token::models::Jwk),
+        schemas(crate::services::token::models::JwkSet),
+        schemas(crate::services::webauthn::models::CreationOptions),
+        schemas(crate::services::webauthn::models::RequestOptions),
+        schemas(crate::services::webauthn::models::RelyingParty),
+        schemas(crate::services::webauthn::models::PublicKeyCredentialUser),
+        schemas(crate::services::webauthn::models::PublicKeyCredentialParameters),
+        schemas(crate::services::webauthn::models::PublicKeyCredentialDescriptor),
+        schemas(crate::services::webauthn::models::AuthenticatorSelection),
+        schemas(crate::services::webauthn::models::RegistrationCredential),
+        schemas(crate::services::webauthn::models::AttestationResponse),
+        schemas(crate::services::webauthn::models::AuthenticationCredential),
+        schemas(crate::services::webauthn::models::AssertionResponse),
     )
 )]
 struct ApiDoc;
@@ -36,48 +158,312 @@ struct ApiDoc;
 #[actix_web::main]
 async fn main() -> Result<()> {
     dotenv::dotenv().ok();
-    init_logging()?;
-    log::info!("Starting auth service...");
 
-    let opts = load_configurations()?;
+    let cli = Cli::parse();
+    let opts = load_configurations(cli.config)?;
 
-    let bind = opts.application.bind.clone();
+    let telemetry = init_tracing(&opts.logging, &opts.tracing)?;
+    tracing::info!("Starting auth service...");
 
-    let state = init_state(opts).await?;
+    if let Some(command) = cli.command {
+        let result = run_admin_command(opts, command).await;
+        telemetry.shutdown();
+        return result;
+    }
 
-    let server = actix_web::HttpServer::new(move || {
+    let application = opts.application.clone();
+    let metrics_bind = opts.metrics.bind.clone();
+    let tls = application.tls.as_ref().map(load_tls).transpose()?;
+
+    let (state, mut lifecycle) = init_state(opts).await?;
+
+    let tls = tls.map(|(config, reloader)| {
+        if let Some(reloader) = reloader {
+            lifecycle.add_job(reloader.start().recipient());
+        }
+        config
+    });
+    let result = serve(state, application, tls, metrics_bind).await;
+
+    lifecycle.shutdown().await;
+    telemetry.shutdown();
+    result
+}
+
+/// Runs the API and metrics listeners until a shutdown signal, then lets
+/// in-flight requests finish for up to `shutdown_timeout`.
+async fn serve(
+    state: AppState,
+    opts: ApplicationOpts,
+    tls: Option<ServerConfig>,
+    metrics_bind: String,
+) -> Result<()> {
+    let metrics = state.metrics.clone();
+    let metrics_server = actix_web::HttpServer::new(move || {
+        actix_web::App::new()
+            .app_data(Data::new(metrics.clone()))
+            .configure(configure_metrics_routes)
+    })
+    .workers(1)
+    .shutdown_timeout(opts.shutdown_timeout)
+    .disable_signals()
+    .bind(metrics_bind)?
+    .run();
+
+    let auth = AuthMiddleware::new(
+        state.auth0.decoding_key().clone(),
+        state.auth0.audience().to_string(),
+    );
+
+    let mut server = actix_web::HttpServer::new(move || {
         let state = state.clone();
 
+        // Every route is behind `AuthMiddleware`, `auth.public_paths` decides
+        // which ones can be called without credentials.
         actix_web::App::new()
+            .wrap(auth.clone())
+            .wrap(AuditMiddleware)
+            .wrap(TraceContextMiddleware)
+            .wrap(MetricsMiddleware)
+            .wrap(RequestIdMiddleware)
             .configure(configure_routes)
             .configure(configure_data(state))
     })
-    .bind(bind)?;
+    .keep_alive(match opts.keep_alive {
+        0 => KeepAlive::Disabled,
+        seconds => KeepAlive::Timeout(Duration::from_secs(seconds)),
+    })
+    .backlog(opts.backlog)
+    .shutdown_timeout(opts.shutdown_timeout)
+    // actix stops the whole system after its own signal handling, which would
+    // take down the actors that are still needed to shut down cleanly.
+    .disable_signals();
+    if let Some(workers) = opts.workers {
+        server = server.workers(workers);
+    }
+    let server = match tls {
+        Some(config) => server
+            .on_connect(record_client_certificate)
+            .bind_rustls_0_23(opts.bind, config)?,
+        None => server.bind(opts.bind)?,
+    }
+    .run();
+
+    let handle = server.handle();
+    let metrics_handle = metrics_server.handle();
+    actix::spawn(async move {
+        match shutdown_signal().await {
+            Ok(signal) => {
+                tracing::info!("Received {}, draining in-flight requests", signal);
+                futures_util::future::join(handle.stop(true), metrics_handle.stop(true)).await;
+            }
+            Err(e) => tracing::error!("Failed to listen for shutdown signals: {}", e),
+        }
+    });
 
-    server.run().await?;
+    futures_util::future::try_join(server, metrics_server).await?;
     Ok(())
 }
 
-async fn init_state(opts: Opts) -> Result<AppState> {
-    let pool = create_connection_pool(opts.database.database_url).await?;
-    let db = DbService::new(pool);
+async fn init_state(opts: Opts) -> Result<(AppState, Lifecycle)> {
+    let metrics = MetricsService::install()?;
+
+    let pool = create_connection_pool(opts.database.database_url.clone()).await?;
+    let db = DbService::new(pool.clone());
     let db = db.start();
 
-    let decoding_key = get_secret(&opts.auth0.dev_key_file);
+    let decoding_key = get_secret(&opts.auth0.dev_key_file)?;
+
+    let auth0 = Auth0Service::new(opts.auth0, decoding_key)?;
+
+    let health = HealthService::new(db.clone(), pool.clone(), auth0.clone(), opts.health);
+
+    let mfa = MfaService::new(
+        opts.mfa.issuer,
+        opts.mfa.recovery_codes,
+        opts.mfa.challenge_ttl,
+        opts.mfa.challenge_max_attempts,
+        mfa_cipher(&opts.token)?,
+    );
+
+    let oidc = OidcService::new(opts.token.issuer.clone(), opts.oidc, db.clone());
+
+    let client_cert_required = opts
+        .application
+        .tls
+        .as_ref()
+        .is_some_and(|tls| tls.client_ca_file.is_some());
+    let admin = AdminService::new(opts.admin.users, client_cert_required);
+
+    let api_keys = ApiKeyService::new(db.clone());
+
+    let sessions = SessionService::new(db.clone(), opts.session)?;
+
+    let public_paths = PublicPaths::new(opts.auth.public_paths)?;
+
+    let trusted_proxies = TrustedProxies::new(opts.application.trusted_proxies)?;
 
-    let auth0 = Auth0Service::new(
-        opts.auth0.client_id,
-        opts.auth0.client_secret,
-        opts.auth0.connection,
-        opts.auth0.client,
-        opts.auth0.audience,
-        decoding_key,
+    let audit = AuditService::new(Arc::new(DbAuditSink::new(db.clone())));
+
+    let (token_issuer, key_rotation) =
+        init_token_issuer(&db, &opts.database.database_url, opts.token).await?;
+
+    let webauthn = WebauthnService::new(
+        opts.webauthn.rp_id,
+        opts.webauthn.rp_name,
+        opts.webauthn.origin,
+        opts.webauthn.timeout,
+        opts.webauthn.user_verification,
+    );
+
+    // Background jobs start last, so a failed startup leaves none behind.
+    let mut jobs = vec![MetricsCollector::new(
+        metrics.clone(),
+        pool.clone(),
+        db.clone(),
+        opts.metrics.collect_interval,
+    )
+    .start()
+    .recipient()];
+    jobs.push(
+        PurgeService::new(db.clone(), opts.database.purge_interval)
+            .start()
+            .recipient(),
+    );
+    if let Some(key_rotation) = key_rotation {
+        jobs.push(key_rotation.start().recipient());
+    }
+
+    let lifecycle = Lifecycle::new(
+        jobs,
+        audit.clone(),
+        db.clone(),
+        pool,
+        opts.application.shutdown_timeout,
+    );
+
+    let state = AppState::new(
+        db,
+        auth0,
+        mfa,
+        token_issuer,
+        webauthn,
+        oidc,
+        admin,
+        api_keys,
+        sessions,
+        public_paths,
+        trusted_proxies,
+        metrics,
+        audit,
+        health,
     );
+    Ok((state, lifecycle))
+}
+
+/// The key TOTP secrets are encrypted with. Only development setups may run
+/// without one, with a key that is lost, along with every enrollment, on restart.
+fn mfa_cipher(opts: &TokenOpts) -> Result<KeyCipher> {
+    match &opts.key_encryption_key {
+        Some(key_encryption_key) => KeyCipher::from_base64(key_encryption_key),
+        None if opts.allow_ephemeral_key => {
+            tracing::warn!("No key encryption key configured, using an ephemeral MFA key");
+            let mut key = [0u8; KEY_ENCRYPTION_KEY_LEN];
+            openssl::rand::rand_bytes(&mut key)?;
+            Ok(KeyCipher::new(key))
+        }
+        None => Err(Error::InvalidInput(
+            "token.key_encryption_key is required to encrypt TOTP secrets".to_string(),
+        )),
+    }
+}
 
-    Ok(AppState::new(db, auth0))
+/// Returns the issuer and, when keys are kept in the database, the job that
+/// rotates them, not yet started.
+async fn init_token_issuer(
+    db: &Addr<DbService>,
+    database_url: &str,
+    opts: TokenOpts,
+) -> Result<(TokenIssuer, Option<KeyRotationService>)> {
+    let manager = match opts.private_key_file {
+        Some(_) => None,
+        None => key_manager(db, &opts)?,
+    };
+    if let Some(manager) = manager {
+        let keys = manager.run_rotation().await?;
+        let token_issuer =
+            TokenIssuer::with_key_store(opts.issuer, opts.audience, opts.access_token_ttl, keys);
+
+        let key_rotation = KeyRotationService::new(
+            manager,
+            token_issuer.clone(),
+            opts.rotation_check_interval,
+            database_url.to_string(),
+        );
+        return Ok((token_issuer, Some(key_rotation)));
+    }
+
+    let token_issuer = match &opts.private_key_file {
+        Some(path) => TokenIssuer::new(
+            opts.issuer,
+            opts.audience,
+            opts.kid,
+            opts.access_token_ttl,
+            &std::fs::read(path)?,
+        ),
+        None if opts.allow_ephemeral_key => {
+            tracing::warn!("No token private key configured, using an ephemeral signing key");
+            TokenIssuer::ephemeral(opts.issuer, opts.audience, opts.kid, opts.access_token_ttl)
+        }
+        None => Err(Error::InvalidInput(
+            "token.private_key_file or token.key_encryption_key is required".to_string(),
+        )),
+    }?;
+    Ok((token_issuer, None))
+}
+
+fn key_manager(db: &Addr<DbService>, opts: &TokenOpts) -> Result<Option<KeyManager>> {
+    let Some(key_encryption_key) = &opts.key_encryption_key else {
+        return Ok(None);
+    };
+    let cipher = KeyCipher::from_base64(key_encryption_key)?;
+
+    Ok(Some(KeyManager::new(db.clone(), cipher, opts)?))
+}
+
+async fn run_admin_command(opts: Opts, command: AdminCommand) -> Result<()> {
+    let pool = create_connection_pool(opts.database.database_url).await?;
+    let db = DbService::new(pool).start();
+
+    let manager = key_manager(&db, &opts.token)?.ok_or(Error::InvalidInput(
+        "token.key_encryption_key is required to manage signing keys".to_string(),
+    ))?;
+
+    match command {
+        AdminCommand::Keys(KeysCommand::List) => {
+            for key in manager.list().await? {
+                println!(
+                    "{}\t{}\t{}\tcreated {}",
+                    key.kid, key.alg, key.state, key.created_at
+                );
+            }
+        }
+        AdminCommand::Keys(KeysCommand::Rotate { alg }) => {
+            let kid = manager.rotate(alg.as_deref()).await?;
+            println!(
+                "Published signing key {}, it becomes active after the publication period",
+                kid
+            );
+        }
+        AdminCommand::Keys(KeysCommand::Revoke { kid }) => {
+            manager.revoke(&kid).await?;
+            println!("Revoked signing key {}", kid);
+        }
+    }
+
+    Ok(())
 }
 
-fn get_secret(path: &str) -> DecodingKey {
-    let pem_bytes = std::fs::read(path).expect("Failed to read PEM file");
-    DecodingKey::from_rsa_pem(&pem_bytes).expect("Failed to load key")
+fn get_secret(path: &str) -> Result<DecodingKey> {
+    Ok(DecodingKey::from_rsa_pem(&std::fs::read(path)?)?)
 }
@@ -0,0 +1,123 @@
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::middleware::request_id;
+use crate::middleware::trusted_proxies::TrustedProxies;
+use crate::services::audit::audit_service::{AuditService, AuditSubject};
+use crate::services::audit::consts::{
+    AUDITED_ROUTES, MAX_USER_AGENT_LEN, OUTCOME_FAILURE, OUTCOME_SUCCESS, SUBJECT_PARAMS,
+};
+use crate::services::audit::models::AuditEvent;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::USER_AGENT;
+use actix_web::web::Data;
+use actix_web::{Error, HttpMessage};
+use futures_util::future::{ok, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+/// Records an audit event for every request to one of `AUDITED_ROUTES`, once
+/// its outcome is known.
+pub struct AuditMiddleware;
+
+pub struct RecordAuditMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AuditMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RecordAuditMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RecordAuditMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RecordAuditMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            let res = service.call(req).await?;
+            if let Some(event) = audit_event(&res) {
+                if let Some(audit_service) = res.request().app_data::<Data<AuditService>>() {
+                    audit_service.record(event);
+                }
+            }
+            Ok(res)
+        })
+    }
+}
+
+fn audit_event<B>(res: &ServiceResponse<B>) -> Option<AuditEvent> {
+    let req = res.request();
+    let route = req.match_pattern()?;
+    let (_, _, action) = AUDITED_ROUTES
+        .iter()
+        .find(|(method, pattern, _)| *method == req.method().as_str() && *pattern == route)?;
+
+    let (outcome, reason) = if res.status().is_success() || res.status().is_redirection() {
+        (OUTCOME_SUCCESS, None)
+    } else {
+        let reason = res
+            .response()
+            .error()
+            .and_then(|error| error.as_error::<crate::errors::Error>())
+            .map(|error| error.code().as_str().to_string())
+            .unwrap_or_else(|| res.status().as_u16().to_string());
+        (OUTCOME_FAILURE, Some(reason))
+    };
+
+    let actor = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .map(|user| user.user_id.clone());
+    let subject = req
+        .extensions()
+        .get::<AuditSubject>()
+        .map(|subject| subject.0.clone())
+        .or_else(|| {
+            SUBJECT_PARAMS
+                .iter()
+                .find_map(|param| req.match_info().get(param))
+                .map(str::to_string)
+        })
+        .or_else(|| actor.clone());
+
+    Some(AuditEvent {
+        occurred_at: chrono::Utc::now(),
+        action,
+        outcome,
+        reason,
+        actor,
+        subject,
+        ip_address: TrustedProxies::client_ip(req),
+        user_agent: req
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|user_agent| user_agent.to_str().ok())
+            .map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_LEN).collect()),
+        request_id: request_id::current(),
+    })
+}
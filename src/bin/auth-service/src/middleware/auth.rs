@@ -1,21 +1,47 @@
+use crate::consts::AUTHORIZATION;
+use crate::errors::ProblemDetails;
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::middleware::public_paths::PublicPaths;
+use crate::services::actors::messages::IsTokenRevoked;
+use crate::services::actors::traced::TracedSend;
+use crate::services::api_keys::api_key_service::ApiKeyService;
+use crate::services::api_keys::consts::{API_KEY_AUTH_SCHEME, API_KEY_HEADER};
 use crate::services::auth0::models::Claims;
+use crate::services::db::postgres_db::DbService;
+use crate::services::oidc::oidc_service::OidcService;
+use crate::services::sessions::session_service::SessionService;
+use crate::services::token::token_issuer::TokenIssuer;
+use actix::Addr;
 use actix_web::body::BoxBody;
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
-use actix_web::{Error, HttpResponse};
+use actix_web::web::Data;
+use actix_web::{Error, HttpMessage};
 use futures_util::future::{ok, LocalBoxFuture, Ready};
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
-use std::fs::File;
-use std::io::Read;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use tokio::sync::RwLock;
 
-pub struct AuthMiddleware;
+/// Authenticates every request. Auth0 tokens are verified with the key and
+/// audience of the configured Auth0 tenant.
+#[derive(Clone)]
+pub struct AuthMiddleware {
+    decoding_key: Arc<DecodingKey>,
+    audience: String,
+}
+
+impl AuthMiddleware {
+    pub fn new(decoding_key: DecodingKey, audience: String) -> Self {
+        AuthMiddleware {
+            decoding_key: Arc::new(decoding_key),
+            audience,
+        }
+    }
+}
 
 pub struct CheckAuthMiddleware<S> {
     service: Rc<S>,
-    decoding_key: Arc<RwLock<DecodingKey>>,
+    decoding_key: Arc<DecodingKey>,
     audience: String,
 }
 
@@ -31,15 +57,10 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        let jwt_secret =
-            std::env::var("AUTH0_DEV_KEY_FILE").unwrap_or_else(|_| "secret".to_string());
-        let audience = std::env::var("AUTH0_AUDIENCE")
-            .unwrap_or_else(|_| "https://someexample.com".to_string());
-        let decoding_key = AuthMiddleware::new_from_file(&jwt_secret).expect("Failed to load key");
         ok(CheckAuthMiddleware {
             service: Rc::new(service),
-            decoding_key: Arc::new(RwLock::new(decoding_key)),
-            audience,
+            decoding_key: self.decoding_key.clone(),
+            audience: self.audience.clone(),
         })
     }
 }
@@ -64,48 +85,135 @@ where
         let audience = self.audience.clone();
 
         Box::pin(async move {
-            let auth_header = req.headers().get("Authorization");
-
-            if req.path() == "/login" || req.path() == "/register" || req.path() == "/" {
-                return Ok(req.into_response(HttpResponse::Ok().finish()));
+            let is_public = req
+                .app_data::<Data<PublicPaths>>()
+                .is_some_and(|public_paths| public_paths.is_public(req.method(), req.path()));
+
+            match authenticate(&req, &decoding_key, audience).await {
+                Ok(Some(user)) => {
+                    req.extensions_mut().insert(user);
+                    service.call(req).await
+                }
+                Ok(None) if is_public => service.call(req).await,
+                Err(e) if is_public => {
+                    tracing::debug!("Ignoring invalid credentials on public path: {}", e);
+                    service.call(req).await
+                }
+                Ok(None) => Ok(req.into_response(ProblemDetails::response_from(
+                    &crate::errors::Error::Unauthorized,
+                ))),
+                Err(e) => {
+                    tracing::error!("Unauthorized access: {}", e);
+                    Ok(req.into_response(ProblemDetails::response_from(
+                        &crate::errors::Error::Unauthorized,
+                    )))
+                }
             }
+        })
+    }
+}
 
-            if let Some(auth_header) = auth_header {
-                if let Ok(auth_token) = auth_header.to_str() {
-                    if let Some(auth_str) = auth_token.strip_prefix("Bearer ") {
-                        let token = auth_str;
-
-                        let decoding_key = decoding_key.read().await;
-
-                        let validation = &mut Validation::new(Algorithm::RS256);
-
-                        validation.set_audience(&[audience]);
+/// Resolves the caller from an API key, a bearer token or a session cookie, in
+/// that order. Returns `None` when the request carries no credentials.
+async fn authenticate(
+    req: &ServiceRequest,
+    decoding_key: &DecodingKey,
+    audience: String,
+) -> crate::errors::Result<Option<AuthenticatedUser>> {
+    if let Some(api_key) = api_key(req) {
+        let api_key_service = req
+            .app_data::<Data<ApiKeyService>>()
+            .ok_or(crate::errors::Error::Unauthorized)?;
+        return api_key_service.authenticate(&api_key).await.map(Some);
+    }
 
-                        return match decode::<Claims>(token, &decoding_key, validation) {
-                            Ok(_) => service.call(req).await,
-                            Err(e) => {
-                                log::error!("Unauthorized access: {}", e);
-                                Ok(req.into_response(HttpResponse::Unauthorized().finish()))
-                            }
+    if let Some(token) = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|auth_header| auth_header.to_str().ok())
+        .and_then(|auth_token| auth_token.strip_prefix("Bearer "))
+    {
+        let validation = &mut Validation::new(Algorithm::RS256);
+
+        validation.set_audience(&[audience]);
+
+        let (user, jti) = decode::<Claims>(token, decoding_key, validation)
+            .map(|data| {
+                // Auth0 only issues tokens for our audience to this service's
+                // own logins, so they act with all of the user's rights.
+                let user = AuthenticatedUser {
+                    user_id: data.claims.sub.trim_start_matches("auth0|").to_string(),
+                    client_id: None,
+                    scope: None,
+                    api_key_id: None,
+                    session_id: None,
+                };
+                (user, data.claims.jti)
+            })
+            .or_else(|e| match req.app_data::<Data<TokenIssuer>>() {
+                Some(token_issuer) => token_issuer
+                    .verify(token)
+                    .map(|claims| {
+                        let user = AuthenticatedUser {
+                            user_id: claims.sub,
+                            client_id: claims.client_id,
+                            scope: claims.scope,
+                            api_key_id: None,
+                            session_id: None,
                         };
-                    }
-                }
-            }
+                        (user, Some(claims.jti))
+                    })
+                    .map_err(|_| e),
+                None => Err(e),
+            })?;
+
+        if is_revoked(req, jti.as_deref(), token).await {
+            return Err(crate::errors::Error::StringError(
+                "token was revoked".to_string(),
+            ));
+        }
+        return Ok(Some(user));
+    }
 
-            Ok(req.into_response(HttpResponse::Unauthorized().finish()))
-        })
+    if let Some(session_service) = req.app_data::<Data<SessionService>>() {
+        if let Some(cookie) = req.cookie(session_service.cookie_name()) {
+            return session_service.authenticate(cookie.value()).await.map(Some);
+        }
     }
-}
 
-impl AuthMiddleware {
-    pub fn new_from_file(path: &str) -> Result<DecodingKey, std::io::Error> {
-        let mut file = File::open(path)?;
-        let mut key_data = Vec::new();
-        file.read_to_end(&mut key_data)?;
+    Ok(None)
+}
 
-        let decoding_key =
-            DecodingKey::from_rsa_pem(key_data.as_slice()).expect("Failed to load key");
+/// Returns the API key from the `X-API-Key` header or an `ApiKey` authorization.
+pub(crate) fn api_key(req: &ServiceRequest) -> Option<String> {
+    if let Some(key) = req.headers().get(API_KEY_HEADER) {
+        return key.to_str().ok().map(str::to_string);
+    }
+    req.headers()
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix(API_KEY_AUTH_SCHEME)
+        .map(str::to_string)
+}
 
-        Ok(decoding_key)
+/// Checks the token against the denylist fed by `/oauth/revoke`. Fails closed
+/// when the database can't be asked.
+async fn is_revoked(req: &ServiceRequest, jti: Option<&str>, token: &str) -> bool {
+    let Some(db) = req.app_data::<Data<Addr<DbService>>>() else {
+        return false;
+    };
+    let token_id = OidcService::revocation_id(jti, token);
+
+    match db.send_traced(IsTokenRevoked { token_id }).await {
+        Ok(Ok(revoked)) => revoked,
+        Ok(Err(e)) => {
+            tracing::error!("Failed to check token revocation: {}", e);
+            true
+        }
+        Err(e) => {
+            tracing::error!("Failed to reach the database actor: {}", e);
+            true
+        }
     }
 }
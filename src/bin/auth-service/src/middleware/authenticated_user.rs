@@ -0,0 +1,123 @@
+use crate::errors::{Error, Result};
+use crate::services::oidc::consts::CLIENT_SUBJECT_PREFIX;
+use crate::services::oidc::oidc_service::has_scope;
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::{ready, Ready};
+use uuid::Uuid;
+
+/// The caller of a route behind `AuthMiddleware`, whether it presented a bearer
+/// token or an API key.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    /// The local user id, or `client:` and the client id for tokens and keys
+    /// owned by an OAuth client.
+    pub user_id: String,
+    pub client_id: Option<String>,
+    /// Space separated scopes the credential is limited to. `None` for logins
+    /// and sessions, which act with all of the user's rights.
+    pub scope: Option<String>,
+    /// Set when the request was authenticated with an API key.
+    pub api_key_id: Option<Uuid>,
+    /// Set when the request was authenticated with a session cookie.
+    pub session_id: Option<Uuid>,
+}
+
+impl AuthenticatedUser {
+    /// Whether the caller is an OAuth client acting for itself rather than a user.
+    pub fn is_client(&self) -> bool {
+        self.user_id.starts_with(CLIENT_SUBJECT_PREFIX)
+    }
+
+    /// Rejects OAuth clients on routes that act on the caller's own account.
+    pub fn require_user(&self) -> Result<()> {
+        match self.is_client() {
+            true => Err(Error::Forbidden),
+            false => Ok(()),
+        }
+    }
+
+    /// Rejects credentials limited to scopes that don't include `scope`.
+    pub fn require_scope(&self, scope: &str) -> Result<()> {
+        match &self.scope {
+            Some(scopes) if !has_scope(scopes, scope) => Err(Error::Forbidden),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rejects API keys on routes that change how the account authenticates,
+    /// so a leaked key can't be turned into a login.
+    pub fn reject_api_key(&self) -> Result<()> {
+        match self.api_key_id {
+            Some(_) => Err(Error::Forbidden),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = Error;
+    type Future = Ready<Result<Self>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthenticatedUser>()
+                .cloned()
+                .ok_or(Error::Unauthorized),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caller(user_id: &str, client_id: Option<&str>) -> AuthenticatedUser {
+        AuthenticatedUser {
+            user_id: user_id.to_string(),
+            client_id: client_id.map(str::to_string),
+            scope: None,
+            api_key_id: None,
+            session_id: None,
+        }
+    }
+
+    #[test]
+    fn tells_clients_from_users() {
+        let cases = [
+            ("user", None, false),
+            ("user", Some("app"), false),
+            ("client:app", Some("app"), true),
+            ("client:app", None, true),
+        ];
+        for (user_id, client_id, is_client) in cases {
+            let caller = caller(user_id, client_id);
+            assert_eq!(caller.is_client(), is_client, "{} {:?}", user_id, client_id);
+            assert_eq!(caller.require_user().is_err(), is_client);
+        }
+    }
+
+    #[test]
+    fn limits_scoped_credentials() {
+        let cases = [
+            (None, "user:write", true),
+            (Some("user:read user:write"), "user:write", true),
+            (Some("user:read"), "user:write", false),
+            (Some("openid profile"), "user:read", false),
+            (Some("user:writer"), "user:write", false),
+            (Some(""), "admin", false),
+        ];
+        for (scope, required, allowed) in cases {
+            let mut caller = caller("user", None);
+            caller.scope = scope.map(str::to_string);
+            assert_eq!(
+                caller.require_scope(required).is_ok(),
+                allowed,
+                "{:?} {}",
+                scope,
+                required
+            );
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::errors::ProblemDetails;
+use crate::services::admin::admin_service::AdminService;
+use crate::tls::ClientCertificate;
+use actix_web::body::BoxBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::web::Data;
+use actix_web::Error;
+use futures_util::future::{ok, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+/// Rejects requests whose connection didn't present a verified client
+/// certificate, when the admin endpoints are configured to require one.
+pub struct ClientCertMiddleware;
+
+pub struct CheckClientCertMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S> Transform<S, ServiceRequest> for ClientCertMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = CheckClientCertMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CheckClientCertMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+impl<S> Service<ServiceRequest> for CheckClientCertMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            let required = req
+                .app_data::<Data<AdminService>>()
+                .is_some_and(|admin_service| admin_service.client_cert_required());
+
+            if required && req.conn_data::<ClientCertificate>().is_none() {
+                tracing::error!("Rejected admin request without a client certificate");
+                return Ok(req.into_response(ProblemDetails::response_from(
+                    &crate::errors::Error::Forbidden,
+                )));
+            }
+            service.call(req).await
+        })
+    }
+}
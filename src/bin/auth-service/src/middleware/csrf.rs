@@ -0,0 +1,107 @@
+use crate::consts::AUTHORIZATION;
+use crate::errors::ProblemDetails;
+use crate::middleware::auth::api_key;
+use crate::services::sessions::consts::CSRF_HEADER;
+use crate::services::sessions::session_service::SessionService;
+use actix_web::body::BoxBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::web::Data;
+use actix_web::Error;
+use futures_util::future::{ok, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+/// Requires the session's CSRF token in `X-CSRF-Token` on unsafe requests that
+/// are authenticated by the session cookie. Bearer and API key requests can't
+/// be forged by another site, so they pass through.
+pub struct CsrfMiddleware;
+
+pub struct CheckCsrfMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S> Transform<S, ServiceRequest> for CsrfMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = CheckCsrfMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CheckCsrfMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+impl<S> Service<ServiceRequest> for CheckCsrfMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            if is_safe(req.method()) || !is_cookie_authenticated(&req) {
+                return service.call(req).await;
+            }
+
+            let Some(session_service) = req.app_data::<Data<SessionService>>().cloned() else {
+                return service.call(req).await;
+            };
+            let Some(session) = req.cookie(session_service.cookie_name()) else {
+                return service.call(req).await;
+            };
+
+            let csrf_token = req
+                .headers()
+                .get(CSRF_HEADER)
+                .and_then(|csrf_token| csrf_token.to_str().ok());
+
+            match csrf_token {
+                Some(csrf_token) if SessionService::verify_csrf(session.value(), csrf_token) => {
+                    service.call(req).await
+                }
+                _ => {
+                    tracing::error!("Rejected request without a valid CSRF token");
+                    Ok(req.into_response(ProblemDetails::response_from(
+                        &crate::errors::Error::Forbidden,
+                    )))
+                }
+            }
+        })
+    }
+}
+
+fn is_safe(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Mirrors `AuthMiddleware`, which only falls back to the session cookie when
+/// the request carries neither an API key nor a bearer token.
+fn is_cookie_authenticated(req: &ServiceRequest) -> bool {
+    let has_bearer = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .is_some_and(|header| header.starts_with("Bearer "));
+
+    api_key(req).is_none() && !has_bearer
+}
@@ -0,0 +1,123 @@
+use crate::services::metrics::consts::{
+    AUTH_LOGINS_TOTAL, AUTH_REGISTRATIONS_TOTAL, HTTP_REQUESTS_TOTAL,
+    HTTP_REQUEST_DURATION_SECONDS, LOGIN_ROUTES, REGISTRATION_ROUTE,
+};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures_util::future::{ok, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Records request counts and latencies per route and status, and the
+/// outcome of logins and registrations.
+pub struct MetricsMiddleware;
+
+pub struct RecordMetricsMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for MetricsMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RecordMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RecordMetricsMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RecordMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let started = Instant::now();
+
+        Box::pin(async move {
+            let res = service.call(req).await?;
+            record(&res, started);
+            Ok(res)
+        })
+    }
+}
+
+fn record<B>(res: &ServiceResponse<B>, started: Instant) {
+    // The route pattern rather than the path, so ids don't explode the
+    // label cardinality.
+    let route = res
+        .request()
+        .match_pattern()
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let method = res.request().method().to_string();
+    let status = res.status();
+
+    metrics::counter!(
+        HTTP_REQUESTS_TOTAL,
+        "method" => method.clone(),
+        "route" => route.clone(),
+        "status" => status.as_u16().to_string()
+    )
+    .increment(1);
+    metrics::histogram!(
+        HTTP_REQUEST_DURATION_SECONDS,
+        "method" => method,
+        "route" => route.clone(),
+        "status" => status.as_u16().to_string()
+    )
+    .record(started.elapsed().as_secs_f64());
+
+    let (outcome, reason) = if status.is_success() || status.is_redirection() {
+        ("success", "none".to_string())
+    } else {
+        ("failure", reason(res))
+    };
+
+    if let Some((_, login_method)) = LOGIN_ROUTES.iter().find(|(path, _)| *path == route) {
+        metrics::counter!(
+            AUTH_LOGINS_TOTAL,
+            "method" => *login_method,
+            "outcome" => outcome,
+            "reason" => reason
+        )
+        .increment(1);
+    } else if route == REGISTRATION_ROUTE {
+        metrics::counter!(
+            AUTH_REGISTRATIONS_TOTAL,
+            "outcome" => outcome,
+            "reason" => reason
+        )
+        .increment(1);
+    }
+}
+
+/// The error code of a failed response, as sent in the problem details.
+fn reason<B>(res: &ServiceResponse<B>) -> String {
+    res.response()
+        .error()
+        .and_then(|error| error.as_error::<crate::errors::Error>())
+        .map(|error| error.code().as_str().to_string())
+        .unwrap_or_else(|| res.status().as_u16().to_string())
+}
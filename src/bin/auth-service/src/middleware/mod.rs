@@ -1 +1,10 @@
+pub mod audit;
 pub mod auth;
+pub mod authenticated_user;
+pub mod client_cert;
+pub mod csrf;
+pub mod metrics;
+pub mod public_paths;
+pub mod request_id;
+pub mod trace_context;
+pub mod trusted_proxies;
@@ -0,0 +1,200 @@
+use crate::errors::{Error, Result};
+use crate::opts::cmd_opts::PublicPathOpts;
+use actix_web::http::Method;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The paths `AuthMiddleware` lets through without credentials.
+#[derive(Clone)]
+pub struct PublicPaths {
+    paths: Arc<Vec<PublicPath>>,
+}
+
+struct PublicPath {
+    segments: Vec<String>,
+    methods: Vec<Method>,
+}
+
+impl PublicPaths {
+    pub fn new(paths: Vec<PublicPathOpts>) -> Result<Self> {
+        let paths = paths
+            .into_iter()
+            .map(|path| {
+                let methods = path
+                    .methods
+                    .iter()
+                    .map(|method| {
+                        Method::from_str(&method.to_uppercase()).map_err(|_| {
+                            Error::InvalidInput(format!("Unknown HTTP method {}", method))
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok(PublicPath {
+                    segments: segments(&path.pattern).map(str::to_string).collect(),
+                    methods,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(PublicPaths {
+            paths: Arc::new(paths),
+        })
+    }
+
+    pub fn is_public(&self, method: &Method, path: &str) -> bool {
+        let path = segments(path).collect::<Vec<_>>();
+        self.paths.iter().any(|public| {
+            (public.methods.is_empty() || public.methods.contains(method))
+                && matches_segments(&public.segments, &path)
+        })
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+fn matches_segments(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| matches_segments(rest, &path[skip..]))
+        }
+        Some((first, rest)) => match path.split_first() {
+            Some((segment, path)) => {
+                matches_segment(first.as_bytes(), segment.as_bytes())
+                    && matches_segments(rest, path)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Matches one path segment against a pattern where `*` stands for any run of
+/// characters.
+fn matches_segment(pattern: &[u8], segment: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => segment.is_empty(),
+        Some((b'*', rest)) => {
+            (0..=segment.len()).any(|skip| matches_segment(rest, &segment[skip..]))
+        }
+        Some((first, rest)) => {
+            segment.first() == Some(first) && matches_segment(rest, &segment[1..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opts::cmd_opts::AuthOpts;
+
+    fn public_paths(patterns: &[(&str, &[&str])]) -> Result<PublicPaths> {
+        PublicPaths::new(
+            patterns
+                .iter()
+                .map(|(pattern, methods)| PublicPathOpts {
+                    pattern: pattern.to_string(),
+                    methods: methods.iter().map(|method| method.to_string()).collect(),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn matches_patterns() -> Result<()> {
+        let cases: &[(&str, Method, &str, bool)] = &[
+            ("/login", Method::POST, "/login", true),
+            ("/login", Method::POST, "/login/", true),
+            ("/login", Method::POST, "//login", true),
+            ("/login", Method::POST, "/login/mfa", false),
+            ("/login", Method::POST, "/logins", false),
+            ("/login", Method::POST, "/", false),
+            ("/", Method::GET, "/", true),
+            ("/", Method::GET, "/login", false),
+            ("/oauth/*", Method::POST, "/oauth/token", true),
+            ("/oauth/*", Method::POST, "/oauth", false),
+            ("/oauth/*", Method::POST, "/oauth/token/extra", false),
+            ("/files/*.json", Method::GET, "/files/openapi.json", true),
+            ("/files/*.json", Method::GET, "/files/openapi.yaml", false),
+            ("/files/a*b*c", Method::GET, "/files/abbc", true),
+            ("/files/a*b*c", Method::GET, "/files/acb", false),
+            ("/docs/**", Method::GET, "/docs", true),
+            ("/docs/**", Method::GET, "/docs/a/b/c", true),
+            ("/docs/**", Method::GET, "/documents", false),
+            ("/**/health", Method::GET, "/health", true),
+            ("/**/health", Method::GET, "/a/b/health", true),
+            ("/**/health", Method::GET, "/a/b/health/x", false),
+        ];
+
+        for (pattern, method, path, public) in cases {
+            let paths = public_paths(&[(pattern, &[])])?;
+            assert_eq!(
+                paths.is_public(method, path),
+                *public,
+                "{} {}",
+                pattern,
+                path
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn matches_methods() -> Result<()> {
+        let paths = public_paths(&[("/login", &["post"]), ("/healthz", &[])])?;
+        let cases = [
+            (Method::POST, "/login", true),
+            (Method::GET, "/login", false),
+            (Method::GET, "/healthz", true),
+            (Method::DELETE, "/healthz", true),
+        ];
+
+        for (method, path, public) in cases {
+            assert_eq!(
+                paths.is_public(&method, path),
+                public,
+                "{} {}",
+                method,
+                path
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_methods() {
+        assert!(PublicPaths::new(vec![PublicPathOpts {
+            pattern: "/login".to_string(),
+            methods: vec!["not a method".to_string()],
+        }])
+        .is_err());
+    }
+
+    #[test]
+    fn defaults_keep_accounts_private() -> Result<()> {
+        let paths = PublicPaths::new(AuthOpts::default().public_paths)?;
+        let cases = [
+            (Method::POST, "/login", true),
+            (Method::POST, "/oauth/token", true),
+            (Method::GET, "/.well-known/jwks.json", true),
+            (Method::GET, "/swagger-ui/index.html", true),
+            (Method::GET, "/user/profile", false),
+            (Method::POST, "/user/api_keys", false),
+            (Method::GET, "/admin/clients", false),
+            (Method::GET, "/unknown", false),
+        ];
+
+        for (method, path, public) in cases {
+            assert_eq!(
+                paths.is_public(&method, path),
+                public,
+                "{} {}",
+                method,
+                path
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use crate::middleware::request_id;
+use crate::telemetry::extract_context;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures_util::future::{ok, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use tracing::field::Empty;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Runs every request in a span that continues the caller's W3C trace
+/// context, so it joins the caller's trace when one was sent.
+pub struct TraceContextMiddleware;
+
+pub struct RequestSpanMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for TraceContextMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestSpanMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestSpanMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RequestSpanMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+
+        // The route is only known once the request has been matched, so the
+        // span is renamed after the response.
+        let span = tracing::info_span!(
+            "http_request",
+            otel.name = %req.method(),
+            otel.kind = "server",
+            otel.status_code = Empty,
+            http.request.method = %req.method(),
+            http.route = Empty,
+            http.response.status_code = Empty,
+            url.path = %req.path(),
+            request_id = request_id::current().as_deref(),
+        );
+        // Fails only when spans aren't exported, which leaves nothing to join.
+        let _ = span.set_parent(extract_context(req.headers()));
+
+        let future = span.in_scope(|| service.call(req));
+        Box::pin(
+            async move {
+                let res = future.await?;
+
+                let span = tracing::Span::current();
+                if let Some(route) = res.request().match_pattern() {
+                    span.record("otel.name", format!("{} {}", res.request().method(), route));
+                    span.record("http.route", route);
+                }
+                span.record("http.response.status_code", res.status().as_u16());
+                if res.status().is_server_error() {
+                    span.record("otel.status_code", "ERROR");
+                }
+                Ok(res)
+            }
+            .instrument(span),
+        )
+    }
+}
@@ -0,0 +1,162 @@
+use crate::errors::{Error, Result};
+use actix_web::web::Data;
+use actix_web::HttpRequest;
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+pub const X_FORWARDED_FOR: &str = "X-Forwarded-For";
+
+/// The proxies in front of the service, whose `X-Forwarded-For` is believed
+/// when recording where a request came from.
+#[derive(Clone)]
+pub struct TrustedProxies {
+    networks: Arc<Vec<IpNet>>,
+}
+
+impl TrustedProxies {
+    pub fn new(proxies: Vec<String>) -> Result<Self> {
+        let networks = proxies
+            .iter()
+            .map(|proxy| {
+                proxy
+                    .parse::<IpNet>()
+                    .or_else(|_| proxy.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| Error::InvalidInput(format!("Invalid trusted proxy {}", proxy)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(TrustedProxies {
+            networks: Arc::new(networks),
+        })
+    }
+
+    /// The address of the client, the peer itself unless it is a trusted
+    /// proxy.
+    pub fn client_ip(req: &HttpRequest) -> Option<String> {
+        let peer = req.peer_addr()?.ip();
+        let client = match req.app_data::<Data<TrustedProxies>>() {
+            Some(proxies) => proxies.resolve(peer, req),
+            None => peer,
+        };
+        Some(client.to_string())
+    }
+
+    /// Each proxy appends the address it got the request from, so the list is
+    /// walked back from the peer for as long as the hops are trusted. Anything
+    /// before the first untrusted hop may have been made up by the client.
+    fn resolve(&self, peer: IpAddr, req: &HttpRequest) -> IpAddr {
+        let forwarded = req
+            .headers()
+            .get_all(X_FORWARDED_FOR)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>();
+
+        let mut client = peer;
+        for hop in forwarded.iter().rev() {
+            if !self.is_trusted(client) {
+                break;
+            }
+            match hop
+                .parse::<IpAddr>()
+                .or_else(|_| hop.parse::<SocketAddr>().map(|addr| addr.ip()))
+            {
+                Ok(hop) => client = hop,
+                Err(_) => break,
+            }
+        }
+        client
+    }
+
+    fn is_trusted(&self, addr: IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(&addr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn resolves_client_behind_trusted_proxies() -> Result<()> {
+        let cases = [
+            // No proxies configured, the header is ignored.
+            (vec![], "203.0.113.9", Some("198.51.100.1"), "203.0.113.9"),
+            // Untrusted peer, the header is ignored.
+            (
+                vec!["10.0.0.0/8"],
+                "203.0.113.9",
+                Some("198.51.100.1"),
+                "203.0.113.9",
+            ),
+            (
+                vec!["10.0.0.0/8"],
+                "10.0.0.2",
+                Some("198.51.100.1"),
+                "198.51.100.1",
+            ),
+            (vec!["10.0.0.2"], "10.0.0.2", None, "10.0.0.2"),
+            // Entries before the first untrusted hop are the client's own.
+            (
+                vec!["10.0.0.0/8"],
+                "10.0.0.2",
+                Some("192.0.2.7, 198.51.100.1, 10.0.0.3"),
+                "198.51.100.1",
+            ),
+            (
+                vec!["10.0.0.0/8"],
+                "10.0.0.2",
+                Some("10.0.0.4, 10.0.0.3"),
+                "10.0.0.4",
+            ),
+            (
+                vec!["10.0.0.0/8"],
+                "10.0.0.2",
+                Some("garbage, 10.0.0.3"),
+                "10.0.0.3",
+            ),
+            (
+                vec!["10.0.0.0/8"],
+                "10.0.0.2",
+                Some("198.51.100.1:4711"),
+                "198.51.100.1",
+            ),
+            (
+                vec!["fd00::/8"],
+                "fd00::1",
+                Some("2001:db8::1"),
+                "2001:db8::1",
+            ),
+        ];
+
+        for (proxies, peer, forwarded, client) in cases {
+            let proxies = TrustedProxies::new(proxies.into_iter().map(str::to_string).collect())?;
+            let peer_ip = peer
+                .parse()
+                .map_err(|_| Error::InvalidInput(format!("Invalid peer {}", peer)))?;
+            let mut req = TestRequest::default()
+                .peer_addr(SocketAddr::new(peer_ip, 443))
+                .app_data(Data::new(proxies));
+            if let Some(forwarded) = forwarded {
+                req = req.insert_header((X_FORWARDED_FOR, forwarded));
+            }
+            assert_eq!(
+                TrustedProxies::client_ip(&req.to_http_request()).as_deref(),
+                Some(client),
+                "{} {:?}",
+                peer,
+                forwarded
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_proxies() {
+        assert!(TrustedProxies::new(vec!["10.0.0.0/33".to_string()]).is_err());
+        assert!(TrustedProxies::new(vec!["proxy.internal".to_string()]).is_err());
+    }
+}
@@ -1,15 +1,70 @@
+use crate::middleware::public_paths::PublicPaths;
+use crate::middleware::trusted_proxies::TrustedProxies;
+use crate::services::admin::admin_service::AdminService;
+use crate::services::api_keys::api_key_service::ApiKeyService;
+use crate::services::audit::audit_service::AuditService;
 use crate::services::auth0::auth0_service::Auth0Service;
 use crate::services::db::postgres_db::DbService;
+use crate::services::health::health_service::HealthService;
+use crate::services::metrics::metrics_service::MetricsService;
+use crate::services::mfa::mfa_service::MfaService;
+use crate::services::oidc::oidc_service::OidcService;
+use crate::services::sessions::session_service::SessionService;
+use crate::services::token::token_issuer::TokenIssuer;
+use crate::services::webauthn::webauthn_service::WebauthnService;
 use actix::Addr;
 
 #[derive(Clone)]
 pub struct AppState {
     pub database: Addr<DbService>,
     pub auth0: Auth0Service,
+    pub mfa: MfaService,
+    pub token_issuer: TokenIssuer,
+    pub webauthn: WebauthnService,
+    pub oidc: OidcService,
+    pub admin: AdminService,
+    pub api_keys: ApiKeyService,
+    pub sessions: SessionService,
+    pub public_paths: PublicPaths,
+    pub trusted_proxies: TrustedProxies,
+    pub metrics: MetricsService,
+    pub audit: AuditService,
+    pub health: HealthService,
 }
 
 impl AppState {
-    pub fn new(database: Addr<DbService>, auth0: Auth0Service) -> Self {
-        Self { database, auth0 }
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        database: Addr<DbService>,
+        auth0: Auth0Service,
+        mfa: MfaService,
+        token_issuer: TokenIssuer,
+        webauthn: WebauthnService,
+        oidc: OidcService,
+        admin: AdminService,
+        api_keys: ApiKeyService,
+        sessions: SessionService,
+        public_paths: PublicPaths,
+        trusted_proxies: TrustedProxies,
+        metrics: MetricsService,
+        audit: AuditService,
+        health: HealthService,
+    ) -> Self {
+        Self {
+            database,
+            auth0,
+            mfa,
+            token_issuer,
+            webauthn,
+            oidc,
+            admin,
+            api_keys,
+            sessions,
+            public_paths,
+            trusted_proxies,
+            metrics,
+            audit,
+            health,
+        }
     }
 }
@@ -1,40 +1,145 @@
 use crate::errors::Result;
+use clap::{Parser, Subcommand};
 use config::{Config, Environment, File};
 use serde::Deserialize;
-use std::env;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+#[derive(Debug, Parser)]
+#[command(about = "Authentication service")]
+pub struct Cli {
+    /// Path to the configuration file.
+    pub config: PathBuf,
+    /// Run an admin command instead of starting the server.
+    #[command(subcommand)]
+    pub command: Option<AdminCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminCommand {
+    /// Manage the keys tokens are signed with.
+    #[command(subcommand)]
+    Keys(KeysCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeysCommand {
+    /// List signing keys that are not revoked.
+    List,
+    /// Publish a new key, which replaces the active one after
+    /// `token.publication_period`.
+    Rotate {
+        /// RS256, ES256 or EdDSA. Defaults to `token.signing_algorithm`.
+        #[arg(long)]
+        alg: Option<String>,
+    },
+    /// Revoke a key immediately, replacing it right away if it is the active key.
+    Revoke { kid: String },
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Opts {
     pub application: ApplicationOpts,
     pub database: DatabaseOpts,
     pub auth0: Auth0Opts,
+    #[serde(default)]
+    pub mfa: MfaOpts,
+    #[serde(default)]
+    pub token: TokenOpts,
+    #[serde(default)]
+    pub webauthn: WebauthnOpts,
+    #[serde(default)]
+    pub oidc: OidcOpts,
+    #[serde(default)]
+    pub admin: AdminOpts,
+    #[serde(default)]
+    pub session: SessionOpts,
+    #[serde(default)]
+    pub auth: AuthOpts,
+    #[serde(default)]
+    pub metrics: MetricsOpts,
+    #[serde(default)]
+    pub tracing: TracingOpts,
+    #[serde(default)]
+    pub logging: LoggingOpts,
+    #[serde(default)]
+    pub health: HealthOpts,
 }
 
-impl Opts {
-    pub fn get_from_args() -> PathBuf {
-        let args: Vec<String> = env::args().collect();
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApplicationOpts {
+    pub bind: String,
+    /// Worker threads, one per available CPU when unset.
+    #[serde(default)]
+    pub workers: Option<usize>,
+    /// Seconds an idle connection is kept open, 0 to close it after each request.
+    #[serde(default = "default_keep_alive")]
+    pub keep_alive: u64,
+    /// Connections waiting to be accepted before new ones are refused.
+    #[serde(default = "default_backlog")]
+    pub backlog: u32,
+    /// Seconds in-flight requests get to finish on shutdown, and again for
+    /// queued audit events to be written.
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout: u64,
+    /// Serves HTTPS, and HTTP/2 through ALPN, instead of plain HTTP.
+    #[serde(default)]
+    pub tls: Option<TlsOpts>,
+    /// Addresses or CIDR ranges of the proxies in front of the service. The
+    /// client address is taken from their `X-Forwarded-For`.
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+}
 
-        if args.len() < 2 {
-            eprintln!("Usage: cargo run --release <config_file>");
-            std::process::exit(1);
-        }
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsOpts {
+    /// PEM certificate chain, leaf first.
+    pub cert_file: String,
+    /// PEM private key, PKCS#8, PKCS#1 or SEC1.
+    pub key_file: String,
+    /// `1.2` or `1.3`.
+    #[serde(default = "default_tls_min_version")]
+    pub min_version: String,
+    /// Seconds between checks whether the certificate or key changed on
+    /// disk, 0 to never reload them.
+    #[serde(default = "default_tls_reload_interval")]
+    pub reload_interval: u64,
+    /// PEM CA certificates to verify client certificates against. When set,
+    /// clients may present one and the `/admin` endpoints require it.
+    #[serde(default)]
+    pub client_ca_file: Option<String>,
+}
 
-        let config_path = &args[1];
-        println!("Using config file: {}", config_path);
+fn default_tls_min_version() -> String {
+    "1.2".to_string()
+}
 
-        PathBuf::from(config_path)
-    }
+fn default_tls_reload_interval() -> u64 {
+    60
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ApplicationOpts {
-    pub bind: String,
+fn default_keep_alive() -> u64 {
+    5
+}
+
+fn default_backlog() -> u32 {
+    2048
+}
+
+fn default_shutdown_timeout() -> u64 {
+    30
 }
 
 #[derive(Debug, Deserialize)]
 pub struct DatabaseOpts {
     pub database_url: String,
+    /// Seconds between purges of expired tokens, sessions and challenges.
+    #[serde(default = "default_purge_interval")]
+    pub purge_interval: u64,
+}
+
+fn default_purge_interval() -> u64 {
+    3600
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,12 +148,348 @@ pub struct Auth0Opts {
     pub client: String,
     pub client_secret: String,
     pub connection: String,
+    #[serde(default = "default_passwordless_connection")]
+    pub passwordless_connection: String,
+    #[serde(default)]
+    pub social_connections: Vec<String>,
+    #[serde(default = "default_callback_url")]
+    pub callback_url: String,
     pub dev_key_file: String,
     pub audience: String,
+    #[serde(default)]
+    pub http: HttpClientOpts,
+}
+
+/// How the service talks to the identity provider.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct HttpClientOpts {
+    /// Milliseconds.
+    pub connect_timeout: u64,
+    /// Milliseconds without data before a response is given up on.
+    pub read_timeout: u64,
+    /// Extra attempts for calls that are safe to repeat.
+    pub max_retries: u32,
+    /// Milliseconds; retries wait a random time up to this, doubled per attempt.
+    pub retry_base_delay: u64,
+    /// Consecutive failures after which calls fail fast.
+    pub breaker_failure_threshold: u32,
+    /// Seconds the breaker stays open before letting a trial call through.
+    pub breaker_reset_timeout: u64,
+}
+
+impl Default for HttpClientOpts {
+    fn default() -> Self {
+        Self {
+            connect_timeout: 2000,
+            read_timeout: 10000,
+            max_retries: 2,
+            retry_base_delay: 100,
+            breaker_failure_threshold: 5,
+            breaker_reset_timeout: 30,
+        }
+    }
+}
+
+fn default_callback_url() -> String {
+    "http://localhost:8080/callback".to_string()
+}
+
+fn default_passwordless_connection() -> String {
+    "email".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MfaOpts {
+    pub issuer: String,
+    pub recovery_codes: usize,
+    pub challenge_ttl: i64,
+    pub challenge_max_attempts: i32,
+}
+
+impl Default for MfaOpts {
+    fn default() -> Self {
+        Self {
+            issuer: "auth-service".to_string(),
+            recovery_codes: 10,
+            challenge_ttl: 300,
+            challenge_max_attempts: 5,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct TokenOpts {
+    pub issuer: String,
+    pub audience: String,
+    pub kid: String,
+    pub access_token_ttl: i64,
+    /// Signs tokens with a fixed key instead of rotated ones from `signing_keys`.
+    pub private_key_file: Option<String>,
+    /// Base64 encoded 32 byte key, required. Encrypts TOTP secrets and, unless
+    /// `private_key_file` is set, the signing keys kept in the `signing_keys`
+    /// table and rotated.
+    pub key_encryption_key: Option<String>,
+    /// Development only: use keys generated at startup for whichever of
+    /// `private_key_file` and `key_encryption_key` is missing. Tokens stop
+    /// verifying and TOTP enrollments stop working on every restart.
+    pub allow_ephemeral_key: bool,
+    pub signing_algorithm: String,
+    pub rotation_interval: i64,
+    /// How long a new key is published in the JWKS before it signs tokens, at
+    /// least as long as verifiers cache the JWKS. Shorter than `rotation_interval`.
+    pub publication_period: i64,
+    /// How long a retired key keeps verifying tokens, at least `access_token_ttl`.
+    pub retirement_period: i64,
+    pub rotation_check_interval: u64,
+}
+
+impl Default for TokenOpts {
+    fn default() -> Self {
+        Self {
+            issuer: "http://localhost:8080".to_string(),
+            audience: "auth-service".to_string(),
+            kid: "auth-service-1".to_string(),
+            access_token_ttl: 3600,
+            private_key_file: None,
+            key_encryption_key: None,
+            allow_ephemeral_key: false,
+            signing_algorithm: "RS256".to_string(),
+            rotation_interval: 7776000,
+            publication_period: 86400,
+            retirement_period: 86400,
+            rotation_check_interval: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct WebauthnOpts {
+    pub rp_id: String,
+    pub rp_name: String,
+    pub origin: String,
+    pub timeout: u64,
+    pub user_verification: String,
+}
+
+impl Default for WebauthnOpts {
+    fn default() -> Self {
+        Self {
+            rp_id: "localhost".to_string(),
+            rp_name: "auth-service".to_string(),
+            origin: "http://localhost:8080".to_string(),
+            timeout: 300000,
+            user_verification: "preferred".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct OidcOpts {
+    pub authorization_code_ttl: i64,
+    pub refresh_token_ttl: i64,
+    /// Seconds an active introspection result is cached, 0 disables the cache.
+    pub introspection_cache_ttl: u64,
+}
+
+impl Default for OidcOpts {
+    fn default() -> Self {
+        Self {
+            authorization_code_ttl: 60,
+            refresh_token_ttl: 2592000,
+            introspection_cache_ttl: 30,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AdminOpts {
+    /// Ids of the users allowed to call the `/admin` endpoints.
+    pub users: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct AuthOpts {
+    /// Paths that don't require credentials, every other one does. The user
+    /// is still attached when the request carries valid ones. Replaces the
+    /// default list, which holds the login, OAuth and probe endpoints.
+    pub public_paths: Vec<PublicPathOpts>,
+}
+
+impl Default for AuthOpts {
+    fn default() -> Self {
+        Self {
+            public_paths: [
+                ("/register", "POST"),
+                ("/login", "POST"),
+                ("/login/mfa", "POST"),
+                ("/logout", "POST"),
+                ("/passwordless/*", "POST"),
+                ("/webauthn/login/*", "POST"),
+                ("/authorize", "GET"),
+                ("/callback", "GET"),
+                ("/.well-known/*", "GET"),
+                ("/oauth/*", "POST"),
+                ("/healthz", "GET"),
+                ("/readyz", "GET"),
+                ("/swagger-ui/**", "GET"),
+                ("/api-docs/openapi.json", "GET"),
+            ]
+            .into_iter()
+            .map(|(pattern, method)| PublicPathOpts {
+                pattern: pattern.to_string(),
+                methods: vec![method.to_string()],
+            })
+            .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PublicPathOpts {
+    /// Glob over the request path: `*` matches within a segment, `**` across
+    /// segments.
+    pub pattern: String,
+    /// Methods the pattern applies to, all of them when empty.
+    #[serde(default)]
+    pub methods: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SessionOpts {
+    /// Also set a session cookie on login, so browser apps don't have to keep
+    /// the bearer token in JS.
+    pub enabled: bool,
+    pub cookie_name: String,
+    pub secure: bool,
+    /// `strict`, `lax` or `none`.
+    pub same_site: String,
+    /// Seconds without requests after which a session ends.
+    pub idle_timeout: i64,
+    /// Seconds after login after which a session ends regardless of activity.
+    pub absolute_timeout: i64,
+    pub cache_ttl: u64,
+    /// Header set by a CDN or proxy with the client's location, e.g.
+    /// `CF-IPCountry`, shown to users as a hint where a session is.
+    pub geo_header: Option<String>,
+}
+
+impl Default for SessionOpts {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cookie_name: "session".to_string(),
+            secure: true,
+            same_site: "strict".to_string(),
+            idle_timeout: 1800,
+            absolute_timeout: 43200,
+            cache_ttl: 30,
+            geo_header: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MetricsOpts {
+    /// Address of the listener serving `/metrics`. It is kept off the API
+    /// listener, so scrapes are only possible from where this is reachable.
+    pub bind: String,
+    /// Seconds between samples of the database pool and actor mailbox.
+    pub collect_interval: u64,
+}
+
+impl Default for MetricsOpts {
+    fn default() -> Self {
+        Self {
+            bind: "localhost:9464".to_string(),
+            collect_interval: 15,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct TracingOpts {
+    pub service_name: String,
+    /// OTLP collector to export spans to; spans are only logged when unset.
+    /// Over `http` this is the full traces URL, e.g. `http://localhost:4318/v1/traces`.
+    pub otlp_endpoint: Option<String>,
+    /// `grpc` or `http` (protobuf over HTTP).
+    pub otlp_protocol: String,
+    /// Milliseconds to wait for the collector on each export.
+    pub export_timeout: u64,
+    /// Share of new traces that are sampled; callers' decisions are kept.
+    pub sample_ratio: f64,
+}
+
+impl Default for TracingOpts {
+    fn default() -> Self {
+        Self {
+            service_name: "auth-service".to_string(),
+            otlp_endpoint: None,
+            otlp_protocol: "grpc".to_string(),
+            export_timeout: 10000,
+            sample_ratio: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct LoggingOpts {
+    /// One of `error`, `warn`, `info`, `debug`, `trace` or `off`.
+    pub level: String,
+    /// Levels for single modules by path, overriding `level` for them.
+    pub modules: HashMap<String, String>,
+    /// `text` or `json`, one object per line.
+    pub format: String,
+    /// Colors in `text` output.
+    pub ansi: bool,
+}
+
+impl Default for LoggingOpts {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            modules: HashMap::new(),
+            format: "text".to_string(),
+            ansi: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct HealthOpts {
+    /// Seconds a readiness check result is reused before checking again.
+    pub cache_ttl: u64,
+    /// Milliseconds each readiness check may take before it counts as failed.
+    pub check_timeout: u64,
+    /// Whether an unreachable identity provider makes the service unready,
+    /// rather than only being reported.
+    pub idp_required: bool,
+}
+
+impl Default for HealthOpts {
+    fn default() -> Self {
+        Self {
+            cache_ttl: 5,
+            check_timeout: 2000,
+            idp_required: true,
+        }
+    }
 }
 
-pub fn load_configurations() -> Result<Opts> {
-    let config_path = Opts::get_from_args();
+pub fn load_configurations(config_path: PathBuf) -> Result<Opts> {
+    println!("Using config file: {}", config_path.display());
     let config_data = Config::new()
         .with_merged(File::from(config_path))?
         .with_merged(Environment::new().separator("_"))?;
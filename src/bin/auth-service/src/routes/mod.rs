@@ -1,5 +1,34 @@
-use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::client_cert::ClientCertMiddleware;
+use crate::middleware::csrf::CsrfMiddleware;
+use crate::services::actix_requests::api_key_requests::{
+    create_api_key, create_client_api_key, list_api_keys, list_client_api_keys, revoke_api_key,
+    revoke_client_api_key,
+};
+use crate::services::actix_requests::audit_requests::{export_audit_events, list_audit_events};
+use crate::services::actix_requests::client_requests::{
+    create_client, delete_client, get_client, list_clients, rotate_client_secret, update_client,
+};
+use crate::services::actix_requests::health_requests::{healthz, readyz};
+use crate::services::actix_requests::metrics_requests::metrics;
+use crate::services::actix_requests::mfa_requests::{
+    confirm_totp, enroll_totp, regenerate_recovery_codes, verify_mfa_login,
+};
+use crate::services::actix_requests::oauth_requests::{authorize, callback, link_identity};
+use crate::services::actix_requests::oidc_requests::{
+    introspect, jwks, openid_configuration, revoke, token,
+};
+use crate::services::actix_requests::passwordless_requests::{
+    passwordless_start, passwordless_verify,
+};
 use crate::services::actix_requests::requests::{change_password, login, profile, register};
+use crate::services::actix_requests::session_requests::{
+    list_sessions, list_user_sessions, logout, revoke_other_sessions, revoke_session,
+    revoke_user_sessions,
+};
+use crate::services::actix_requests::webauthn_requests::{
+    webauthn_login_options, webauthn_login_verify, webauthn_register_options,
+    webauthn_register_verify,
+};
 use crate::ApiDoc;
 use actix_web::web;
 use actix_web::web::ServiceConfig;
@@ -11,14 +40,112 @@ pub fn configure_routes(cfg: &mut ServiceConfig) {
 
     cfg.service(
         web::scope("/user")
-            .wrap(AuthMiddleware)
+            .wrap(CsrfMiddleware)
             .service(web::resource("/change_password").route(web::post().to(change_password)))
-            .service(web::resource("/profile").route(web::get().to(profile))),
+            .service(web::resource("/profile").route(web::get().to(profile)))
+            .service(web::resource("/identities/link").route(web::post().to(link_identity)))
+            .service(web::resource("/mfa/totp/enroll").route(web::post().to(enroll_totp)))
+            .service(web::resource("/mfa/totp/confirm").route(web::post().to(confirm_totp)))
+            .service(
+                web::resource("/mfa/recovery_codes")
+                    .route(web::post().to(regenerate_recovery_codes)),
+            )
+            .service(
+                web::resource("/webauthn/register/options")
+                    .route(web::post().to(webauthn_register_options)),
+            )
+            .service(
+                web::resource("/webauthn/register/verify")
+                    .route(web::post().to(webauthn_register_verify)),
+            )
+            .service(
+                web::resource("/api_keys")
+                    .route(web::get().to(list_api_keys))
+                    .route(web::post().to(create_api_key)),
+            )
+            .service(web::resource("/api_keys/{id}").route(web::delete().to(revoke_api_key)))
+            .service(
+                web::resource("/sessions")
+                    .route(web::get().to(list_sessions))
+                    .route(web::delete().to(revoke_other_sessions)),
+            )
+            .service(web::resource("/sessions/{id}").route(web::delete().to(revoke_session))),
+    )
+    .service(
+        web::scope("/admin")
+            .wrap(CsrfMiddleware)
+            .wrap(ClientCertMiddleware)
+            .service(
+                web::resource("/clients")
+                    .route(web::get().to(list_clients))
+                    .route(web::post().to(create_client)),
+            )
+            .service(
+                web::resource("/clients/{client_id}")
+                    .route(web::get().to(get_client))
+                    .route(web::put().to(update_client))
+                    .route(web::delete().to(delete_client)),
+            )
+            .service(
+                web::resource("/clients/{client_id}/secret")
+                    .route(web::post().to(rotate_client_secret)),
+            )
+            .service(
+                web::resource("/clients/{client_id}/api_keys")
+                    .route(web::get().to(list_client_api_keys))
+                    .route(web::post().to(create_client_api_key)),
+            )
+            .service(
+                web::resource("/clients/{client_id}/api_keys/{id}")
+                    .route(web::delete().to(revoke_client_api_key)),
+            )
+            .service(
+                web::resource("/users/{user_id}/sessions")
+                    .route(web::get().to(list_user_sessions))
+                    .route(web::delete().to(revoke_user_sessions)),
+            )
+            .service(web::resource("/audit").route(web::get().to(list_audit_events)))
+            .service(web::resource("/audit/export").route(web::get().to(export_audit_events))),
     )
     .service(
         web::scope("")
             .service(web::resource("/register").route(web::post().to(register)))
             .service(web::resource("/login").route(web::post().to(login)))
+            .service(web::resource("/login/mfa").route(web::post().to(verify_mfa_login)))
+            .service(
+                web::resource("/logout")
+                    .wrap(CsrfMiddleware)
+                    .route(web::post().to(logout)),
+            )
+            .service(web::resource("/authorize").route(web::get().to(authorize)))
+            .service(web::resource("/callback").route(web::get().to(callback)))
+            .service(
+                web::resource("/.well-known/openid-configuration")
+                    .route(web::get().to(openid_configuration)),
+            )
+            .service(web::resource("/.well-known/jwks.json").route(web::get().to(jwks)))
+            .service(web::resource("/oauth/token").route(web::post().to(token)))
+            .service(web::resource("/oauth/introspect").route(web::post().to(introspect)))
+            .service(web::resource("/oauth/revoke").route(web::post().to(revoke)))
+            .service(web::resource("/passwordless/start").route(web::post().to(passwordless_start)))
+            .service(
+                web::resource("/passwordless/verify").route(web::post().to(passwordless_verify)),
+            )
+            .service(
+                web::resource("/webauthn/login/options")
+                    .route(web::post().to(webauthn_login_options)),
+            )
+            .service(
+                web::resource("/webauthn/login/verify")
+                    .route(web::post().to(webauthn_login_verify)),
+            )
+            .service(web::resource("/healthz").route(web::get().to(healthz)))
+            .service(web::resource("/readyz").route(web::get().to(readyz)))
             .service(SwaggerUi::new("/swagger-ui/{_:.*}").url("/api-docs/openapi.json", openapi)),
     );
 }
+
+/// Routes of the metrics listener, which serves nothing else.
+pub fn configure_metrics_routes(cfg: &mut ServiceConfig) {
+    cfg.service(web::resource("/metrics").route(web::get().to(metrics)));
+}
@@ -0,0 +1,278 @@
+use crate::errors::{Error, Result};
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::services::actix_requests::models::{ApiKeyResponse, CreateApiKeyData};
+use crate::services::actors::messages::{ApiKeyOwner, CreateApiKey, GetApiKeys, RevokeApiKey};
+use crate::services::actors::traced::TracedSend;
+use crate::services::admin::admin_service::AdminService;
+use crate::services::api_keys::api_key_service::ApiKeyService;
+use crate::services::db::postgres_db::DbService;
+use crate::services::oidc::consts::{
+    SCOPE_ADMIN, SCOPE_USER_READ, SCOPE_USER_WRITE, SUPPORTED_SCOPES,
+};
+use crate::services::oidc::oidc_service::OidcService;
+use actix::Addr;
+use actix_web::web::{Data, Json, Path};
+use actix_web::HttpResponse;
+use uuid::Uuid;
+
+#[utoipa::path(
+    post,
+    path = "/user/api_keys",
+    request_body = CreateApiKeyData,
+    responses(
+        (status = 200, description = "API key created, the key is only shown once", body = ApiKeyResponse),
+        (status = BAD_REQUEST, description = "Invalid name, expiry or scope"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not allowed for API keys, clients or tokens without the user:write scope")
+    )
+)]
+pub async fn create_api_key(
+    db: Data<Addr<DbService>>,
+    user: AuthenticatedUser,
+    data: Json<CreateApiKeyData>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for creating an API key!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_WRITE)?;
+    user.reject_api_key()?;
+
+    let scope = normalize_scope(data.scope.as_deref())?;
+    let key = create_key(
+        &db,
+        ApiKeyOwner::User(user.user_id),
+        data.into_inner(),
+        scope,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(key))
+}
+
+#[utoipa::path(
+    get,
+    path = "/user/api_keys",
+    responses(
+        (status = 200, description = "API keys of the user that are not revoked", body = [ApiKeyResponse]),
+        (status = UNAUTHORIZED, description = "Missing or invalid token")
+    )
+)]
+pub async fn list_api_keys(
+    db: Data<Addr<DbService>>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for listing API keys!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_READ)?;
+    let keys = list_keys(&db, ApiKeyOwner::User(user.user_id)).await?;
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/user/api_keys/{id}",
+    responses(
+        (status = 200, description = "API key revoked"),
+        (status = NOT_FOUND, description = "Unknown or already revoked key"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token")
+    )
+)]
+pub async fn revoke_api_key(
+    db: Data<Addr<DbService>>,
+    user: AuthenticatedUser,
+    id: Path<Uuid>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for revoking an API key!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_WRITE)?;
+    revoke_key(&db, ApiKeyOwner::User(user.user_id), id.into_inner()).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/clients/{client_id}/api_keys",
+    request_body = CreateApiKeyData,
+    responses(
+        (status = 200, description = "API key created for the client, the key is only shown once", body = ApiKeyResponse),
+        (status = BAD_REQUEST, description = "Invalid name, expiry or scope"),
+        (status = NOT_FOUND, description = "Unknown client"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn create_client_api_key(
+    admin_service: Data<AdminService>,
+    oidc_service: Data<OidcService>,
+    db: Data<Addr<DbService>>,
+    user: AuthenticatedUser,
+    client_id: Path<String>,
+    data: Json<CreateApiKeyData>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for creating a client API key!");
+    admin_service.authorize(&user)?;
+    let client_id = client_id.into_inner();
+
+    let client = oidc_service
+        .client(&client_id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Client {}", client_id)))?;
+
+    let scope = normalize_scope(data.scope.as_deref())?;
+    if !client.allowed_scopes.is_empty() {
+        if let Some(denied) = scope
+            .split_whitespace()
+            .find(|scope| !client.allowed_scopes.iter().any(|allowed| allowed == scope))
+        {
+            return Err(Error::InvalidInput(format!(
+                "Client may not use scope {}",
+                denied
+            )));
+        }
+    }
+
+    let key = create_key(
+        &db,
+        ApiKeyOwner::Client(client_id),
+        data.into_inner(),
+        scope,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(key))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/clients/{client_id}/api_keys",
+    responses(
+        (status = 200, description = "API keys of the client that are not revoked", body = [ApiKeyResponse]),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn list_client_api_keys(
+    admin_service: Data<AdminService>,
+    db: Data<Addr<DbService>>,
+    user: AuthenticatedUser,
+    client_id: Path<String>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for listing client API keys!");
+    admin_service.authorize(&user)?;
+
+    let keys = list_keys(&db, ApiKeyOwner::Client(client_id.into_inner())).await?;
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/clients/{client_id}/api_keys/{id}",
+    responses(
+        (status = 200, description = "API key revoked"),
+        (status = NOT_FOUND, description = "Unknown or already revoked key"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn revoke_client_api_key(
+    admin_service: Data<AdminService>,
+    db: Data<Addr<DbService>>,
+    user: AuthenticatedUser,
+    path: Path<(String, Uuid)>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for revoking a client API key!");
+    admin_service.authorize(&user)?;
+    let (client_id, id) = path.into_inner();
+
+    revoke_key(&db, ApiKeyOwner::Client(client_id), id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn create_key(
+    db: &Addr<DbService>,
+    owner: ApiKeyOwner,
+    data: CreateApiKeyData,
+    scope: String,
+) -> Result<ApiKeyResponse> {
+    if data.name.trim().is_empty() {
+        return Err(Error::InvalidInput("Missing name".to_string()));
+    }
+    if data.expires_in.is_some_and(|expires_in| expires_in <= 0) {
+        return Err(Error::InvalidInput(
+            "expires_in must be positive".to_string(),
+        ));
+    }
+
+    let generated = ApiKeyService::generate()?;
+    let id = Uuid::new_v4();
+    db.send_traced(CreateApiKey {
+        id,
+        prefix: generated.prefix.clone(),
+        secret_hash: generated.secret_hash,
+        name: data.name.clone(),
+        owner,
+        scope: scope.clone(),
+        expires_at: data
+            .expires_in
+            .map(|expires_in| chrono::Utc::now() + chrono::Duration::seconds(expires_in)),
+    })
+    .await??;
+
+    let now = chrono::Utc::now().timestamp();
+    Ok(ApiKeyResponse {
+        id,
+        name: data.name,
+        prefix: generated.prefix,
+        scope,
+        expires_at: data.expires_in.map(|expires_in| now + expires_in),
+        last_used_at: None,
+        created_at: now,
+        key: Some(generated.key),
+    })
+}
+
+async fn list_keys(db: &Addr<DbService>, owner: ApiKeyOwner) -> Result<Vec<ApiKeyResponse>> {
+    Ok(db
+        .send_traced(GetApiKeys { owner })
+        .await??
+        .into_iter()
+        .map(ApiKeyResponse::from)
+        .collect())
+}
+
+async fn revoke_key(db: &Addr<DbService>, owner: ApiKeyOwner, id: Uuid) -> Result<()> {
+    if !db.send_traced(RevokeApiKey { id, owner }).await?? {
+        return Err(Error::NotFound(format!("API key {}", id)));
+    }
+    Ok(())
+}
+
+/// Keys may carry any scope a token can, plus `admin`, and need at least one.
+fn normalize_scope(scope: Option<&str>) -> Result<String> {
+    let scopes: Vec<&str> = scope.unwrap_or_default().split_whitespace().collect();
+    if scopes.is_empty() {
+        return Err(Error::InvalidInput(
+            "An API key needs at least one scope".to_string(),
+        ));
+    }
+    if let Some(unknown) = scopes
+        .iter()
+        .find(|scope| **scope != SCOPE_ADMIN && !SUPPORTED_SCOPES.contains(scope))
+    {
+        return Err(Error::InvalidInput(format!("Unknown scope {}", unknown)));
+    }
+    Ok(scopes.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requires_known_scopes() {
+        assert!(normalize_scope(None).is_err());
+        assert!(normalize_scope(Some(" ")).is_err());
+        assert!(normalize_scope(Some("user:read unknown")).is_err());
+        assert_eq!(
+            normalize_scope(Some(" user:read  admin ")).ok().as_deref(),
+            Some("user:read admin")
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use crate::errors::{Error, Result};
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::services::actix_requests::models::{AuditEventResponse, AuditQuery};
+use crate::services::actors::messages::GetAuditEvents;
+use crate::services::actors::traced::TracedSend;
+use crate::services::admin::admin_service::AdminService;
+use crate::services::audit::consts::{DEFAULT_AUDIT_LIMIT, EXPORT_PAGE_SIZE, MAX_AUDIT_LIMIT};
+use crate::services::db::postgres_db::DbService;
+use actix::Addr;
+use actix_web::http::header::CONTENT_DISPOSITION;
+use actix_web::web::{Bytes, Data, Query};
+use actix_web::HttpResponse;
+use chrono::{DateTime, Utc};
+
+#[utoipa::path(
+    get,
+    path = "/admin/audit",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "Matching audit events, newest first", body = [AuditEventResponse]),
+        (status = BAD_REQUEST, description = "Invalid filter"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn list_audit_events(
+    admin_service: Data<AdminService>,
+    db: Data<Addr<DbService>>,
+    query: Query<AuditQuery>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for listing audit events!");
+    admin_service.authorize(&user)?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_LIMIT)
+        .clamp(1, MAX_AUDIT_LIMIT);
+    let events = db
+        .send_traced(filter(&query, limit)?)
+        .await??
+        .into_iter()
+        .map(AuditEventResponse::from)
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(events))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/audit/export",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "All matching audit events as JSON lines, newest first", body = String, content_type = "application/x-ndjson"),
+        (status = BAD_REQUEST, description = "Invalid filter"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn export_audit_events(
+    admin_service: Data<AdminService>,
+    db: Data<Addr<DbService>>,
+    query: Query<AuditQuery>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for exporting audit events!");
+    admin_service.authorize(&user)?;
+
+    // `limit` doesn't apply, the export pages through everything that
+    // matches so the response never holds more than a page in memory.
+    let first_page = filter(&query, EXPORT_PAGE_SIZE)?;
+    let lines = futures_util::stream::try_unfold(Some(first_page), move |page| {
+        let db = db.clone();
+        async move {
+            let Some(page) = page else {
+                return Ok(None);
+            };
+            let events = db.send_traced(page.clone()).await??;
+
+            let next_page = (events.len() as i64 == page.limit).then(|| GetAuditEvents {
+                before: events.last().map(|event| event.id),
+                ..page
+            });
+            let mut lines = String::new();
+            for event in events {
+                lines.push_str(&serde_json::to_string(&AuditEventResponse::from(event))?);
+                lines.push('\n');
+            }
+            Ok::<_, Error>(Some((Bytes::from(lines), next_page)))
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .insert_header((CONTENT_DISPOSITION, "attachment; filename=\"audit.jsonl\""))
+        .streaming(lines))
+}
+
+fn filter(query: &AuditQuery, limit: i64) -> Result<GetAuditEvents> {
+    Ok(GetAuditEvents {
+        action: query.action.clone(),
+        outcome: query.outcome.clone(),
+        actor: query.actor.clone(),
+        subject: query.subject.clone(),
+        since: query.since.map(timestamp).transpose()?,
+        until: query.until.map(timestamp).transpose()?,
+        before: query.before,
+        limit,
+    })
+}
+
+fn timestamp(seconds: i64) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp(seconds, 0)
+        .ok_or_else(|| Error::InvalidInput(format!("Invalid timestamp {}", seconds)))
+}
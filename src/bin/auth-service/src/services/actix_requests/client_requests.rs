@@ -0,0 +1,280 @@
+use crate::errors::{Error, Result};
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::services::actix_requests::models::{
+    CreateOauthClientData, OauthClientResponse, UpdateOauthClientData,
+};
+use crate::services::actors::messages::{
+    CreateOauthClient, DeleteOauthClient, GetOauthClients, UpdateOauthClient,
+    UpdateOauthClientSecret,
+};
+use crate::services::actors::traced::TracedSend;
+use crate::services::admin::admin_service::AdminService;
+use crate::services::db::postgres_db::DbService;
+use crate::services::db::tables::OauthClients;
+use crate::services::oauth::pkce::random_token;
+use crate::services::oidc::oidc_service::OidcService;
+use actix::Addr;
+use actix_web::web::{Data, Json, Path};
+use actix_web::HttpResponse;
+
+#[utoipa::path(
+    get,
+    path = "/admin/clients",
+    responses(
+        (status = 200, description = "Registered OAuth clients", body = [OauthClientResponse]),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn list_clients(
+    admin_service: Data<AdminService>,
+    db: Data<Addr<DbService>>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for listing OAuth clients!");
+    admin_service.authorize(&user)?;
+
+    let clients = db
+        .send_traced(GetOauthClients)
+        .await??
+        .into_iter()
+        .map(OauthClientResponse::from)
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(clients))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/clients",
+    request_body = CreateOauthClientData,
+    responses(
+        (status = 200, description = "Client registered, with its secret for confidential clients", body = OauthClientResponse),
+        (status = BAD_REQUEST, description = "Invalid client policy"),
+        (status = CONFLICT, description = "Client already exists"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn create_client(
+    admin_service: Data<AdminService>,
+    oidc_service: Data<OidcService>,
+    db: Data<Addr<DbService>>,
+    data: Json<CreateOauthClientData>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for creating an OAuth client!");
+    admin_service.authorize(&user)?;
+    let data = data.into_inner();
+
+    oidc_service.check_client_policy(
+        data.is_confidential,
+        &data.grant_types,
+        &data.redirect_uris,
+        &data.allowed_scopes,
+    )?;
+    check_token_lifetimes(data.access_token_ttl, data.refresh_token_ttl)?;
+
+    let client_id = match data.client_id {
+        Some(client_id) => client_id,
+        None => random_token()?,
+    };
+    if oidc_service.client(&client_id).await?.is_some() {
+        return Err(Error::Conflict(format!(
+            "Client {} already exists",
+            client_id
+        )));
+    }
+
+    let (client_secret, client_secret_hash) = if data.is_confidential {
+        let (secret, hash) = OidcService::generate_client_secret()?;
+        (Some(secret), Some(hash))
+    } else {
+        (None, None)
+    };
+
+    db.send_traced(CreateOauthClient {
+        client_id: client_id.clone(),
+        name: data.name,
+        client_secret_hash,
+        grant_types: data.grant_types,
+        redirect_uris: data.redirect_uris,
+        allowed_scopes: data.allowed_scopes,
+        access_token_ttl: data.access_token_ttl,
+        refresh_token_ttl: data.refresh_token_ttl,
+    })
+    .await??;
+
+    let client = find_client(&oidc_service, &client_id).await?;
+    Ok(HttpResponse::Ok().json(OauthClientResponse {
+        client_secret,
+        ..OauthClientResponse::from(client)
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/clients/{client_id}",
+    responses(
+        (status = 200, description = "The OAuth client", body = OauthClientResponse),
+        (status = NOT_FOUND, description = "Unknown client"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn get_client(
+    admin_service: Data<AdminService>,
+    oidc_service: Data<OidcService>,
+    client_id: Path<String>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for an OAuth client!");
+    admin_service.authorize(&user)?;
+
+    let client = find_client(&oidc_service, &client_id).await?;
+    Ok(HttpResponse::Ok().json(OauthClientResponse::from(client)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/clients/{client_id}",
+    request_body = UpdateOauthClientData,
+    responses(
+        (status = 200, description = "Client updated", body = OauthClientResponse),
+        (status = BAD_REQUEST, description = "Invalid client policy"),
+        (status = NOT_FOUND, description = "Unknown client"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn update_client(
+    admin_service: Data<AdminService>,
+    oidc_service: Data<OidcService>,
+    db: Data<Addr<DbService>>,
+    client_id: Path<String>,
+    data: Json<UpdateOauthClientData>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for updating an OAuth client!");
+    admin_service.authorize(&user)?;
+    let client_id = client_id.into_inner();
+    let data = data.into_inner();
+
+    let client = find_client(&oidc_service, &client_id).await?;
+    oidc_service.check_client_policy(
+        client.client_secret_hash.is_some(),
+        &data.grant_types,
+        &data.redirect_uris,
+        &data.allowed_scopes,
+    )?;
+    check_token_lifetimes(data.access_token_ttl, data.refresh_token_ttl)?;
+
+    db.send_traced(UpdateOauthClient {
+        client_id: client_id.clone(),
+        name: data.name,
+        grant_types: data.grant_types,
+        redirect_uris: data.redirect_uris,
+        allowed_scopes: data.allowed_scopes,
+        access_token_ttl: data.access_token_ttl,
+        refresh_token_ttl: data.refresh_token_ttl,
+    })
+    .await??;
+
+    let client = find_client(&oidc_service, &client_id).await?;
+    Ok(HttpResponse::Ok().json(OauthClientResponse::from(client)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/clients/{client_id}",
+    responses(
+        (status = 200, description = "Client deleted along with its refresh tokens"),
+        (status = NOT_FOUND, description = "Unknown client"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn delete_client(
+    admin_service: Data<AdminService>,
+    db: Data<Addr<DbService>>,
+    client_id: Path<String>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for deleting an OAuth client!");
+    admin_service.authorize(&user)?;
+    let client_id = client_id.into_inner();
+
+    if !db
+        .send_traced(DeleteOauthClient {
+            client_id: client_id.clone(),
+        })
+        .await??
+    {
+        return Err(Error::NotFound(format!("Client {}", client_id)));
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/clients/{client_id}/secret",
+    responses(
+        (status = 200, description = "New secret issued, the old one stops working", body = OauthClientResponse),
+        (status = BAD_REQUEST, description = "Public clients have no secret"),
+        (status = NOT_FOUND, description = "Unknown client"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn rotate_client_secret(
+    admin_service: Data<AdminService>,
+    oidc_service: Data<OidcService>,
+    db: Data<Addr<DbService>>,
+    client_id: Path<String>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for rotating an OAuth client secret!");
+    admin_service.authorize(&user)?;
+    let client_id = client_id.into_inner();
+
+    let client = find_client(&oidc_service, &client_id).await?;
+    if client.client_secret_hash.is_none() {
+        return Err(Error::InvalidInput(
+            "Public clients have no secret".to_string(),
+        ));
+    }
+
+    let (client_secret, client_secret_hash) = OidcService::generate_client_secret()?;
+    db.send_traced(UpdateOauthClientSecret {
+        client_id: client_id.clone(),
+        client_secret_hash,
+    })
+    .await??;
+
+    let client = find_client(&oidc_service, &client_id).await?;
+    Ok(HttpResponse::Ok().json(OauthClientResponse {
+        client_secret: Some(client_secret),
+        ..OauthClientResponse::from(client)
+    }))
+}
+
+async fn find_client(oidc_service: &OidcService, client_id: &str) -> Result<OauthClients> {
+    oidc_service
+        .client(client_id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Client {}", client_id)))
+}
+
+fn check_token_lifetimes(
+    access_token_ttl: Option<i64>,
+    refresh_token_ttl: Option<i64>,
+) -> Result<()> {
+    if access_token_ttl.is_some_and(|ttl| ttl <= 0) || refresh_token_ttl.is_some_and(|ttl| ttl <= 0)
+    {
+        return Err(Error::InvalidInput(
+            "Token lifetimes must be positive".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use crate::errors::Result;
+use crate::services::health::consts::STATUS_UP;
+use crate::services::health::health_service::HealthService;
+use crate::services::health::models::LivenessResponse;
+use actix_web::web::Data;
+use actix_web::HttpResponse;
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses(
+        (status = 200, description = "The process is alive", body = LivenessResponse)
+    )
+)]
+pub async fn healthz() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(LivenessResponse { status: STATUS_UP }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "Ready to serve traffic", body = ReadinessResponse),
+        (status = 503, description = "A required component is down", body = ReadinessResponse)
+    )
+)]
+pub async fn readyz(health_service: Data<HealthService>) -> Result<HttpResponse> {
+    let readiness = health_service.readiness().await;
+
+    if readiness.status == STATUS_UP {
+        Ok(HttpResponse::Ok().json(readiness))
+    } else {
+        Ok(HttpResponse::ServiceUnavailable().json(readiness))
+    }
+}
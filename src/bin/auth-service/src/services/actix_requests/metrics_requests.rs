@@ -0,0 +1,11 @@
+use crate::errors::Result;
+use crate::services::metrics::metrics_service::MetricsService;
+use actix_web::web::Data;
+use actix_web::HttpResponse;
+
+/// Metrics in the Prometheus text format, served on the metrics listener.
+pub async fn metrics(metrics_service: Data<MetricsService>) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics_service.render()))
+}
@@ -0,0 +1,294 @@
+use crate::errors::{Error, Result};
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::services::actix_requests::models::{
+    MfaChallengeResponse, MfaVerifyData, RecoveryCodesResponse, TotpCodeData,
+    TotpEnrollmentResponse,
+};
+use crate::services::actix_requests::session_requests::login_response;
+use crate::services::actors::messages::{
+    ClaimMfaChallengeAttempt, ConfirmMfaFactor, CreateMfaChallenge, CreateMfaFactor,
+    DeleteMfaChallenge, GetMfaFactor, GetUser, ReplaceRecoveryCodes, UpdateMfaLastUsedStep,
+    UseRecoveryCode,
+};
+use crate::services::actors::traced::TracedSend;
+use crate::services::audit::audit_service::AuditService;
+use crate::services::db::postgres_db::DbService;
+use crate::services::mfa::mfa_service::MfaService;
+use crate::services::oidc::consts::SCOPE_USER_WRITE;
+use crate::services::sessions::session_service::SessionService;
+use actix::Addr;
+use actix_web::web::{Data, Json};
+use actix_web::{HttpRequest, HttpResponse};
+
+#[utoipa::path(
+    post,
+    path = "/user/mfa/totp/enroll",
+    responses(
+        (status = 200, description = "TOTP secret generated", body = TotpEnrollmentResponse),
+        (status = BAD_REQUEST, description = "TOTP already enrolled"),
+        (status = NOT_FOUND, description = "User not found"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token")
+    )
+)]
+pub async fn enroll_totp(
+    mfa_service: Data<MfaService>,
+    db: Data<Addr<DbService>>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for TOTP enrollment!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_WRITE)?;
+    user.reject_api_key()?;
+    let user_id = user.user_id;
+
+    let Some(user) = db
+        .send_traced(GetUser {
+            id: user_id.clone(),
+        })
+        .await??
+    else {
+        return Err(Error::UserNotFound);
+    };
+
+    let factor = db
+        .send_traced(GetMfaFactor {
+            user_id: user_id.clone(),
+        })
+        .await??;
+    if factor.is_some_and(|factor| factor.is_confirmed) {
+        return Err(Error::InvalidInput("TOTP already enrolled".to_string()));
+    }
+
+    let secret = mfa_service.generate_secret()?;
+    let otpauth_uri = mfa_service.otpauth_uri(&user.email, &secret)?;
+
+    db.send_traced(CreateMfaFactor {
+        encrypted_secret: mfa_service.encrypt_secret(&user_id, &secret)?,
+        user_id,
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(TotpEnrollmentResponse {
+        secret,
+        otpauth_uri,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/user/mfa/totp/confirm",
+    request_body = TotpCodeData,
+    responses(
+        (status = 200, description = "TOTP enrollment confirmed", body = RecoveryCodesResponse),
+        (status = BAD_REQUEST, description = "No pending TOTP enrollment"),
+        (status = UNAUTHORIZED, description = "Invalid code or token")
+    )
+)]
+pub async fn confirm_totp(
+    mfa_service: Data<MfaService>,
+    db: Data<Addr<DbService>>,
+    data: Json<TotpCodeData>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for TOTP confirmation!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_WRITE)?;
+    user.reject_api_key()?;
+    let user_id = user.user_id;
+
+    let factor = db
+        .send_traced(GetMfaFactor {
+            user_id: user_id.clone(),
+        })
+        .await??
+        .filter(|factor| !factor.is_confirmed)
+        .ok_or(Error::InvalidInput(
+            "No pending TOTP enrollment".to_string(),
+        ))?;
+
+    let step = mfa_service
+        .verify_code(&mfa_service.factor_secret(&factor)?, &data.code, None)?
+        .ok_or(Error::InvalidMfaCode)?;
+
+    db.send_traced(ConfirmMfaFactor {
+        user_id: user_id.clone(),
+        step,
+    })
+    .await??;
+
+    let recovery_codes = replace_recovery_codes(&mfa_service, &db, user_id).await?;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/user/mfa/recovery_codes",
+    responses(
+        (status = 200, description = "Recovery codes regenerated", body = RecoveryCodesResponse),
+        (status = BAD_REQUEST, description = "MFA is not enabled"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token")
+    )
+)]
+pub async fn regenerate_recovery_codes(
+    mfa_service: Data<MfaService>,
+    db: Data<Addr<DbService>>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for recovery codes!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_WRITE)?;
+    user.reject_api_key()?;
+    let user_id = user.user_id;
+
+    let factor = db
+        .send_traced(GetMfaFactor {
+            user_id: user_id.clone(),
+        })
+        .await??;
+    if !factor.is_some_and(|factor| factor.is_confirmed) {
+        return Err(Error::InvalidInput("MFA is not enabled".to_string()));
+    }
+
+    let recovery_codes = replace_recovery_codes(&mfa_service, &db, user_id).await?;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/login/mfa",
+    request_body = MfaVerifyData,
+    responses(
+        (status = 200, description = "User successfully login", body = LoginUserResponse),
+        (status = 200, description = "Session started, the cookie is the credential", body = SessionLoginResponse),
+        (status = UNAUTHORIZED, description = "Invalid code or expired challenge")
+    )
+)]
+pub async fn verify_mfa_login(
+    mfa_service: Data<MfaService>,
+    session_service: Data<SessionService>,
+    db: Data<Addr<DbService>>,
+    data: Json<MfaVerifyData>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for MFA login!");
+    let token_hash = MfaService::hash_token(&data.mfa_token);
+
+    let Some(challenge) = db
+        .send_traced(ClaimMfaChallengeAttempt {
+            token_hash: token_hash.clone(),
+            max_attempts: mfa_service.challenge_max_attempts(),
+        })
+        .await??
+    else {
+        db.send_traced(DeleteMfaChallenge { token_hash }).await??;
+        return Err(Error::MfaChallengeExpired);
+    };
+    AuditService::set_subject(&req, challenge.user_id.clone());
+
+    if challenge.expires_at < chrono::Utc::now() {
+        db.send_traced(DeleteMfaChallenge { token_hash }).await??;
+        return Err(Error::MfaChallengeExpired);
+    }
+
+    let verified = match (&data.code, &data.recovery_code) {
+        (Some(code), _) => {
+            let factor = db
+                .send_traced(GetMfaFactor {
+                    user_id: challenge.user_id.clone(),
+                })
+                .await??
+                .filter(|factor| factor.is_confirmed)
+                .ok_or(Error::MfaChallengeExpired)?;
+
+            let secret = mfa_service.factor_secret(&factor)?;
+            match mfa_service.verify_code(&secret, code, factor.last_used_step)? {
+                Some(step) => {
+                    db.send_traced(UpdateMfaLastUsedStep {
+                        user_id: challenge.user_id.clone(),
+                        step,
+                    })
+                    .await??
+                }
+                None => false,
+            }
+        }
+        (None, Some(recovery_code)) => {
+            db.send_traced(UseRecoveryCode {
+                user_id: challenge.user_id.clone(),
+                code_hash: MfaService::hash_recovery_code(recovery_code),
+            })
+            .await??
+        }
+        (None, None) => {
+            return Err(Error::InvalidInput(
+                "Either code or recovery_code is required".to_string(),
+            ))
+        }
+    };
+
+    if !verified {
+        return Err(Error::InvalidMfaCode);
+    }
+
+    db.send_traced(DeleteMfaChallenge { token_hash }).await??;
+    let token =
+        MfaService::decrypt_access_token(&data.mfa_token, &challenge.encrypted_access_token)?;
+
+    login_response(&session_service, &req, &challenge.user_id, token).await
+}
+
+/// Starts the second login step for a user with a confirmed MFA factor.
+/// Returns `None` when the user has not enrolled MFA and can be logged in directly.
+pub(crate) async fn start_mfa_challenge(
+    mfa_service: &MfaService,
+    db: &Addr<DbService>,
+    user_id: String,
+    access_token: String,
+) -> Result<Option<MfaChallengeResponse>> {
+    let factor = db
+        .send_traced(GetMfaFactor {
+            user_id: user_id.clone(),
+        })
+        .await??;
+    if !factor.is_some_and(|factor| factor.is_confirmed) {
+        return Ok(None);
+    }
+
+    let mfa_token = mfa_service.generate_challenge_token()?;
+    let expires_in = mfa_service.challenge_ttl();
+
+    db.send_traced(CreateMfaChallenge {
+        token_hash: MfaService::hash_token(&mfa_token),
+        user_id,
+        encrypted_access_token: MfaService::encrypt_access_token(&mfa_token, &access_token)?,
+        expires_at: chrono::Utc::now() + chrono::Duration::seconds(expires_in),
+    })
+    .await??;
+
+    Ok(Some(MfaChallengeResponse {
+        mfa_required: true,
+        mfa_token,
+        expires_in,
+    }))
+}
+
+async fn replace_recovery_codes(
+    mfa_service: &MfaService,
+    db: &Addr<DbService>,
+    user_id: String,
+) -> Result<Vec<String>> {
+    let recovery_codes = mfa_service.generate_recovery_codes()?;
+
+    db.send_traced(ReplaceRecoveryCodes {
+        user_id,
+        code_hashes: recovery_codes
+            .iter()
+            .map(|code| MfaService::hash_recovery_code(code))
+            .collect(),
+    })
+    .await??;
+
+    Ok(recovery_codes)
+}
@@ -1,2 +1,13 @@
+pub mod api_key_requests;
+pub mod audit_requests;
+pub mod client_requests;
+pub mod health_requests;
+pub mod metrics_requests;
+pub mod mfa_requests;
 pub mod models;
+pub mod oauth_requests;
+pub mod oidc_requests;
+pub mod passwordless_requests;
 pub mod requests;
+pub mod session_requests;
+pub mod webauthn_requests;
@@ -1,5 +1,7 @@
+use crate::services::db::tables::{ApiKeys, AuditEvents, OauthClients, Sessions, Users};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct UserData {
@@ -15,20 +17,326 @@ pub struct RegisteredUserData {
     pub password: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
-pub struct UpdatePasswordData {
+// structs for returning data to client
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProfileResponse {
     pub user_id: String,
+    pub username: String,
     pub email: String,
+    pub email_verified: bool,
+    /// Unix timestamps.
+    pub created_at: i64,
+    pub updated_at: Option<i64>,
+}
+
+impl From<Users> for ProfileResponse {
+    fn from(user: Users) -> Self {
+        ProfileResponse {
+            user_id: user.auth_id,
+            username: user.username,
+            email: user.email,
+            email_verified: user.is_email_activate,
+            created_at: user.created_at.timestamp(),
+            updated_at: user.updated_at.map(|updated_at| updated_at.timestamp()),
+        }
+    }
 }
 
-// structs for returning data to client
 #[derive(Debug, Clone, Serialize)]
 pub struct RegisterUserResponse {
     pub user_id: String,
     pub token: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct LoginUserResponse {
     pub token: String,
 }
+
+/// Body of a login that started a session, whose cookie is the credential.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SessionLoginResponse {
+    pub session_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TotpCodeData {
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MfaVerifyData {
+    pub mfa_token: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+pub struct WebauthnLoginOptionsData {
+    pub user_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WebauthnCredentialResponse {
+    pub credential_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PasswordlessStartData {
+    pub email: String,
+    /// Either `code` (the default) for a one-time code or `link` for a magic
+    /// link. A link has to be opened in the browser that asked for it.
+    pub send: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PasswordlessVerifyData {
+    pub email: String,
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct AuthorizeQuery {
+    /// Auth0 connection to log in with, e.g. `google-oauth2` or `github`.
+    pub connection: Option<String>,
+    /// The remaining parameters are sent by our own OAuth clients, which get an
+    /// authorization code for `/oauth/token` instead of the Auth0 tokens.
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub response_type: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LinkIdentityData {
+    /// Auth0 connection of the identity to link, e.g. `google-oauth2`.
+    pub connection: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LinkIdentityResponse {
+    /// Where to send the browser to sign in with the identity to link.
+    pub authorization_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LinkedIdentityResponse {
+    pub provider: String,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: String,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateOauthClientData {
+    /// Generated when not given.
+    pub client_id: Option<String>,
+    pub name: String,
+    /// Confidential clients get a secret; public clients must use PKCE.
+    pub is_confidential: bool,
+    pub grant_types: Vec<String>,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    /// Empty allows every supported scope.
+    #[serde(default)]
+    pub allowed_scopes: Vec<String>,
+    /// Seconds, overriding `token.access_token_ttl`.
+    pub access_token_ttl: Option<i64>,
+    /// Seconds, overriding `oidc.refresh_token_ttl`.
+    pub refresh_token_ttl: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpdateOauthClientData {
+    pub name: String,
+    pub grant_types: Vec<String>,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    #[serde(default)]
+    pub allowed_scopes: Vec<String>,
+    pub access_token_ttl: Option<i64>,
+    pub refresh_token_ttl: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OauthClientResponse {
+    pub client_id: String,
+    pub name: String,
+    pub is_confidential: bool,
+    pub grant_types: Vec<String>,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub access_token_ttl: Option<i64>,
+    pub refresh_token_ttl: Option<i64>,
+    /// Unix timestamps.
+    pub created_at: i64,
+    pub updated_at: Option<i64>,
+    /// Only returned when the secret is created, it can't be read again.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+}
+
+impl From<OauthClients> for OauthClientResponse {
+    fn from(client: OauthClients) -> Self {
+        OauthClientResponse {
+            client_id: client.client_id,
+            name: client.name,
+            is_confidential: client.client_secret_hash.is_some(),
+            grant_types: client.grant_types,
+            redirect_uris: client.redirect_uris,
+            allowed_scopes: client.allowed_scopes,
+            access_token_ttl: client.access_token_ttl,
+            refresh_token_ttl: client.refresh_token_ttl,
+            created_at: client.created_at.timestamp(),
+            updated_at: client.updated_at.map(|updated_at| updated_at.timestamp()),
+            client_secret: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateApiKeyData {
+    pub name: String,
+    /// Space separated scopes the key is limited to, such as `user:read`.
+    /// At least one is required.
+    pub scope: Option<String>,
+    /// Seconds until the key expires, no expiry when not set.
+    pub expires_in: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scope: String,
+    /// Unix timestamps.
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+    pub created_at: i64,
+    /// The full key, only returned when it is created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl From<ApiKeys> for ApiKeyResponse {
+    fn from(key: ApiKeys) -> Self {
+        ApiKeyResponse {
+            id: key.id,
+            name: key.name,
+            prefix: key.prefix,
+            scope: key.scope,
+            expires_at: key.expires_at.map(|expires_at| expires_at.timestamp()),
+            last_used_at: key
+                .last_used_at
+                .map(|last_used_at| last_used_at.timestamp()),
+            created_at: key.created_at.timestamp(),
+            key: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SessionResponse {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    /// Where the session was started, as reported by the CDN in front of us.
+    pub geo_hint: Option<String>,
+    /// Unix timestamps.
+    pub created_at: i64,
+    pub last_seen_at: i64,
+    pub expires_at: i64,
+    /// Whether this is the session the request was made with.
+    pub current: bool,
+}
+
+impl SessionResponse {
+    pub fn new(session: Sessions, current: Option<Uuid>) -> Self {
+        SessionResponse {
+            current: current == Some(session.id),
+            id: session.id,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            geo_hint: session.geo_hint,
+            created_at: session.created_at.timestamp(),
+            last_seen_at: session.last_seen_at.timestamp(),
+            expires_at: session.expires_at.timestamp(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct AuditQuery {
+    /// E.g. `user.login` or `admin.client_deleted`.
+    pub action: Option<String>,
+    /// `success` or `failure`.
+    pub outcome: Option<String>,
+    pub actor: Option<String>,
+    pub subject: Option<String>,
+    /// Unix timestamps, `until` exclusive.
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    /// Only events older than the one with this id, to fetch the next page.
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AuditEventResponse {
+    pub id: i64,
+    /// Unix timestamp.
+    pub occurred_at: i64,
+    pub action: String,
+    pub outcome: String,
+    pub reason: Option<String>,
+    pub actor: Option<String>,
+    pub subject: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl From<AuditEvents> for AuditEventResponse {
+    fn from(event: AuditEvents) -> Self {
+        AuditEventResponse {
+            id: event.id,
+            occurred_at: event.occurred_at.timestamp(),
+            action: event.action,
+            outcome: event.outcome,
+            reason: event.reason,
+            actor: event.actor,
+            subject: event.subject,
+            ip_address: event.ip_address,
+            user_agent: event.user_agent,
+            request_id: event.request_id,
+        }
+    }
+}
@@ -0,0 +1,619 @@
+use crate::errors::{Error, Result};
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::services::actix_requests::mfa_requests::start_mfa_challenge;
+use crate::services::actix_requests::models::{
+    AuthorizeQuery, CallbackQuery, LinkIdentityData, LinkIdentityResponse, LinkedIdentityResponse,
+};
+use crate::services::actix_requests::session_requests::login_response;
+use crate::services::actors::messages::{
+    CheckUser, ClientAuthorization, CreateAuthorizationCode, CreateAuthorizationRequest,
+    CreateUser, CreateUserIdentity, GetMfaFactor, GetUserByEmail, GetUserIdentity,
+    TakeAuthorizationRequest, UpdateActivateEmail,
+};
+use crate::services::actors::traced::TracedSend;
+use crate::services::audit::audit_service::AuditService;
+use crate::services::auth0::auth0_service::Auth0Service;
+use crate::services::auth0::models::IdTokenClaims;
+use crate::services::db::postgres_db::DbService;
+use crate::services::db::tables::OauthAuthorizationRequests;
+use crate::services::mfa::mfa_service::MfaService;
+use crate::services::oauth::consts::{
+    AUTHORIZATION_REQUEST_TTL, DATABASE_PROVIDER, PASSWORDLESS_PROVIDER, STATE_COOKIE_NAME,
+    STATE_COOKIE_PATH,
+};
+use crate::services::oauth::pkce::{code_challenge, random_token, state_binding};
+use crate::services::oidc::consts::{
+    ERROR_ACCESS_DENIED, ERROR_INTERACTION_REQUIRED, SCOPE_OPENID, SCOPE_USER_WRITE,
+};
+use crate::services::oidc::oidc_service::OidcService;
+use crate::services::sessions::session_service::SessionService;
+use crate::services::token::token_issuer::TokenIssuer;
+use actix::Addr;
+use actix_web::cookie::{time, Cookie, SameSite};
+use actix_web::http::header::LOCATION;
+use actix_web::web::{Data, Json, Query};
+use actix_web::{HttpRequest, HttpResponse};
+use data_encoding::HEXLOWER;
+use reqwest::Url;
+
+#[utoipa::path(
+    get,
+    path = "/authorize",
+    params(AuthorizeQuery),
+    responses(
+        (status = 302, description = "Redirect to the identity provider login page"),
+        (status = BAD_REQUEST, description = "Unknown connection or invalid client parameters")
+    )
+)]
+pub async fn authorize(
+    auth0_service: Data<Auth0Service>,
+    oidc_service: Data<OidcService>,
+    session_service: Data<SessionService>,
+    db: Data<Addr<DbService>>,
+    query: Query<AuthorizeQuery>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for authorize!");
+    let query = query.into_inner();
+    let client = client_authorization(&oidc_service, &query).await?;
+
+    let (url, state_cookie) = start_authorization(
+        &auth0_service,
+        &session_service,
+        &db,
+        query.connection,
+        client,
+        None,
+    )
+    .await?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, url))
+        .cookie(state_cookie)
+        .finish())
+}
+
+#[utoipa::path(
+    post,
+    path = "/user/identities/link",
+    request_body = LinkIdentityData,
+    responses(
+        (status = 200, description = "Sign in with the identity to link at authorization_url", body = LinkIdentityResponse),
+        (status = BAD_REQUEST, description = "Unknown connection"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token")
+    )
+)]
+pub async fn link_identity(
+    auth0_service: Data<Auth0Service>,
+    session_service: Data<SessionService>,
+    db: Data<Addr<DbService>>,
+    data: Json<LinkIdentityData>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for identity link!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_WRITE)?;
+    user.reject_api_key()?;
+
+    let (authorization_url, state_cookie) = start_authorization(
+        &auth0_service,
+        &session_service,
+        &db,
+        Some(data.into_inner().connection),
+        None,
+        Some(user.user_id),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok()
+        .cookie(state_cookie)
+        .json(LinkIdentityResponse { authorization_url }))
+}
+
+/// Records a pending authorization request and returns the identity provider
+/// URL to send the browser to, with the cookie that ties the request to it.
+async fn start_authorization(
+    auth0_service: &Auth0Service,
+    session_service: &SessionService,
+    db: &Addr<DbService>,
+    connection: Option<String>,
+    client: Option<ClientAuthorization>,
+    link_user_id: Option<String>,
+) -> Result<(String, Cookie<'static>)> {
+    if let Some(connection) = &connection {
+        if !auth0_service.is_allowed_connection(connection) {
+            return Err(Error::InvalidInput(format!(
+                "Unknown connection {}",
+                connection
+            )));
+        }
+    }
+
+    let pending = create_authorization_request(
+        session_service,
+        db,
+        connection.clone(),
+        client,
+        link_user_id,
+    )
+    .await?;
+    let url = auth0_service.authorization_url(
+        &pending.state,
+        &pending.nonce,
+        &pending.code_challenge,
+        connection.as_deref(),
+    )?;
+
+    Ok((url, pending.state_cookie))
+}
+
+/// An authorization request waiting for the identity provider to send the
+/// browser back to `/callback`.
+pub(crate) struct PendingAuthorization {
+    pub state: String,
+    pub nonce: String,
+    pub code_challenge: String,
+    pub state_cookie: Cookie<'static>,
+}
+
+/// Records an authorization request and the cookie that ties it to the browser.
+pub(crate) async fn create_authorization_request(
+    session_service: &SessionService,
+    db: &Addr<DbService>,
+    connection: Option<String>,
+    client: Option<ClientAuthorization>,
+    link_user_id: Option<String>,
+) -> Result<PendingAuthorization> {
+    let state = random_token()?;
+    let nonce = random_token()?;
+    let code_verifier = random_token()?;
+    let code_challenge = code_challenge(&code_verifier);
+
+    let mut state_cookie = state_cookie(session_service, state_binding(&state));
+    state_cookie.set_max_age(time::Duration::seconds(AUTHORIZATION_REQUEST_TTL));
+
+    db.send_traced(CreateAuthorizationRequest {
+        state: state.clone(),
+        code_verifier,
+        nonce: nonce.clone(),
+        connection,
+        client,
+        link_user_id,
+        expires_at: chrono::Utc::now() + chrono::Duration::seconds(AUTHORIZATION_REQUEST_TTL),
+    })
+    .await??;
+
+    Ok(PendingAuthorization {
+        state,
+        nonce,
+        code_challenge,
+        state_cookie,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/callback",
+    params(CallbackQuery),
+    responses(
+        (status = 200, description = "User successfully login", body = LoginUserResponse),
+        (status = 200, description = "Session started, the cookie is the credential", body = SessionLoginResponse),
+        (status = 200, description = "Second factor required", body = MfaChallengeResponse),
+        (status = 200, description = "Identity linked to the signed-in user", body = LinkedIdentityResponse),
+        (status = 302, description = "Redirect back to the OAuth client with a code"),
+        (status = BAD_REQUEST, description = "Unknown state, state not started in this browser or authorization error"),
+        (status = UNAUTHORIZED, description = "Invalid id_token"),
+        (status = CONFLICT, description = "Identity belongs to another user, or its email to an account it has to be linked from")
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn callback(
+    auth0_service: Data<Auth0Service>,
+    oidc_service: Data<OidcService>,
+    token_issuer: Data<TokenIssuer>,
+    mfa_service: Data<MfaService>,
+    session_service: Data<SessionService>,
+    db: Data<Addr<DbService>>,
+    query: Query<CallbackQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for callback!");
+    let query = query.into_inner();
+
+    // Checked before the state is used up, so a callback URL replayed in
+    // another browser can't log that browser in.
+    let bound = req.cookie(STATE_COOKIE_NAME).is_some_and(|cookie| {
+        openssl::memcmp::eq(
+            cookie.value().as_bytes(),
+            state_binding(&query.state).as_bytes(),
+        )
+    });
+    if !bound {
+        return Err(Error::InvalidInput(
+            "State was not started in this browser".to_string(),
+        ));
+    }
+
+    let mut response = finish_callback(
+        &auth0_service,
+        &oidc_service,
+        &token_issuer,
+        &mfa_service,
+        &session_service,
+        &db,
+        query,
+        &req,
+    )
+    .await?;
+    let mut removal = state_cookie(&session_service, String::new());
+    removal.make_removal();
+    response
+        .add_cookie(&removal)
+        .map_err(|e| Error::StringError(e.to_string()))?;
+    Ok(response)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn finish_callback(
+    auth0_service: &Auth0Service,
+    oidc_service: &OidcService,
+    token_issuer: &TokenIssuer,
+    mfa_service: &MfaService,
+    session_service: &SessionService,
+    db: &Addr<DbService>,
+    query: CallbackQuery,
+    req: &HttpRequest,
+) -> Result<HttpResponse> {
+    let request = db
+        .send_traced(TakeAuthorizationRequest { state: query.state })
+        .await??
+        .filter(|request| request.expires_at > chrono::Utc::now())
+        .ok_or(Error::InvalidInput("Unknown or expired state".to_string()))?;
+
+    if let Some(error) = query.error {
+        if let Some(redirect_uri) = &request.redirect_uri {
+            return client_redirect(
+                redirect_uri,
+                &[("error", ERROR_ACCESS_DENIED)],
+                request.client_state.as_deref(),
+            );
+        }
+        return Err(Error::InvalidInput(
+            query.error_description.unwrap_or(error),
+        ));
+    }
+    let code = query
+        .code
+        .ok_or(Error::InvalidInput("Missing code".to_string()))?;
+
+    let tokens = auth0_service
+        .exchange_authorization_code(code, request.code_verifier.clone())
+        .await?;
+    let claims = auth0_service.validate_id_token(&tokens.id_token, &request.nonce)?;
+
+    if let Some(link_user_id) = request.link_user_id {
+        AuditService::set_subject(req, link_user_id.clone());
+        let provider = link_social_identity(db, link_user_id, &claims).await?;
+        return Ok(HttpResponse::Ok().json(LinkedIdentityResponse { provider }));
+    }
+
+    let (provider, _) = split_subject(&claims.sub)?;
+    let is_database_user = provider == DATABASE_PROVIDER;
+    let user_id = ensure_local_user(db, claims).await?;
+    AuditService::set_subject(req, user_id.clone());
+
+    if request.client_id.is_some() {
+        return redirect_with_code(oidc_service, session_service, db, req, request, user_id).await;
+    }
+
+    // Auth0 tokens of social and passwordless users carry the provider's
+    // subject, not the local user id, so those users get a local token.
+    let access_token = if is_database_user {
+        tokens.access_token
+    } else {
+        token_issuer.issue_access_token(&user_id)?
+    };
+
+    if let Some(challenge) =
+        start_mfa_challenge(mfa_service, db, user_id.clone(), access_token.clone()).await?
+    {
+        return Ok(HttpResponse::Ok().json(&challenge));
+    }
+
+    login_response(session_service, req, &user_id, access_token).await
+}
+
+/// The cookie that ties a pending authorization request to the browser.
+/// `Lax`, as the identity provider redirects back with a top-level GET.
+fn state_cookie(session_service: &SessionService, value: String) -> Cookie<'static> {
+    Cookie::build(STATE_COOKIE_NAME, value)
+        .path(STATE_COOKIE_PATH)
+        .http_only(true)
+        .secure(session_service.secure_cookies())
+        .same_site(SameSite::Lax)
+        .finish()
+}
+
+/// Validates the OAuth client parameters of an `/authorize` request, if any.
+async fn client_authorization(
+    oidc_service: &OidcService,
+    query: &AuthorizeQuery,
+) -> Result<Option<ClientAuthorization>> {
+    let Some(client_id) = &query.client_id else {
+        return Ok(None);
+    };
+    let redirect_uri = query
+        .redirect_uri
+        .clone()
+        .ok_or(Error::InvalidInput("Missing redirect_uri".to_string()))?;
+
+    let client = oidc_service
+        .check_authorization_request(
+            client_id,
+            &redirect_uri,
+            query.response_type.as_deref(),
+            query.code_challenge.as_deref(),
+            query.code_challenge_method.as_deref(),
+        )
+        .await?;
+    let scope = oidc_service
+        .normalize_scope(&client, query.scope.as_deref(), SCOPE_OPENID)
+        .map_err(|e| Error::InvalidInput(e.to_string()))?;
+
+    Ok(Some(ClientAuthorization {
+        client_id: client_id.clone(),
+        redirect_uri,
+        state: query.state.clone(),
+        code_challenge: query.code_challenge.clone(),
+        nonce: query.nonce.clone(),
+        scope,
+    }))
+}
+
+/// Finishes a flow started by one of our OAuth clients by sending the user back
+/// to the client with an authorization code. The browser also gets a session,
+/// so signing it out revokes the client's refresh tokens.
+async fn redirect_with_code(
+    oidc_service: &OidcService,
+    session_service: &SessionService,
+    db: &Addr<DbService>,
+    req: &HttpRequest,
+    request: OauthAuthorizationRequests,
+    user_id: String,
+) -> Result<HttpResponse> {
+    let (Some(client_id), Some(redirect_uri), Some(scope)) =
+        (request.client_id, request.redirect_uri, request.scope)
+    else {
+        return Err(Error::InvalidInput(
+            "Incomplete client authorization request".to_string(),
+        ));
+    };
+
+    // The redirect can't carry a second-factor challenge, so clients don't get
+    // a code for users with MFA until the flow supports it.
+    let factor = db
+        .send_traced(GetMfaFactor {
+            user_id: user_id.clone(),
+        })
+        .await??;
+    if factor.is_some_and(|factor| factor.is_confirmed) {
+        return client_redirect(
+            &redirect_uri,
+            &[("error", ERROR_INTERACTION_REQUIRED)],
+            request.client_state.as_deref(),
+        );
+    }
+
+    let session = session_service.start(req, &user_id).await?;
+
+    let code = random_token()?;
+    db.send_traced(CreateAuthorizationCode {
+        code_hash: OidcService::hash_token(&code),
+        client_id,
+        user_id,
+        redirect_uri: redirect_uri.clone(),
+        code_challenge: request.client_code_challenge,
+        nonce: request.client_nonce,
+        scope,
+        session_id: session.as_ref().map(|session| session.id),
+        expires_at: chrono::Utc::now()
+            + chrono::Duration::seconds(oidc_service.authorization_code_ttl()),
+    })
+    .await??;
+
+    let mut response = client_redirect(
+        &redirect_uri,
+        &[("code", &code)],
+        request.client_state.as_deref(),
+    )?;
+    if let Some(session) = session {
+        for cookie in session.cookies {
+            response
+                .add_cookie(&cookie)
+                .map_err(|e| Error::StringError(e.to_string()))?;
+        }
+    }
+    Ok(response)
+}
+
+fn client_redirect(
+    redirect_uri: &str,
+    params: &[(&str, &str)],
+    state: Option<&str>,
+) -> Result<HttpResponse> {
+    let mut url = Url::parse(redirect_uri).map_err(|e| Error::StringError(e.to_string()))?;
+    {
+        let mut query = url.query_pairs_mut();
+        query.extend_pairs(params);
+        if let Some(state) = state {
+            query.append_pair("state", state);
+        }
+    }
+
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, url.to_string()))
+        .finish())
+}
+
+/// Resolves the local user for a verified `id_token`, creating the `users` row
+/// the first time someone signs in through the authorization code flow.
+async fn ensure_local_user(db: &Addr<DbService>, claims: IdTokenClaims) -> Result<String> {
+    let (provider, provider_user_id) = split_subject(&claims.sub)?;
+
+    if provider == DATABASE_PROVIDER {
+        let user_id = provider_user_id.to_string();
+        if !db
+            .send_traced(CheckUser {
+                id: user_id.clone(),
+            })
+            .await??
+        {
+            create_local_user(db, user_id.clone(), &claims).await?;
+        }
+        return Ok(user_id);
+    }
+
+    ensure_social_user(db, provider, provider_user_id, &claims).await
+}
+
+/// Maps a social identity to a local user. An identity seen for the first time is
+/// linked to the existing user with the same email if both the identity provider
+/// and we verified it; an unverified local account has to link it explicitly
+/// from a signed-in session. Otherwise the identity gets a new user.
+async fn ensure_social_user(
+    db: &Addr<DbService>,
+    provider: &str,
+    provider_user_id: &str,
+    claims: &IdTokenClaims,
+) -> Result<String> {
+    let identity = db
+        .send_traced(GetUserIdentity {
+            provider: provider.to_string(),
+            provider_user_id: provider_user_id.to_string(),
+        })
+        .await??;
+    if let Some(identity) = identity {
+        return Ok(identity.user_id);
+    }
+
+    let existing = match (&claims.email, claims.email_verified) {
+        (Some(email), Some(true)) => {
+            db.send_traced(GetUserByEmail {
+                email: email.clone(),
+            })
+            .await??
+        }
+        _ => None,
+    };
+
+    // A magic link proves the user owns the mailbox, as a passwordless code does.
+    let proves_email = provider == PASSWORDLESS_PROVIDER;
+    let user_id = match existing {
+        Some(user) if !user.is_email_activate && !proves_email => {
+            return Err(Error::Conflict(
+                "An account with this email exists, sign in to link this identity".to_string(),
+            ));
+        }
+        Some(user) => {
+            if !user.is_email_activate {
+                db.send_traced(UpdateActivateEmail {
+                    user_id: user.auth_id.clone(),
+                })
+                .await??;
+            }
+            tracing::info!(
+                "Linking {} identity to existing user {}",
+                provider,
+                user.auth_id
+            );
+            user.auth_id
+        }
+        None => {
+            let user_id = generate_local_user_id()?;
+            create_local_user(db, user_id.clone(), claims).await?;
+            user_id
+        }
+    };
+
+    db.send_traced(CreateUserIdentity {
+        provider: provider.to_string(),
+        provider_user_id: provider_user_id.to_string(),
+        user_id: user_id.clone(),
+    })
+    .await??;
+
+    Ok(user_id)
+}
+
+/// Links a social identity to a signed-in user, unless it already belongs to
+/// another user. Returns the identity provider.
+async fn link_social_identity(
+    db: &Addr<DbService>,
+    user_id: String,
+    claims: &IdTokenClaims,
+) -> Result<String> {
+    let (provider, provider_user_id) = split_subject(&claims.sub)?;
+    if provider == DATABASE_PROVIDER {
+        return Err(Error::InvalidInput(
+            "Only social identities can be linked".to_string(),
+        ));
+    }
+
+    let identity = db
+        .send_traced(GetUserIdentity {
+            provider: provider.to_string(),
+            provider_user_id: provider_user_id.to_string(),
+        })
+        .await??;
+    match identity {
+        Some(identity) if identity.user_id == user_id => return Ok(provider.to_string()),
+        Some(_) => {
+            return Err(Error::Conflict(
+                "Identity is linked to another user".to_string(),
+            ))
+        }
+        None => {}
+    }
+
+    db.send_traced(CreateUserIdentity {
+        provider: provider.to_string(),
+        provider_user_id: provider_user_id.to_string(),
+        user_id: user_id.clone(),
+    })
+    .await??;
+    tracing::info!("Linked {} identity to user {}", provider, user_id);
+
+    Ok(provider.to_string())
+}
+
+/// Splits an Auth0 subject into the provider and the provider's user id.
+fn split_subject(sub: &str) -> Result<(&str, &str)> {
+    sub.split_once('|')
+        .ok_or(Error::InvalidInput("Unexpected subject format".to_string()))
+}
+
+async fn create_local_user(
+    db: &Addr<DbService>,
+    user_id: String,
+    claims: &IdTokenClaims,
+) -> Result<()> {
+    let email = claims
+        .email
+        .clone()
+        .ok_or(Error::InvalidInput("id_token has no email".to_string()))?;
+
+    db.send_traced(CreateUser {
+        id: user_id,
+        username: claims.nickname.clone().unwrap_or_else(|| email.clone()),
+        password: String::new(),
+        email,
+    })
+    .await?
+}
+
+/// Social subjects don't fit `users.auth_id`, so social-only users get an id in
+/// the same 24 hex character shape as Auth0 database user ids.
+fn generate_local_user_id() -> Result<String> {
+    let mut bytes = [0u8; 12];
+    openssl::rand::rand_bytes(&mut bytes)?;
+    Ok(HEXLOWER.encode(&bytes))
+}
@@ -0,0 +1,734 @@
+use crate::errors::{Error, Result};
+use crate::services::actix_requests::models::RegisteredUserData;
+use crate::services::actix_requests::requests::verified_subject;
+use crate::services::actors::messages::{
+    CheckUser, CreateRefreshToken, GetMfaFactor, GetRefreshToken, GetUser, GetUserByEmail,
+    GetUserIdentity, IsTokenRevoked, RevokeToken, TakeAuthorizationCode, TakeRefreshToken,
+};
+use crate::services::actors::traced::TracedSend;
+use crate::services::auth0::auth0_service::Auth0Service;
+use crate::services::db::postgres_db::DbService;
+use crate::services::db::tables::OauthClients;
+use crate::services::oauth::consts::DATABASE_PROVIDER;
+use crate::services::oauth::pkce::{code_challenge, random_token};
+use crate::services::oidc::consts::{
+    ERROR_INVALID_GRANT, ERROR_INVALID_REQUEST, ERROR_INVALID_SCOPE, ERROR_UNAUTHORIZED_CLIENT,
+    GRANT_TYPE_AUTHORIZATION_CODE, GRANT_TYPE_CLIENT_CREDENTIALS, GRANT_TYPE_PASSWORD,
+    GRANT_TYPE_REFRESH_TOKEN, SCOPE_EMAIL, SCOPE_OPENID, TOKEN_TYPE_HINT_ACCESS_TOKEN,
+    TOKEN_TYPE_HINT_REFRESH_TOKEN,
+};
+use crate::services::oidc::models::{
+    IntrospectionRequest, IntrospectionResponse, RevocationRequest, TokenRequest, TokenResponse,
+};
+use crate::services::oidc::oidc_service::{has_scope, oauth_error, OidcService};
+use crate::services::token::consts::TOKEN_TYPE_BEARER;
+use crate::services::token::token_issuer::TokenIssuer;
+use crate::utils::basic_credentials;
+use actix::Addr;
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::web::{Data, Form};
+use actix_web::{HttpRequest, HttpResponse};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+#[utoipa::path(
+    get,
+    path = "/.well-known/openid-configuration",
+    responses(
+        (status = 200, description = "OpenID Provider metadata", body = DiscoveryDocument)
+    )
+)]
+pub async fn openid_configuration(
+    oidc_service: Data<OidcService>,
+    token_issuer: Data<TokenIssuer>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for OpenID configuration!");
+    let document = oidc_service.discovery_document(token_issuer.signing_algorithms());
+    Ok(HttpResponse::Ok().json(document))
+}
+
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "Public keys of the token signing keys", body = JwkSet)
+    )
+)]
+pub async fn jwks(token_issuer: Data<TokenIssuer>) -> Result<HttpResponse> {
+    tracing::info!("Getting request for JWKS!");
+    Ok(HttpResponse::Ok().json(token_issuer.jwks()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/oauth/token",
+    request_body(content = TokenRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Tokens issued", body = TokenResponse),
+        (status = BAD_REQUEST, description = "Invalid grant or request"),
+        (status = UNAUTHORIZED, description = "Client authentication failed")
+    )
+)]
+pub async fn token(
+    oidc_service: Data<OidcService>,
+    token_issuer: Data<TokenIssuer>,
+    auth0_service: Data<Auth0Service>,
+    db: Data<Addr<DbService>>,
+    data: Form<TokenRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for token!");
+    let data = data.into_inner();
+
+    let (client_id, client_secret) =
+        client_credentials(&req, data.client_id.clone(), data.client_secret.clone())?;
+    let client = oidc_service
+        .authenticate_client(client_id.as_deref(), client_secret.as_deref())
+        .await?;
+    oidc_service.check_grant_type(&client, &data.grant_type)?;
+
+    let response = match data.grant_type.as_str() {
+        GRANT_TYPE_AUTHORIZATION_CODE => {
+            authorization_code_grant(&oidc_service, &token_issuer, &db, &client, data).await?
+        }
+        GRANT_TYPE_PASSWORD => {
+            password_grant(
+                &oidc_service,
+                &token_issuer,
+                &auth0_service,
+                &db,
+                &client,
+                data,
+            )
+            .await?
+        }
+        GRANT_TYPE_REFRESH_TOKEN => {
+            refresh_token_grant(&oidc_service, &token_issuer, &db, &client, data).await?
+        }
+        GRANT_TYPE_CLIENT_CREDENTIALS => {
+            client_credentials_grant(&oidc_service, &token_issuer, &client, data)?
+        }
+        _ => unreachable!("grant type was checked against the supported grant types"),
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/oauth/introspect",
+    request_body(content = IntrospectionRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Token state, only `active` for inactive tokens", body = IntrospectionResponse),
+        (status = UNAUTHORIZED, description = "Client authentication failed")
+    )
+)]
+pub async fn introspect(
+    oidc_service: Data<OidcService>,
+    token_issuer: Data<TokenIssuer>,
+    auth0_service: Data<Auth0Service>,
+    db: Data<Addr<DbService>>,
+    data: Form<IntrospectionRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for introspection!");
+    let data = data.into_inner();
+
+    let (client_id, client_secret) =
+        client_credentials(&req, data.client_id.clone(), data.client_secret.clone())?;
+    oidc_service
+        .authenticate_confidential_client(client_id.as_deref(), client_secret.as_deref())
+        .await?;
+
+    let token_hash = OidcService::hash_token(&data.token);
+    let cache = oidc_service.introspection_cache();
+
+    let response = match cache.get(&token_hash) {
+        Some(response) => response,
+        None => {
+            let response = introspect_token(
+                &token_issuer,
+                &auth0_service,
+                &db,
+                &data.token,
+                data.token_type_hint.as_deref(),
+            )
+            .await?;
+            cache.insert(token_hash, response.clone());
+            response
+        }
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/oauth/revoke",
+    request_body(content = RevocationRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Token revoked, or it was unknown or already invalid"),
+        (status = BAD_REQUEST, description = "Token was issued to another client"),
+        (status = UNAUTHORIZED, description = "Client authentication failed")
+    )
+)]
+pub async fn revoke(
+    oidc_service: Data<OidcService>,
+    token_issuer: Data<TokenIssuer>,
+    auth0_service: Data<Auth0Service>,
+    db: Data<Addr<DbService>>,
+    data: Form<RevocationRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for revocation!");
+    let data = data.into_inner();
+
+    let (client_id, client_secret) =
+        client_credentials(&req, data.client_id.clone(), data.client_secret.clone())?;
+    let client = oidc_service
+        .authenticate_client(client_id.as_deref(), client_secret.as_deref())
+        .await?;
+
+    let revoked = revoke_token(
+        &token_issuer,
+        &auth0_service,
+        &db,
+        &client,
+        &data.token,
+        data.token_type_hint.as_deref(),
+    )
+    .await?;
+
+    if !revoked {
+        tracing::info!("Revocation of an unknown or invalid token");
+    }
+
+    oidc_service
+        .introspection_cache()
+        .remove(&OidcService::hash_token(&data.token));
+
+    // RFC 7009 §2.2: unknown and invalid tokens get the same response.
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Returns the client id and secret from HTTP Basic authentication, falling
+/// back to the ones sent in the form body.
+fn client_credentials(
+    req: &HttpRequest,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+) -> Result<(Option<String>, Option<String>)> {
+    Ok(match basic_credentials(req)? {
+        Some((client_id, client_secret)) => (Some(client_id), Some(client_secret)),
+        None => (client_id, client_secret),
+    })
+}
+
+async fn introspect_token(
+    token_issuer: &TokenIssuer,
+    auth0_service: &Auth0Service,
+    db: &Addr<DbService>,
+    token: &str,
+    token_type_hint: Option<&str>,
+) -> Result<IntrospectionResponse> {
+    let refresh_first = token_type_hint == Some(TOKEN_TYPE_HINT_REFRESH_TOKEN);
+
+    if refresh_first {
+        if let Some(response) = introspect_refresh_token(db, token).await? {
+            return Ok(response);
+        }
+    }
+    if let Some(response) = introspect_access_token(token_issuer, auth0_service, db, token).await? {
+        return Ok(response);
+    }
+    if !refresh_first {
+        if let Some(response) = introspect_refresh_token(db, token).await? {
+            return Ok(response);
+        }
+    }
+
+    Ok(IntrospectionResponse::default())
+}
+
+/// Returns `None` when the token isn't a valid access token of ours or Auth0's.
+async fn introspect_access_token(
+    token_issuer: &TokenIssuer,
+    auth0_service: &Auth0Service,
+    db: &Addr<DbService>,
+    token: &str,
+) -> Result<Option<IntrospectionResponse>> {
+    let (claims, user_exists) = if let Ok(claims) = token_issuer.verify(token) {
+        // Client credentials tokens are issued to the client itself.
+        let user_exists = if claims
+            .client_id
+            .as_deref()
+            .is_some_and(|client_id| claims.sub == OidcService::client_subject(client_id))
+        {
+            true
+        } else {
+            db.send_traced(CheckUser {
+                id: claims.sub.clone(),
+            })
+            .await??
+        };
+        let Value::Object(claims) = serde_json::to_value(claims)? else {
+            return Ok(None);
+        };
+        (claims, user_exists)
+    } else if let Ok(claims) = auth0_service.decode_access_token(token) {
+        let user_exists = match claims.get("sub").and_then(Value::as_str) {
+            Some(sub) => auth0_user_exists(db, sub).await?,
+            None => false,
+        };
+        (claims, user_exists)
+    } else {
+        return Ok(None);
+    };
+
+    let token_id = OidcService::revocation_id(claims.get("jti").and_then(Value::as_str), token);
+    if !user_exists || db.send_traced(IsTokenRevoked { token_id }).await?? {
+        return Ok(Some(IntrospectionResponse::default()));
+    }
+
+    Ok(Some(active_response(claims, TOKEN_TYPE_HINT_ACCESS_TOKEN)))
+}
+
+async fn introspect_refresh_token(
+    db: &Addr<DbService>,
+    token: &str,
+) -> Result<Option<IntrospectionResponse>> {
+    let Some(refresh_token) = db
+        .send_traced(GetRefreshToken {
+            token_hash: OidcService::hash_token(token),
+        })
+        .await??
+    else {
+        return Ok(None);
+    };
+
+    if refresh_token.expires_at <= chrono::Utc::now()
+        || !db
+            .send_traced(CheckUser {
+                id: refresh_token.user_id.clone(),
+            })
+            .await??
+    {
+        return Ok(Some(IntrospectionResponse::default()));
+    }
+
+    Ok(Some(IntrospectionResponse {
+        active: true,
+        sub: Some(refresh_token.user_id),
+        scope: Some(refresh_token.scope),
+        client_id: Some(refresh_token.client_id),
+        exp: Some(refresh_token.expires_at.timestamp()),
+        iat: Some(refresh_token.created_at.timestamp()),
+        token_type: Some(TOKEN_TYPE_HINT_REFRESH_TOKEN.to_string()),
+        ..IntrospectionResponse::default()
+    }))
+}
+
+/// Whether the local user behind an Auth0 subject still exists.
+async fn auth0_user_exists(db: &Addr<DbService>, sub: &str) -> Result<bool> {
+    match sub.split_once('|') {
+        Some((DATABASE_PROVIDER, user_id)) => {
+            db.send_traced(CheckUser {
+                id: user_id.to_string(),
+            })
+            .await?
+        }
+        Some((provider, provider_user_id)) => Ok(db
+            .send_traced(GetUserIdentity {
+                provider: provider.to_string(),
+                provider_user_id: provider_user_id.to_string(),
+            })
+            .await??
+            .is_some()),
+        None => Ok(false),
+    }
+}
+
+/// Tries the token as the hinted type first. Returns `false` when it is
+/// neither one of our refresh tokens nor a valid access token.
+async fn revoke_token(
+    token_issuer: &TokenIssuer,
+    auth0_service: &Auth0Service,
+    db: &Addr<DbService>,
+    client: &OauthClients,
+    token: &str,
+    token_type_hint: Option<&str>,
+) -> Result<bool> {
+    let refresh_first = token_type_hint == Some(TOKEN_TYPE_HINT_REFRESH_TOKEN);
+
+    if refresh_first && revoke_refresh_token(db, client, token).await? {
+        return Ok(true);
+    }
+    if revoke_access_token(token_issuer, auth0_service, db, client, token).await? {
+        return Ok(true);
+    }
+    if !refresh_first {
+        return revoke_refresh_token(db, client, token).await;
+    }
+
+    Ok(false)
+}
+
+/// Returns `false` when the token isn't one of our refresh tokens.
+async fn revoke_refresh_token(
+    db: &Addr<DbService>,
+    client: &OauthClients,
+    token: &str,
+) -> Result<bool> {
+    let token_hash = OidcService::hash_token(token);
+    let Some(refresh_token) = db
+        .send_traced(GetRefreshToken {
+            token_hash: token_hash.clone(),
+        })
+        .await??
+    else {
+        return Ok(false);
+    };
+
+    if refresh_token.client_id != client.client_id {
+        return Err(not_issued_to_client());
+    }
+
+    db.send_traced(TakeRefreshToken {
+        token_hash,
+        client_id: client.client_id.clone(),
+    })
+    .await??;
+    Ok(true)
+}
+
+/// Adds an access token the client was issued to the denylist. Returns
+/// `false` when the token isn't a valid access token. Tokens without a
+/// client, e.g. from `/login`, and Auth0's tokens were issued to no client,
+/// so no client may revoke them (RFC 7009 §2.1).
+async fn revoke_access_token(
+    token_issuer: &TokenIssuer,
+    auth0_service: &Auth0Service,
+    db: &Addr<DbService>,
+    client: &OauthClients,
+    token: &str,
+) -> Result<bool> {
+    let claims = match token_issuer.verify(token) {
+        Ok(claims) => claims,
+        Err(_) if auth0_service.decode_access_token(token).is_ok() => {
+            return Err(not_issued_to_client());
+        }
+        Err(_) => return Ok(false),
+    };
+    if claims.client_id.as_deref() != Some(client.client_id.as_str()) {
+        return Err(not_issued_to_client());
+    }
+
+    let expires_at =
+        chrono::DateTime::from_timestamp(claims.exp, 0).unwrap_or_else(chrono::Utc::now);
+    db.send_traced(RevokeToken {
+        token_id: OidcService::revocation_id(Some(&claims.jti), token),
+        expires_at,
+    })
+    .await??;
+    Ok(true)
+}
+
+fn not_issued_to_client() -> Error {
+    oauth_error(
+        ERROR_UNAUTHORIZED_CLIENT,
+        "Token was issued to another client",
+    )
+}
+
+fn active_response(mut claims: Map<String, Value>, token_type: &str) -> IntrospectionResponse {
+    let mut take_string = |key: &str| match claims.remove(key) {
+        Some(Value::String(value)) => Some(value),
+        _ => None,
+    };
+
+    let sub = take_string("sub");
+    let scope = take_string("scope");
+    let client_id = take_string("client_id").or_else(|| take_string("azp"));
+    let iss = take_string("iss");
+    let jti = take_string("jti");
+
+    IntrospectionResponse {
+        active: true,
+        sub,
+        scope,
+        client_id,
+        iss,
+        jti,
+        exp: claims.remove("exp").and_then(|exp| exp.as_i64()),
+        iat: claims.remove("iat").and_then(|iat| iat.as_i64()),
+        token_type: Some(token_type.to_string()),
+        claims,
+    }
+}
+
+async fn authorization_code_grant(
+    oidc_service: &OidcService,
+    token_issuer: &TokenIssuer,
+    db: &Addr<DbService>,
+    client: &OauthClients,
+    data: TokenRequest,
+) -> Result<TokenResponse> {
+    let code = required(data.code, "code")?;
+    let redirect_uri = required(data.redirect_uri, "redirect_uri")?;
+
+    let code = db
+        .send_traced(TakeAuthorizationCode {
+            code_hash: OidcService::hash_token(&code),
+        })
+        .await??
+        .filter(|code| code.expires_at > chrono::Utc::now())
+        .ok_or_else(|| oauth_error(ERROR_INVALID_GRANT, "Unknown or expired code"))?;
+
+    if code.client_id != client.client_id || code.redirect_uri != redirect_uri {
+        return Err(oauth_error(
+            ERROR_INVALID_GRANT,
+            "Code was issued to another client or redirect_uri",
+        ));
+    }
+
+    match (&code.code_challenge, &data.code_verifier) {
+        (Some(challenge), Some(verifier))
+            if openssl::memcmp::eq(challenge.as_bytes(), code_challenge(verifier).as_bytes()) => {}
+        (Some(_), _) => {
+            return Err(oauth_error(ERROR_INVALID_GRANT, "Invalid code_verifier"));
+        }
+        (None, _) => {}
+    }
+
+    issue_tokens(
+        oidc_service,
+        token_issuer,
+        db,
+        client,
+        code.user_id,
+        code.scope,
+        code.nonce,
+        code.session_id,
+    )
+    .await
+}
+
+async fn password_grant(
+    oidc_service: &OidcService,
+    token_issuer: &TokenIssuer,
+    auth0_service: &Auth0Service,
+    db: &Addr<DbService>,
+    client: &OauthClients,
+    data: TokenRequest,
+) -> Result<TokenResponse> {
+    let username = required(data.username, "username")?;
+    let password = required(data.password, "password")?;
+    let scope = oidc_service.normalize_scope(client, data.scope.as_deref(), SCOPE_OPENID)?;
+
+    let user = db
+        .send_traced(GetUserByEmail {
+            email: username.clone(),
+        })
+        .await??
+        .ok_or_else(|| oauth_error(ERROR_INVALID_GRANT, "Invalid username or password"))?;
+
+    let result = auth0_service
+        .send_request_to_login(RegisteredUserData {
+            id: user.auth_id.clone(),
+            username,
+            password,
+        })
+        .await
+        .map_err(|_| oauth_error(ERROR_INVALID_GRANT, "Invalid username or password"))?;
+    // Emails aren't unique, so the token, not the lookup, says who logged in.
+    verified_subject(auth0_service.extract_user_id(&result.token)?, &user.auth_id)
+        .map_err(|_| oauth_error(ERROR_INVALID_GRANT, "Invalid username or password"))?;
+
+    // The password grant has no way to ask for a second factor, so it is not
+    // available to users with MFA.
+    let factor = db
+        .send_traced(GetMfaFactor {
+            user_id: user.auth_id.clone(),
+        })
+        .await??;
+    if factor.is_some_and(|factor| factor.is_confirmed) {
+        return Err(oauth_error(
+            ERROR_INVALID_GRANT,
+            "Second factor required, which the password grant does not support",
+        ));
+    }
+
+    issue_tokens(
+        oidc_service,
+        token_issuer,
+        db,
+        client,
+        user.auth_id,
+        scope,
+        None,
+        None,
+    )
+    .await
+}
+
+async fn refresh_token_grant(
+    oidc_service: &OidcService,
+    token_issuer: &TokenIssuer,
+    db: &Addr<DbService>,
+    client: &OauthClients,
+    data: TokenRequest,
+) -> Result<TokenResponse> {
+    let refresh_token = required(data.refresh_token, "refresh_token")?;
+
+    // Only the client the token was issued to consumes it; for any other
+    // client it is as unknown as a made-up token.
+    let stored = db
+        .send_traced(TakeRefreshToken {
+            token_hash: OidcService::hash_token(&refresh_token),
+            client_id: client.client_id.clone(),
+        })
+        .await??
+        .filter(|token| token.expires_at > chrono::Utc::now())
+        .ok_or_else(|| oauth_error(ERROR_INVALID_GRANT, "Unknown or expired refresh token"))?;
+
+    // A refresh may narrow the original scope but never widen it.
+    let scope = match &data.scope {
+        Some(requested) => {
+            let requested = oidc_service.normalize_scope(client, Some(requested), "")?;
+            if !requested
+                .split_whitespace()
+                .all(|scope| has_scope(&stored.scope, scope))
+            {
+                return Err(oauth_error(
+                    ERROR_INVALID_SCOPE,
+                    "Requested scope exceeds the original grant",
+                ));
+            }
+            requested
+        }
+        None => stored.scope,
+    };
+
+    if !db
+        .send_traced(CheckUser {
+            id: stored.user_id.clone(),
+        })
+        .await??
+    {
+        return Err(oauth_error(ERROR_INVALID_GRANT, "User no longer exists"));
+    }
+
+    issue_tokens(
+        oidc_service,
+        token_issuer,
+        db,
+        client,
+        stored.user_id,
+        scope,
+        None,
+        stored.session_id,
+    )
+    .await
+}
+
+fn client_credentials_grant(
+    oidc_service: &OidcService,
+    token_issuer: &TokenIssuer,
+    client: &OauthClients,
+    data: TokenRequest,
+) -> Result<TokenResponse> {
+    let scope = oidc_service.normalize_scope(client, data.scope.as_deref(), "")?;
+    let scope = (!scope.is_empty()).then_some(scope);
+
+    let ttl = OidcService::access_token_ttl(client, token_issuer.access_token_ttl());
+    let access_token = token_issuer.issue_scoped_access_token(
+        &OidcService::client_subject(&client.client_id),
+        Some(&client.client_id),
+        scope.as_deref(),
+        ttl,
+    )?;
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: TOKEN_TYPE_BEARER.to_string(),
+        expires_in: ttl,
+        refresh_token: None,
+        id_token: None,
+        scope,
+    })
+}
+
+/// Issues the access token for a user, plus an `id_token` for the `openid`
+/// scope and a refresh token for clients allowed to use the refresh grant.
+#[allow(clippy::too_many_arguments)]
+async fn issue_tokens(
+    oidc_service: &OidcService,
+    token_issuer: &TokenIssuer,
+    db: &Addr<DbService>,
+    client: &OauthClients,
+    user_id: String,
+    scope: String,
+    nonce: Option<String>,
+    session_id: Option<Uuid>,
+) -> Result<TokenResponse> {
+    let ttl = OidcService::access_token_ttl(client, token_issuer.access_token_ttl());
+    let access_token = token_issuer.issue_scoped_access_token(
+        &user_id,
+        Some(&client.client_id),
+        Some(&scope),
+        ttl,
+    )?;
+
+    let id_token = if has_scope(&scope, SCOPE_OPENID) {
+        let email = if has_scope(&scope, SCOPE_EMAIL) {
+            db.send_traced(GetUser {
+                id: user_id.clone(),
+            })
+            .await??
+            .map(|user| user.email)
+        } else {
+            None
+        };
+        Some(token_issuer.issue_id_token(&user_id, &client.client_id, nonce, email)?)
+    } else {
+        None
+    };
+
+    let refresh_token = if client
+        .grant_types
+        .iter()
+        .any(|grant| grant == GRANT_TYPE_REFRESH_TOKEN)
+    {
+        let refresh_token = random_token()?;
+        db.send_traced(CreateRefreshToken {
+            token_hash: OidcService::hash_token(&refresh_token),
+            client_id: client.client_id.clone(),
+            user_id,
+            scope: scope.clone(),
+            session_id,
+            expires_at: chrono::Utc::now()
+                + chrono::Duration::seconds(oidc_service.refresh_token_ttl(client)),
+        })
+        .await??;
+        Some(refresh_token)
+    } else {
+        None
+    };
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: TOKEN_TYPE_BEARER.to_string(),
+        expires_in: ttl,
+        refresh_token,
+        id_token,
+        scope: Some(scope),
+    })
+}
+
+fn required(value: Option<String>, name: &str) -> Result<String> {
+    value.ok_or_else(|| oauth_error(ERROR_INVALID_REQUEST, &format!("Missing {}", name)))
+}
@@ -0,0 +1,139 @@
+use crate::errors::{Error, Result};
+use crate::services::actix_requests::mfa_requests::start_mfa_challenge;
+use crate::services::actix_requests::models::{PasswordlessStartData, PasswordlessVerifyData};
+use crate::services::actix_requests::oauth_requests::create_authorization_request;
+use crate::services::actix_requests::session_requests::login_response;
+use crate::services::actors::messages::{GetUserByEmail, UpdateActivateEmail};
+use crate::services::actors::traced::TracedSend;
+use crate::services::audit::audit_service::AuditService;
+use crate::services::auth0::auth0_service::Auth0Service;
+use crate::services::auth0::consts::{PASSWORDLESS_SEND_CODE, PASSWORDLESS_SEND_LINK};
+use crate::services::db::postgres_db::DbService;
+use crate::services::mfa::mfa_service::MfaService;
+use crate::services::sessions::session_service::SessionService;
+use crate::services::token::token_issuer::TokenIssuer;
+use actix::Addr;
+use actix_web::web::{Data, Json};
+use actix_web::{HttpRequest, HttpResponse};
+
+#[utoipa::path(
+    post,
+    path = "/passwordless/start",
+    request_body = PasswordlessStartData,
+    responses(
+        (status = 200, description = "Code or magic link sent if the email is registered"),
+        (status = BAD_REQUEST, description = "Unknown delivery method")
+    )
+)]
+pub async fn passwordless_start(
+    auth0_service: Data<Auth0Service>,
+    session_service: Data<SessionService>,
+    db: Data<Addr<DbService>>,
+    data: Json<PasswordlessStartData>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for passwordless start!");
+    let data = data.into_inner();
+    let mut response = HttpResponse::Ok();
+
+    // A magic link ends at `/callback` like a social login, so it needs an
+    // authorization request bound to this browser.
+    let link = match data.send.as_deref().unwrap_or(PASSWORDLESS_SEND_CODE) {
+        PASSWORDLESS_SEND_CODE => None,
+        PASSWORDLESS_SEND_LINK => {
+            let pending =
+                create_authorization_request(&session_service, &db, None, None, None).await?;
+            response.cookie(pending.state_cookie.clone());
+            Some(auth0_service.magic_link_params(
+                &pending.state,
+                &pending.nonce,
+                &pending.code_challenge,
+            ))
+        }
+        send => return Err(Error::InvalidInput(format!("Unknown send method {}", send))),
+    };
+
+    // Answer the same way, and equally fast, for unknown emails so the
+    // endpoint can't be used to probe which addresses are registered: the
+    // message is sent after the response, and Auth0 errors are only logged.
+    let registered = match db
+        .send_traced(GetUserByEmail {
+            email: data.email.clone(),
+        })
+        .await?
+    {
+        Ok(user) => user.is_some(),
+        // A shared address can't be resolved to one account at verify time.
+        Err(Error::Conflict(_)) => false,
+        Err(e) => return Err(e),
+    };
+    if registered {
+        let auth0_service = auth0_service.into_inner();
+        actix::spawn(async move {
+            if let Err(e) = auth0_service
+                .send_passwordless_start(data.email, link)
+                .await
+            {
+                tracing::error!("Failed to send passwordless message: {}", e);
+            }
+        });
+    }
+
+    Ok(response.body("If the email is registered, a sign-in message was sent"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/passwordless/verify",
+    request_body = PasswordlessVerifyData,
+    responses(
+        (status = 200, description = "User successfully login", body = LoginUserResponse),
+        (status = 200, description = "Session started, the cookie is the credential", body = SessionLoginResponse),
+        (status = 200, description = "Second factor required", body = MfaChallengeResponse),
+        (status = UNAUTHORIZED, description = "Invalid or expired code"),
+        (status = CONFLICT, description = "More than one account uses this email")
+    )
+)]
+pub async fn passwordless_verify(
+    auth0_service: Data<Auth0Service>,
+    mfa_service: Data<MfaService>,
+    token_issuer: Data<TokenIssuer>,
+    session_service: Data<SessionService>,
+    db: Data<Addr<DbService>>,
+    data: Json<PasswordlessVerifyData>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for passwordless verify!");
+    let data = data.into_inner();
+    AuditService::set_subject(&req, data.email.clone());
+
+    auth0_service
+        .verify_passwordless_otp(data.email.clone(), data.code)
+        .await?;
+
+    // Only answered after the code is checked, so a conflict on a shared
+    // address isn't visible to someone who doesn't own the mailbox.
+    let user = db
+        .send_traced(GetUserByEmail { email: data.email })
+        .await??
+        .ok_or(Error::Unauthorized)?;
+    AuditService::set_subject(&req, user.auth_id.clone());
+    // The code was sent to the mailbox, so entering it proves the user owns it.
+    if !user.is_email_activate {
+        db.send_traced(UpdateActivateEmail {
+            user_id: user.auth_id.clone(),
+        })
+        .await??;
+    }
+
+    // Auth0 identifies passwordless users by their email connection, so the
+    // token is issued locally for the matching `users` row instead.
+    let token = token_issuer.issue_access_token(&user.auth_id)?;
+
+    if let Some(challenge) =
+        start_mfa_challenge(&mfa_service, &db, user.auth_id.clone(), token.clone()).await?
+    {
+        return Ok(HttpResponse::Ok().json(&challenge));
+    }
+
+    login_response(&session_service, &req, &user.auth_id, token).await
+}
@@ -1,8 +1,16 @@
-use crate::errors::Result;
-use crate::services::actix_requests::models::{RegisteredUserData, UpdatePasswordData, UserData};
-use crate::services::actors::messages::{CheckUser, CreateUser};
+use crate::errors::{Error, Result};
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::services::actix_requests::mfa_requests::start_mfa_challenge;
+use crate::services::actix_requests::models::{ProfileResponse, RegisteredUserData, UserData};
+use crate::services::actix_requests::session_requests::login_response;
+use crate::services::actors::messages::{CheckUser, CreateUser, GetUser};
+use crate::services::actors::traced::TracedSend;
+use crate::services::audit::audit_service::AuditService;
 use crate::services::auth0::auth0_service::Auth0Service;
 use crate::services::db::postgres_db::DbService;
+use crate::services::mfa::mfa_service::MfaService;
+use crate::services::oidc::consts::{SCOPE_USER_READ, SCOPE_USER_WRITE};
+use crate::services::sessions::session_service::SessionService;
 use actix::Addr;
 use actix_web::web::{Data, Json};
 use actix_web::{HttpRequest, HttpResponse};
@@ -12,16 +20,20 @@ use actix_web::{HttpRequest, HttpResponse};
     path = "/register",
     responses(
         (status = 200, description = "User successfully registered", body = UserData),
-        (status = BAD_REQUEST, description = "User already registered")
+        (status = CONFLICT, description = "User already registered"),
+        (status = UNPROCESSABLE_ENTITY, description = "Password rejected by the password policy")
     )
 )]
 pub async fn register(
     user: Json<UserData>,
     auth0_service: Data<Auth0Service>,
     db: Data<Addr<DbService>>,
+    req: HttpRequest,
 ) -> Result<HttpResponse> {
-    log::info!("Getting request for register!");
+    tracing::info!("Getting request for register!");
+    AuditService::set_subject(&req, user.email.clone());
     let auth0_response = auth0_service.register_user(user.clone()).await?;
+    AuditService::set_subject(&req, auth0_response._id.to_string());
 
     let user = CreateUser {
         id: auth0_response._id.to_string(),
@@ -30,7 +42,7 @@ pub async fn register(
         email: user.email.to_string(),
     };
 
-    db.send(user).await??;
+    db.send_traced(user).await??;
 
     Ok(HttpResponse::Ok().json(&auth0_response))
 }
@@ -40,25 +52,52 @@ pub async fn register(
     path = "/login",
     responses(
         (status = 200, description = "User successfully login", body = RegisteredUserData),
-        (status = BAD_REQUEST, description = "User not found")
+        (status = 200, description = "Session started, the cookie is the credential", body = SessionLoginResponse),
+        (status = 200, description = "Second factor required", body = MfaChallengeResponse),
+        (status = NOT_FOUND, description = "User not found"),
+        (status = UNAUTHORIZED, description = "Wrong username or password, or credentials for another user"),
+        (status = TOO_MANY_REQUESTS, description = "Too many failed attempts")
     )
 )]
 pub async fn login(
     auth0_service: Data<Auth0Service>,
+    mfa_service: Data<MfaService>,
+    session_service: Data<SessionService>,
     db: Data<Addr<DbService>>,
     user: Json<RegisteredUserData>,
+    req: HttpRequest,
 ) -> Result<HttpResponse> {
+    AuditService::set_subject(&req, user.id.clone());
     let if_user = CheckUser {
         id: user.id.clone(),
     };
 
-    if db.send(if_user).await?? {
-        log::info!("Getting request for login!");
+    if db.send_traced(if_user).await?? {
+        tracing::info!("Getting request for login!");
+        let claimed_id = user.id.clone();
         let result = auth0_service.send_request_to_login(user.0).await?;
-        Ok(HttpResponse::Ok().json(&result))
+        // The body id is only a claim; the token says who actually logged in.
+        let user_id = verified_subject(auth0_service.extract_user_id(&result.token)?, &claimed_id)?;
+
+        if let Some(challenge) =
+            start_mfa_challenge(&mfa_service, &db, user_id.clone(), result.token.clone()).await?
+        {
+            return Ok(HttpResponse::Ok().json(&challenge));
+        }
+
+        login_response(&session_service, &req, &user_id, result.token).await
     } else {
-        Ok(HttpResponse::BadRequest().finish())
+        Err(Error::UserNotFound)
+    }
+}
+
+/// Returns the authenticated `subject` if it is the user the request names.
+pub(crate) fn verified_subject(subject: String, claimed_id: &str) -> Result<String> {
+    if subject != claimed_id {
+        tracing::warn!("Credentials for {} used to claim {}", subject, claimed_id);
+        return Err(Error::Unauthorized);
     }
+    Ok(subject)
 }
 
 #[utoipa::path(
@@ -66,68 +105,62 @@ pub async fn login(
     path = "/change_password",
     responses(
         (status = 200, description = "Successfully send email to change password"),
-        (status = BAD_REQUEST, description = "User not found")
+        (status = FORBIDDEN, description = "Not allowed for API keys, clients or tokens without the user:write scope"),
+        (status = NOT_FOUND, description = "User not found")
     )
 )]
 pub async fn change_password(
     db: Data<Addr<DbService>>,
-    user: Json<UpdatePasswordData>,
     auth0_service: Data<Auth0Service>,
+    caller: AuthenticatedUser,
+    req: HttpRequest,
 ) -> Result<HttpResponse> {
-    let if_user = CheckUser {
-        id: user.user_id.clone(),
-    };
-
-    if db.send(if_user).await?? {
-        log::info!("Getting request for change password!");
-
-        auth0_service
-            .send_request_to_change_pass(user.user_id.clone(), user.email.clone())
-            .await?;
+    caller.require_user()?;
+    caller.reject_api_key()?;
+    caller.require_scope(SCOPE_USER_WRITE)?;
+    AuditService::set_subject(&req, caller.user_id.clone());
+
+    // The reset goes to the caller's own account and the address stored for it.
+    let user = db
+        .send_traced(GetUser {
+            id: caller.user_id.clone(),
+        })
+        .await??
+        .ok_or(Error::UserNotFound)?;
+
+    tracing::info!("Getting request for change password!");
+
+    auth0_service
+        .send_request_to_change_pass(user.auth_id, user.email)
+        .await?;
 
-        Ok(HttpResponse::Ok().body("Sent email to change password!"))
-    } else {
-        Ok(HttpResponse::BadRequest().finish())
-    }
+    Ok(HttpResponse::Ok().body("Sent email to change password!"))
 }
 
 #[utoipa::path(
     get,
-    path = "/profile/{user_id}",
+    path = "/profile",
     responses(
-        (status = 200, description = "Successfully get user profile", body = String),
-        (status = BAD_REQUEST, description = "User not found")
+        (status = 200, description = "Successfully get user profile", body = ProfileResponse),
+        (status = FORBIDDEN, description = "Not allowed for clients or tokens without the user:read scope"),
+        (status = NOT_FOUND, description = "User not found")
     )
 )]
 pub async fn profile(
-    auth0_service: Data<Auth0Service>,
     db_service: Data<Addr<DbService>>,
-    req: HttpRequest,
+    caller: AuthenticatedUser,
 ) -> Result<HttpResponse> {
-    log::info!("Getting request for profile!");
-
-    let Some(auth_header) = req.headers().get("Authorization") else {
-        return Ok(HttpResponse::Unauthorized().finish());
-    };
-
-    let token = auth_header.to_str()?;
-
-    let access_token = token
-        .strip_prefix("Bearer ")
-        .ok_or(crate::errors::Error::StringError(
-            "Invalid token".to_string(),
-        ))?;
-
-    let user_id = auth0_service.extract_user_id(access_token)?;
-
-    let if_user = CheckUser { id: user_id };
-
-    if !db_service.send(if_user).await?? {
-        return Ok(HttpResponse::BadRequest().finish());
-    }
-
-    let profile = auth0_service
-        .send_request_to_get_profile(access_token)
-        .await?;
-    Ok(HttpResponse::Ok().json(profile))
+    tracing::info!("Getting request for profile!");
+    caller.require_user()?;
+    caller.require_scope(SCOPE_USER_READ)?;
+
+    // Served from the users row, so it works for every kind of credential.
+    let user = db_service
+        .send_traced(GetUser {
+            id: caller.user_id.clone(),
+        })
+        .await??
+        .ok_or(Error::UserNotFound)?;
+
+    Ok(HttpResponse::Ok().json(ProfileResponse::from(user)))
 }
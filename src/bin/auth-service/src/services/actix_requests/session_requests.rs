@@ -0,0 +1,185 @@
+use crate::errors::{Error, Result};
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::services::actix_requests::models::{
+    LoginUserResponse, SessionLoginResponse, SessionResponse,
+};
+use crate::services::admin::admin_service::AdminService;
+use crate::services::oidc::consts::{SCOPE_USER_READ, SCOPE_USER_WRITE};
+use crate::services::sessions::session_service::SessionService;
+use actix_web::web::{Data, Path};
+use actix_web::{HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+#[utoipa::path(
+    get,
+    path = "/user/sessions",
+    responses(
+        (status = 200, description = "Active sessions of the user", body = [SessionResponse]),
+        (status = UNAUTHORIZED, description = "Missing or invalid token")
+    )
+)]
+pub async fn list_sessions(
+    session_service: Data<SessionService>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for listing sessions!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_READ)?;
+    let sessions = list(&session_service, &user.user_id, user.session_id).await?;
+    Ok(HttpResponse::Ok().json(sessions))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/user/sessions/{id}",
+    responses(
+        (status = 200, description = "Session signed out and its refresh tokens revoked"),
+        (status = NOT_FOUND, description = "Unknown session"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "API keys can't sign out sessions")
+    )
+)]
+pub async fn revoke_session(
+    session_service: Data<SessionService>,
+    user: AuthenticatedUser,
+    id: Path<Uuid>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for revoking a session!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_WRITE)?;
+    user.reject_api_key()?;
+    let id = id.into_inner();
+
+    if !session_service.revoke(&user.user_id, id).await? {
+        return Err(Error::NotFound(format!("Session {}", id)));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/user/sessions",
+    responses(
+        (status = 200, description = "All other sessions signed out and their refresh tokens revoked"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "API keys can't sign out sessions")
+    )
+)]
+pub async fn revoke_other_sessions(
+    session_service: Data<SessionService>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for revoking other sessions!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_WRITE)?;
+    user.reject_api_key()?;
+
+    session_service
+        .revoke_all(&user.user_id, user.session_id)
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/{user_id}/sessions",
+    responses(
+        (status = 200, description = "Active sessions of the user", body = [SessionResponse]),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn list_user_sessions(
+    admin_service: Data<AdminService>,
+    session_service: Data<SessionService>,
+    user: AuthenticatedUser,
+    user_id: Path<String>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for listing user sessions!");
+    admin_service.authorize(&user)?;
+
+    let sessions = list(&session_service, &user_id, None).await?;
+    Ok(HttpResponse::Ok().json(sessions))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{user_id}/sessions",
+    responses(
+        (status = 200, description = "All sessions of the user signed out and their refresh tokens revoked"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token"),
+        (status = FORBIDDEN, description = "Not an admin")
+    )
+)]
+pub async fn revoke_user_sessions(
+    admin_service: Data<AdminService>,
+    session_service: Data<SessionService>,
+    user: AuthenticatedUser,
+    user_id: Path<String>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for revoking user sessions!");
+    admin_service.authorize(&user)?;
+
+    let revoked = session_service.revoke_all(&user_id, None).await?;
+    tracing::info!("Signed out {} sessions of user {}", revoked, user_id);
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    post,
+    path = "/logout",
+    responses(
+        (status = 200, description = "Session ended and its cookies cleared"),
+        (status = FORBIDDEN, description = "Missing or invalid CSRF token")
+    )
+)]
+pub async fn logout(
+    session_service: Data<SessionService>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for logout!");
+    if let Some(cookie) = req.cookie(session_service.cookie_name()) {
+        session_service.end(cookie.value()).await?;
+    }
+
+    let mut response = HttpResponse::Ok();
+    for cookie in session_service.removal_cookies() {
+        response.cookie(cookie);
+    }
+    Ok(response.finish())
+}
+
+/// Finishes an interactive login. With sessions enabled the session cookie is
+/// the only credential handed out, so the bearer token never reaches page
+/// scripts; otherwise the token is returned in the body.
+pub(crate) async fn login_response(
+    session_service: &SessionService,
+    req: &HttpRequest,
+    user_id: &str,
+    token: String,
+) -> Result<HttpResponse> {
+    let Some(session) = session_service.start(req, user_id).await? else {
+        return Ok(HttpResponse::Ok().json(LoginUserResponse { token }));
+    };
+
+    let mut response = HttpResponse::Ok();
+    for cookie in session.cookies {
+        response.cookie(cookie);
+    }
+    Ok(response.json(SessionLoginResponse {
+        session_id: session.id,
+    }))
+}
+
+async fn list(
+    session_service: &SessionService,
+    user_id: &str,
+    current: Option<Uuid>,
+) -> Result<Vec<SessionResponse>> {
+    Ok(session_service
+        .list(user_id)
+        .await?
+        .into_iter()
+        .map(|session| SessionResponse::new(session, current))
+        .collect())
+}
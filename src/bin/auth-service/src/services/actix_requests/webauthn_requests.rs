@@ -0,0 +1,254 @@
+use crate::errors::{Error, Result};
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::services::actix_requests::models::{
+    WebauthnCredentialResponse, WebauthnLoginOptionsData,
+};
+use crate::services::actix_requests::session_requests::login_response;
+use crate::services::actors::messages::{
+    CheckUser, CreateWebauthnChallenge, CreateWebauthnCredential, GetUser,
+    GetUserWebauthnCredentials, GetWebauthnCredential, TakeWebauthnChallenge,
+    UpdateWebauthnSignCount,
+};
+use crate::services::actors::traced::TracedSend;
+use crate::services::audit::audit_service::AuditService;
+use crate::services::db::postgres_db::DbService;
+use crate::services::db::tables::WebauthnChallenges;
+use crate::services::oidc::consts::SCOPE_USER_WRITE;
+use crate::services::sessions::session_service::SessionService;
+use crate::services::token::token_issuer::TokenIssuer;
+use crate::services::webauthn::consts::{CEREMONY_AUTHENTICATION, CEREMONY_REGISTRATION};
+use crate::services::webauthn::models::{AuthenticationCredential, RegistrationCredential};
+use crate::services::webauthn::webauthn_service::WebauthnService;
+use actix::Addr;
+use actix_web::web::{Data, Json};
+use actix_web::{HttpRequest, HttpResponse};
+
+#[utoipa::path(
+    post,
+    path = "/user/webauthn/register/options",
+    responses(
+        (status = 200, description = "Credential creation options", body = CreationOptions),
+        (status = NOT_FOUND, description = "User not found"),
+        (status = UNAUTHORIZED, description = "Missing or invalid token")
+    )
+)]
+pub async fn webauthn_register_options(
+    webauthn_service: Data<WebauthnService>,
+    db: Data<Addr<DbService>>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for WebAuthn registration options!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_WRITE)?;
+    user.reject_api_key()?;
+    let user_id = user.user_id;
+
+    let Some(user) = db
+        .send_traced(GetUser {
+            id: user_id.clone(),
+        })
+        .await??
+    else {
+        return Err(Error::UserNotFound);
+    };
+
+    let existing = db
+        .send_traced(GetUserWebauthnCredentials {
+            user_id: user_id.clone(),
+        })
+        .await??
+        .into_iter()
+        .map(|credential| credential.credential_id)
+        .collect();
+
+    let challenge = webauthn_service.generate_challenge()?;
+    db.send_traced(CreateWebauthnChallenge {
+        challenge: challenge.clone(),
+        user_id: Some(user_id.clone()),
+        ceremony: CEREMONY_REGISTRATION.to_string(),
+        expires_at: chrono::Utc::now()
+            + chrono::Duration::seconds(webauthn_service.challenge_ttl()),
+    })
+    .await??;
+
+    let options = webauthn_service.creation_options(challenge, &user_id, user.username, existing);
+    Ok(HttpResponse::Ok().json(options))
+}
+
+#[utoipa::path(
+    post,
+    path = "/user/webauthn/register/verify",
+    request_body = RegistrationCredential,
+    responses(
+        (status = 200, description = "Credential registered", body = WebauthnCredentialResponse),
+        (status = BAD_REQUEST, description = "Credential already registered"),
+        (status = UNAUTHORIZED, description = "Attestation verification failed")
+    )
+)]
+pub async fn webauthn_register_verify(
+    webauthn_service: Data<WebauthnService>,
+    db: Data<Addr<DbService>>,
+    credential: Json<RegistrationCredential>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for WebAuthn registration!");
+    user.require_user()?;
+    user.require_scope(SCOPE_USER_WRITE)?;
+    user.reject_api_key()?;
+    let user_id = user.user_id;
+
+    let client_data = webauthn_service.parse_client_data(&credential.response.client_data_json)?;
+    let challenge = take_challenge(&db, &client_data.challenge, CEREMONY_REGISTRATION).await?;
+    if challenge.user_id.as_deref() != Some(user_id.as_str()) {
+        return Err(Error::WebauthnVerification(
+            "Challenge was issued to another user".to_string(),
+        ));
+    }
+
+    let verified = webauthn_service.verify_registration(&credential, &challenge.challenge)?;
+
+    let existing = db
+        .send_traced(GetWebauthnCredential {
+            credential_id: verified.credential_id.clone(),
+        })
+        .await??;
+    if existing.is_some() {
+        return Err(Error::InvalidInput(
+            "Credential already registered".to_string(),
+        ));
+    }
+
+    db.send_traced(CreateWebauthnCredential {
+        credential_id: verified.credential_id.clone(),
+        user_id,
+        public_key: verified.public_key,
+        alg: verified.alg as i32,
+        sign_count: verified.sign_count,
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(WebauthnCredentialResponse {
+        credential_id: verified.credential_id,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/webauthn/login/options",
+    request_body = WebauthnLoginOptionsData,
+    responses(
+        (status = 200, description = "Credential request options", body = RequestOptions)
+    )
+)]
+pub async fn webauthn_login_options(
+    webauthn_service: Data<WebauthnService>,
+    db: Data<Addr<DbService>>,
+    data: Option<Json<WebauthnLoginOptionsData>>,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for WebAuthn login options!");
+    // A user id only restricts who may answer the challenge; the options
+    // look the same whether or not that user exists or has passkeys.
+    let user_id = data.and_then(|data| data.into_inner().user_id);
+
+    let challenge = webauthn_service.generate_challenge()?;
+    db.send_traced(CreateWebauthnChallenge {
+        challenge: challenge.clone(),
+        user_id,
+        ceremony: CEREMONY_AUTHENTICATION.to_string(),
+        expires_at: chrono::Utc::now()
+            + chrono::Duration::seconds(webauthn_service.challenge_ttl()),
+    })
+    .await??;
+
+    let options = webauthn_service.request_options(challenge);
+    Ok(HttpResponse::Ok().json(options))
+}
+
+#[utoipa::path(
+    post,
+    path = "/webauthn/login/verify",
+    request_body = AuthenticationCredential,
+    responses(
+        (status = 200, description = "User successfully login", body = LoginUserResponse),
+        (status = 200, description = "Session started, the cookie is the credential", body = SessionLoginResponse),
+        (status = UNAUTHORIZED, description = "Assertion verification failed")
+    )
+)]
+pub async fn webauthn_login_verify(
+    webauthn_service: Data<WebauthnService>,
+    token_issuer: Data<TokenIssuer>,
+    session_service: Data<SessionService>,
+    db: Data<Addr<DbService>>,
+    credential: Json<AuthenticationCredential>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    tracing::info!("Getting request for WebAuthn login!");
+
+    let client_data = webauthn_service.parse_client_data(&credential.response.client_data_json)?;
+    let challenge = take_challenge(&db, &client_data.challenge, CEREMONY_AUTHENTICATION).await?;
+
+    let credential_id = WebauthnService::normalize_credential_id(&credential.raw_id)?;
+    let stored = db
+        .send_traced(GetWebauthnCredential { credential_id })
+        .await??
+        .ok_or(Error::Unauthorized)?;
+    AuditService::set_subject(&req, stored.user_id.clone());
+
+    if challenge
+        .user_id
+        .as_ref()
+        .is_some_and(|user_id| *user_id != stored.user_id)
+    {
+        return Err(Error::Unauthorized);
+    }
+    if let Some(user_handle) = &credential.response.user_handle {
+        if user_handle.trim_end_matches('=') != WebauthnService::user_handle(&stored.user_id) {
+            return Err(Error::Unauthorized);
+        }
+    }
+
+    let sign_count = webauthn_service.verify_authentication(
+        &credential,
+        &challenge.challenge,
+        &stored.public_key,
+        stored.alg as i64,
+        stored.sign_count,
+    )?;
+
+    db.send_traced(UpdateWebauthnSignCount {
+        credential_id: stored.credential_id,
+        sign_count,
+    })
+    .await??;
+
+    if !db
+        .send_traced(CheckUser {
+            id: stored.user_id.clone(),
+        })
+        .await??
+    {
+        return Err(Error::Unauthorized);
+    }
+
+    let token = token_issuer.issue_access_token(&stored.user_id)?;
+    login_response(&session_service, &req, &stored.user_id, token).await
+}
+
+async fn take_challenge(
+    db: &Addr<DbService>,
+    challenge: &str,
+    ceremony: &str,
+) -> Result<WebauthnChallenges> {
+    let challenge = db
+        .send_traced(TakeWebauthnChallenge {
+            challenge: challenge.trim_end_matches('=').to_string(),
+        })
+        .await??
+        .ok_or(Error::WebauthnVerification("Unknown challenge".to_string()))?;
+
+    if challenge.ceremony != ceremony || challenge.expires_at < chrono::Utc::now() {
+        return Err(Error::WebauthnVerification("Challenge expired".to_string()));
+    }
+
+    Ok(challenge)
+}
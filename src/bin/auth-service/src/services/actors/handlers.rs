@@ -1,12 +1,37 @@
 use crate::services::actors::messages::{
-    CheckIfRegisteredUser, CheckUser, CreateUser, DeleteUser, UpdateActivateEmail, UpdateEmail,
-    UpdateUsername,
+    ApiKeyOwner, CheckDatabase, CheckIfRegisteredUser, CheckUser, ClaimMfaChallengeAttempt,
+    ConfirmMfaFactor, CreateApiKey, CreateAuditEvent, CreateAuthorizationCode,
+    CreateAuthorizationRequest, CreateMfaChallenge, CreateMfaFactor, CreateOauthClient,
+    CreateRefreshToken, CreateSession, CreateUser, CreateUserIdentity, CreateWebauthnChallenge,
+    CreateWebauthnCredential, DeleteMfaChallenge, DeleteOauthClient, DeleteSession, DeleteUser,
+    DeleteUserSessions, GetApiKeyByPrefix, GetApiKeys, GetAuditEvents, GetMfaFactor,
+    GetOauthClient, GetOauthClients, GetRefreshToken, GetSession, GetSigningKeys, GetUser,
+    GetUserByEmail, GetUserIdentity, GetUserSessions, GetUserWebauthnCredentials,
+    GetWebauthnCredential, IsTokenRevoked, Ping, PurgeExpired, ReplaceRecoveryCodes, RevokeApiKey,
+    RevokeSigningKey, RevokeToken, RotateSigningKeys, RotationOutcome, Stop, TakeAuthorizationCode,
+    TakeAuthorizationRequest, TakeRefreshToken, TakeWebauthnChallenge, TouchApiKey, TouchSession,
+    UpdateActivateEmail, UpdateEmail, UpdateMfaLastUsedStep, UpdateOauthClient,
+    UpdateOauthClientSecret, UpdateUsername, UpdateWebauthnSignCount, UseRecoveryCode,
 };
 use crate::services::db::postgres_db::DbService;
-use crate::services::db::tables::Users;
-use actix::{AtomicResponse, Handler, WrapFuture};
-use diesel::{ExpressionMethods, QueryDsl};
-use diesel_async::RunQueryDsl;
+use crate::services::db::schema::{
+    api_keys, audit_events, authorization_codes, mfa_challenges, mfa_factors, mfa_recovery_codes,
+    oauth_authorization_requests, oauth_clients, refresh_tokens, revoked_tokens, sessions,
+    signing_keys, user_identities, users, webauthn_challenges, webauthn_credentials,
+};
+use crate::services::db::tables::{
+    ApiKeys, AuditEvents, AuthorizationCodes, MfaChallenges, MfaFactors, MfaRecoveryCodes,
+    OauthAuthorizationRequests, OauthClients, RefreshTokens, RevokedTokens, Sessions, SigningKeys,
+    UserIdentities, Users, WebauthnChallenges, WebauthnCredentials,
+};
+use crate::services::token::consts::{
+    KEY_STATE_ACTIVE, KEY_STATE_PENDING, KEY_STATE_RETIRING, KEY_STATE_REVOKED,
+    SIGNING_KEYS_LOCK_ID,
+};
+use actix::{ActorContext, AtomicResponse, Handler, WrapFuture};
+use diesel::{BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
 
 impl Handler<CreateUser> for DbService {
     type Result = AtomicResponse<Self, crate::errors::Result<()>>;
@@ -31,7 +56,7 @@ impl Handler<CreateUser> for DbService {
                 .await?;
             Ok(())
         };
-        log::info!("Creating user {}", msg.id);
+        tracing::info!("Creating user {}", msg.id);
 
         let db = self.clone();
 
@@ -55,7 +80,7 @@ impl Handler<UpdateActivateEmail> for DbService {
                 .await?;
             Ok(())
         };
-        log::info!("Updating user is_activate_email {}", msg.user_id);
+        tracing::info!("Updating user is_activate_email {}", msg.user_id);
 
         let db = self.clone();
         AtomicResponse::new(Box::pin(query.into_actor(&db)))
@@ -78,7 +103,7 @@ impl Handler<DeleteUser> for DbService {
             .await?;
             Ok(())
         };
-        log::info!("Deleting user {}", msg.user_id);
+        tracing::info!("Deleting user {}", msg.user_id);
 
         let db = self.clone();
         AtomicResponse::new(Box::pin(query.into_actor(&db)))
@@ -100,7 +125,7 @@ impl Handler<UpdateEmail> for DbService {
                 .await?;
             Ok(())
         };
-        log::info!("Updating user email {}", msg.user_id);
+        tracing::info!("Updating user email {}", msg.user_id);
 
         let db = self.clone();
         AtomicResponse::new(Box::pin(query.into_actor(&db)))
@@ -122,7 +147,7 @@ impl Handler<UpdateUsername> for DbService {
                 .await?;
             Ok(())
         };
-        log::info!("Updating user username {}", msg.user_id);
+        tracing::info!("Updating user username {}", msg.user_id);
 
         let db = self.clone();
         AtomicResponse::new(Box::pin(query.into_actor(&db)))
@@ -144,7 +169,7 @@ impl Handler<CheckUser> for DbService {
                 .await?;
             Ok(user > 0)
         };
-        log::info!("Checking user {}", msg.id);
+        tracing::info!("Checking user {}", msg.id);
 
         let db = self.clone();
         AtomicResponse::new(Box::pin(query.into_actor(&db)))
@@ -167,9 +192,1585 @@ impl Handler<CheckIfRegisteredUser> for DbService {
 
             Ok(user > 0)
         };
-        log::info!("Checking if user is registered");
+        tracing::info!("Checking if user is registered");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetUser> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<Users>>>;
+
+    fn handle(&mut self, msg: GetUser, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.id.clone();
+        let query = async move {
+            let user = users::table
+                .select((
+                    users::auth_id,
+                    users::username,
+                    users::email,
+                    users::is_email_activate,
+                    users::created_at,
+                    users::updated_at,
+                ))
+                .filter(users::auth_id.eq(user_id))
+                .first::<Users>(&mut conn.await?)
+                .await
+                .optional()?;
+            Ok(user)
+        };
+        tracing::info!("Getting user {}", msg.id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetUserByEmail> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<Users>>>;
+
+    fn handle(&mut self, msg: GetUserByEmail, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let user = users::table
+                .select((
+                    users::auth_id,
+                    users::username,
+                    users::email,
+                    users::is_email_activate,
+                    users::created_at,
+                    users::updated_at,
+                ))
+                .filter(users::email.eq(msg.email))
+                .limit(2)
+                .load::<Users>(&mut conn.await?)
+                .await?;
+            if user.len() > 1 {
+                return Err(crate::errors::Error::Conflict(
+                    "More than one account uses this email".to_string(),
+                ));
+            }
+            Ok(user.into_iter().next())
+        };
+        tracing::info!("Getting user by email");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CreateMfaFactor> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateMfaFactor, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let factor = MfaFactors {
+                user_id,
+                encrypted_secret: msg.encrypted_secret,
+                is_confirmed: false,
+                last_used_step: None,
+                created_at: chrono::Utc::now(),
+                confirmed_at: None,
+            };
+
+            let _ = diesel::insert_into(mfa_factors::table)
+                .values(&factor)
+                .on_conflict(mfa_factors::user_id)
+                .do_update()
+                .set((
+                    mfa_factors::encrypted_secret.eq(&factor.encrypted_secret),
+                    mfa_factors::is_confirmed.eq(false),
+                    mfa_factors::last_used_step.eq(None::<i64>),
+                    mfa_factors::created_at.eq(factor.created_at),
+                    mfa_factors::confirmed_at.eq(None::<chrono::DateTime<chrono::Utc>>),
+                ))
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Creating MFA factor for user {}", msg.user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetMfaFactor> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<MfaFactors>>>;
+
+    fn handle(&mut self, msg: GetMfaFactor, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let factor = mfa_factors::table
+                .filter(mfa_factors::user_id.eq(user_id))
+                .first::<MfaFactors>(&mut conn.await?)
+                .await
+                .optional()?;
+            Ok(factor)
+        };
+        tracing::info!("Getting MFA factor for user {}", msg.user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<ConfirmMfaFactor> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: ConfirmMfaFactor, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let _ = diesel::update(mfa_factors::table)
+                .filter(mfa_factors::user_id.eq(user_id))
+                .set((
+                    mfa_factors::is_confirmed.eq(true),
+                    mfa_factors::last_used_step.eq(msg.step),
+                    mfa_factors::confirmed_at.eq(chrono::Utc::now()),
+                ))
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Confirming MFA factor for user {}", msg.user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<UpdateMfaLastUsedStep> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<bool>>;
+
+    fn handle(&mut self, msg: UpdateMfaLastUsedStep, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let updated = diesel::update(mfa_factors::table)
+                .filter(mfa_factors::user_id.eq(user_id))
+                .filter(
+                    mfa_factors::last_used_step
+                        .is_null()
+                        .or(mfa_factors::last_used_step.lt(msg.step)),
+                )
+                .set(mfa_factors::last_used_step.eq(msg.step))
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(updated > 0)
+        };
+        tracing::info!("Updating MFA last used step for user {}", msg.user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<ReplaceRecoveryCodes> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: ReplaceRecoveryCodes, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let now = chrono::Utc::now();
+            let codes = msg
+                .code_hashes
+                .into_iter()
+                .map(|code_hash| MfaRecoveryCodes {
+                    code_hash,
+                    user_id: user_id.clone(),
+                    created_at: now,
+                    used_at: None,
+                })
+                .collect::<Vec<_>>();
+
+            let mut conn = conn.await?;
+            conn.transaction::<_, crate::errors::Error, _>(|conn| {
+                async move {
+                    diesel::delete(
+                        mfa_recovery_codes::table.filter(mfa_recovery_codes::user_id.eq(user_id)),
+                    )
+                    .execute(conn)
+                    .await?;
+                    diesel::insert_into(mfa_recovery_codes::table)
+                        .values(codes)
+                        .execute(conn)
+                        .await?;
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await
+        };
+        tracing::info!("Replacing MFA recovery codes for user {}", msg.user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<UseRecoveryCode> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<bool>>;
+
+    fn handle(&mut self, msg: UseRecoveryCode, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let updated = diesel::update(mfa_recovery_codes::table)
+                .filter(mfa_recovery_codes::code_hash.eq(msg.code_hash))
+                .filter(mfa_recovery_codes::user_id.eq(user_id))
+                .filter(mfa_recovery_codes::used_at.is_null())
+                .set(mfa_recovery_codes::used_at.eq(chrono::Utc::now()))
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(updated > 0)
+        };
+        tracing::info!("Using MFA recovery code for user {}", msg.user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CreateMfaChallenge> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateMfaChallenge, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let challenge = MfaChallenges {
+                token_hash: msg.token_hash,
+                user_id,
+                encrypted_access_token: msg.encrypted_access_token,
+                attempts: 0,
+                created_at: chrono::Utc::now(),
+                expires_at: msg.expires_at,
+            };
+
+            let _ = diesel::insert_into(mfa_challenges::table)
+                .values(challenge)
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Creating MFA challenge for user {}", msg.user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<ClaimMfaChallengeAttempt> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<MfaChallenges>>>;
+
+    fn handle(&mut self, msg: ClaimMfaChallengeAttempt, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            // Check and count in one statement so concurrent guesses can't all
+            // pass the limit before any of them is recorded.
+            let challenge = diesel::update(
+                mfa_challenges::table
+                    .filter(mfa_challenges::token_hash.eq(msg.token_hash))
+                    .filter(mfa_challenges::attempts.lt(msg.max_attempts)),
+            )
+            .set(mfa_challenges::attempts.eq(mfa_challenges::attempts + 1))
+            .get_result::<MfaChallenges>(&mut conn.await?)
+            .await
+            .optional()?;
+            Ok(challenge)
+        };
+        tracing::info!("Claiming MFA challenge attempt");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<DeleteMfaChallenge> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: DeleteMfaChallenge, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let _ = diesel::delete(
+                mfa_challenges::table.filter(mfa_challenges::token_hash.eq(msg.token_hash)),
+            )
+            .execute(&mut conn.await?)
+            .await?;
+            Ok(())
+        };
+        tracing::info!("Deleting MFA challenge");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CreateWebauthnChallenge> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateWebauthnChallenge, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let ceremony = msg.ceremony.clone();
+        let query = async move {
+            let challenge = WebauthnChallenges {
+                challenge: msg.challenge,
+                user_id: msg.user_id,
+                ceremony: msg.ceremony,
+                created_at: chrono::Utc::now(),
+                expires_at: msg.expires_at,
+            };
+
+            let _ = diesel::insert_into(webauthn_challenges::table)
+                .values(challenge)
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Creating WebAuthn {} challenge", ceremony);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<TakeWebauthnChallenge> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<WebauthnChallenges>>>;
+
+    fn handle(&mut self, msg: TakeWebauthnChallenge, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let challenge = diesel::delete(
+                webauthn_challenges::table.filter(webauthn_challenges::challenge.eq(msg.challenge)),
+            )
+            .get_result::<WebauthnChallenges>(&mut conn.await?)
+            .await
+            .optional()?;
+            Ok(challenge)
+        };
+        tracing::info!("Taking WebAuthn challenge");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CreateWebauthnCredential> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateWebauthnCredential, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let credential = WebauthnCredentials {
+                credential_id: msg.credential_id,
+                user_id,
+                public_key: msg.public_key,
+                alg: msg.alg,
+                sign_count: msg.sign_count,
+                created_at: chrono::Utc::now(),
+                last_used_at: None,
+            };
+
+            let _ = diesel::insert_into(webauthn_credentials::table)
+                .values(credential)
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Creating WebAuthn credential for user {}", msg.user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetWebauthnCredential> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<WebauthnCredentials>>>;
+
+    fn handle(&mut self, msg: GetWebauthnCredential, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let credential = webauthn_credentials::table
+                .filter(webauthn_credentials::credential_id.eq(msg.credential_id))
+                .first::<WebauthnCredentials>(&mut conn.await?)
+                .await
+                .optional()?;
+            Ok(credential)
+        };
+        tracing::info!("Getting WebAuthn credential");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetUserWebauthnCredentials> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Vec<WebauthnCredentials>>>;
+
+    fn handle(&mut self, msg: GetUserWebauthnCredentials, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let credentials = webauthn_credentials::table
+                .filter(webauthn_credentials::user_id.eq(user_id))
+                .load::<WebauthnCredentials>(&mut conn.await?)
+                .await?;
+            Ok(credentials)
+        };
+        tracing::info!("Getting WebAuthn credentials for user {}", msg.user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<UpdateWebauthnSignCount> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: UpdateWebauthnSignCount, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let _ = diesel::update(webauthn_credentials::table)
+                .filter(webauthn_credentials::credential_id.eq(msg.credential_id))
+                .set((
+                    webauthn_credentials::sign_count.eq(msg.sign_count),
+                    webauthn_credentials::last_used_at.eq(chrono::Utc::now()),
+                ))
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Updating WebAuthn credential sign count");
 
         let db = self.clone();
         AtomicResponse::new(Box::pin(query.into_actor(&db)))
     }
 }
+
+impl Handler<CreateAuthorizationRequest> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateAuthorizationRequest, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let request = OauthAuthorizationRequests {
+                state: msg.state,
+                code_verifier: msg.code_verifier,
+                nonce: msg.nonce,
+                created_at: chrono::Utc::now(),
+                expires_at: msg.expires_at,
+                connection: msg.connection,
+                client_id: None,
+                redirect_uri: None,
+                client_state: None,
+                client_code_challenge: None,
+                client_nonce: None,
+                scope: None,
+                link_user_id: msg.link_user_id,
+            };
+            let request = match msg.client {
+                Some(client) => OauthAuthorizationRequests {
+                    client_id: Some(client.client_id),
+                    redirect_uri: Some(client.redirect_uri),
+                    client_state: client.state,
+                    client_code_challenge: client.code_challenge,
+                    client_nonce: client.nonce,
+                    scope: Some(client.scope),
+                    ..request
+                },
+                None => request,
+            };
+
+            let _ = diesel::insert_into(oauth_authorization_requests::table)
+                .values(request)
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Creating authorization request");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<TakeAuthorizationRequest> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<OauthAuthorizationRequests>>>;
+
+    fn handle(&mut self, msg: TakeAuthorizationRequest, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let request = diesel::delete(
+                oauth_authorization_requests::table
+                    .filter(oauth_authorization_requests::state.eq(msg.state)),
+            )
+            .get_result::<OauthAuthorizationRequests>(&mut conn.await?)
+            .await
+            .optional()?;
+            Ok(request)
+        };
+        tracing::info!("Taking authorization request");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetUserIdentity> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<UserIdentities>>>;
+
+    fn handle(&mut self, msg: GetUserIdentity, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let provider = msg.provider.clone();
+        let query = async move {
+            let identity = user_identities::table
+                .filter(user_identities::provider.eq(provider))
+                .filter(user_identities::provider_user_id.eq(msg.provider_user_id))
+                .first::<UserIdentities>(&mut conn.await?)
+                .await
+                .optional()?;
+            Ok(identity)
+        };
+        tracing::info!("Getting {} identity", msg.provider);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CreateUserIdentity> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateUserIdentity, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let identity = UserIdentities {
+                provider: msg.provider,
+                provider_user_id: msg.provider_user_id,
+                user_id,
+                linked_at: chrono::Utc::now(),
+            };
+
+            let _ = diesel::insert_into(user_identities::table)
+                .values(identity)
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Linking identity to user {}", msg.user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CreateAuthorizationCode> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateAuthorizationCode, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let client_id = msg.client_id.clone();
+        let query = async move {
+            let code = AuthorizationCodes {
+                code_hash: msg.code_hash,
+                client_id,
+                user_id: msg.user_id,
+                redirect_uri: msg.redirect_uri,
+                code_challenge: msg.code_challenge,
+                nonce: msg.nonce,
+                scope: msg.scope,
+                created_at: chrono::Utc::now(),
+                expires_at: msg.expires_at,
+                session_id: msg.session_id,
+            };
+
+            let _ = diesel::insert_into(authorization_codes::table)
+                .values(code)
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Creating authorization code for client {}", msg.client_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<TakeAuthorizationCode> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<AuthorizationCodes>>>;
+
+    fn handle(&mut self, msg: TakeAuthorizationCode, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let code = diesel::delete(
+                authorization_codes::table.filter(authorization_codes::code_hash.eq(msg.code_hash)),
+            )
+            .get_result::<AuthorizationCodes>(&mut conn.await?)
+            .await
+            .optional()?;
+            Ok(code)
+        };
+        tracing::info!("Taking authorization code");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CreateRefreshToken> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateRefreshToken, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let client_id = msg.client_id.clone();
+        let query = async move {
+            let token = RefreshTokens {
+                token_hash: msg.token_hash,
+                client_id,
+                user_id: msg.user_id,
+                scope: msg.scope,
+                created_at: chrono::Utc::now(),
+                expires_at: msg.expires_at,
+                session_id: msg.session_id,
+            };
+
+            let _ = diesel::insert_into(refresh_tokens::table)
+                .values(token)
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Creating refresh token for client {}", msg.client_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<TakeRefreshToken> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<RefreshTokens>>>;
+
+    fn handle(&mut self, msg: TakeRefreshToken, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let token = diesel::delete(
+                refresh_tokens::table
+                    .filter(refresh_tokens::token_hash.eq(msg.token_hash))
+                    .filter(refresh_tokens::client_id.eq(msg.client_id)),
+            )
+            .get_result::<RefreshTokens>(&mut conn.await?)
+            .await
+            .optional()?;
+            Ok(token)
+        };
+        tracing::info!("Taking refresh token");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<RotateSigningKeys> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<RotationOutcome>>;
+
+    fn handle(&mut self, msg: RotateSigningKeys, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let mut conn = conn.await?;
+            conn.transaction::<_, crate::errors::Error, _>(|conn| {
+                async move {
+                    lock_signing_keys(conn).await?;
+                    rotate_signing_keys(conn, msg).await
+                }
+                .scope_boxed()
+            })
+            .await
+        };
+        tracing::info!("Rotating signing keys");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+/// Serializes changes to the signing keys across instances until the
+/// transaction ends.
+async fn lock_signing_keys(conn: &mut AsyncPgConnection) -> crate::errors::Result<()> {
+    diesel::sql_query("SELECT pg_advisory_xact_lock($1)")
+        .bind::<diesel::sql_types::BigInt, _>(SIGNING_KEYS_LOCK_ID)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// The body of `RotateSigningKeys`, run inside the caller's transaction.
+async fn rotate_signing_keys(
+    conn: &mut AsyncPgConnection,
+    msg: RotateSigningKeys,
+) -> crate::errors::Result<RotationOutcome> {
+    let now = chrono::Utc::now();
+    let mut outcome = RotationOutcome::default();
+    let keys = signing_keys::table
+        .filter(signing_keys::state.ne(KEY_STATE_REVOKED))
+        .load::<SigningKeys>(conn)
+        .await?;
+    let active = keys.iter().find(|key| key.state == KEY_STATE_ACTIVE);
+
+    let mut pending = keys
+        .iter()
+        .find(|key| key.state == KEY_STATE_PENDING)
+        .map(|key| (key.kid.clone(), key.created_at));
+    let publish =
+        active.is_none_or(|key| key.activated_at.unwrap_or(key.created_at) <= msg.publish_before);
+    if let (None, Some(candidate), true) = (&pending, msg.candidate, publish) {
+        diesel::insert_into(signing_keys::table)
+            .values(SigningKeys {
+                kid: candidate.kid.clone(),
+                alg: candidate.alg,
+                state: KEY_STATE_PENDING.to_string(),
+                encrypted_private_key: candidate.encrypted_private_key,
+                created_at: now,
+                activated_at: None,
+                retired_at: None,
+                revoked_at: None,
+            })
+            .execute(conn)
+            .await?;
+        outcome.published = Some(candidate.kid.clone());
+        pending = Some((candidate.kid, now));
+    }
+
+    if let Some((kid, created_at)) = pending {
+        if active.is_none() || created_at <= msg.activate_before {
+            diesel::update(signing_keys::table.filter(signing_keys::state.eq(KEY_STATE_ACTIVE)))
+                .set((
+                    signing_keys::state.eq(KEY_STATE_RETIRING),
+                    signing_keys::retired_at.eq(Some(now)),
+                ))
+                .execute(conn)
+                .await?;
+            diesel::update(signing_keys::table.filter(signing_keys::kid.eq(&kid)))
+                .set((
+                    signing_keys::state.eq(KEY_STATE_ACTIVE),
+                    signing_keys::activated_at.eq(Some(now)),
+                ))
+                .execute(conn)
+                .await?;
+            outcome.activated = Some(kid);
+        }
+    }
+
+    outcome.revoked = diesel::update(
+        signing_keys::table
+            .filter(signing_keys::state.eq(KEY_STATE_RETIRING))
+            .filter(signing_keys::retired_at.lt(msg.retired_before)),
+    )
+    .set((
+        signing_keys::state.eq(KEY_STATE_REVOKED),
+        signing_keys::revoked_at.eq(Some(now)),
+    ))
+    .execute(conn)
+    .await?;
+
+    Ok(outcome)
+}
+
+impl Handler<GetSigningKeys> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Vec<SigningKeys>>>;
+
+    fn handle(&mut self, _: GetSigningKeys, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let keys = signing_keys::table
+                .filter(signing_keys::state.ne(KEY_STATE_REVOKED))
+                .order(signing_keys::created_at.desc())
+                .load::<SigningKeys>(&mut conn.await?)
+                .await?;
+            Ok(keys)
+        };
+        tracing::info!("Getting signing keys");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<RevokeSigningKey> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<bool>>;
+
+    fn handle(&mut self, msg: RevokeSigningKey, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let kid = msg.kid.clone();
+        let query = async move {
+            let mut conn = conn.await?;
+            conn.transaction::<_, crate::errors::Error, _>(|conn| {
+                async move {
+                    lock_signing_keys(conn).await?;
+
+                    let is_active = signing_keys::table
+                        .filter(signing_keys::kid.eq(&kid))
+                        .filter(signing_keys::state.eq(KEY_STATE_ACTIVE))
+                        .count()
+                        .get_result::<i64>(conn)
+                        .await?
+                        > 0;
+                    if is_active {
+                        let replaced = match msg.rotation {
+                            Some(rotation) => rotate_signing_keys(conn, rotation)
+                                .await?
+                                .activated
+                                .is_some(),
+                            None => false,
+                        };
+                        if !replaced {
+                            return Err(crate::errors::Error::Conflict(format!(
+                                "Signing key {} became active, revoke it again",
+                                kid
+                            )));
+                        }
+                    }
+
+                    let updated = diesel::update(
+                        signing_keys::table
+                            .filter(signing_keys::kid.eq(&kid))
+                            .filter(signing_keys::state.ne(KEY_STATE_REVOKED)),
+                    )
+                    .set((
+                        signing_keys::state.eq(KEY_STATE_REVOKED),
+                        signing_keys::revoked_at.eq(Some(chrono::Utc::now())),
+                    ))
+                    .execute(conn)
+                    .await?;
+                    Ok(updated > 0)
+                }
+                .scope_boxed()
+            })
+            .await
+        };
+        tracing::info!("Revoking signing key {}", msg.kid);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetRefreshToken> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<RefreshTokens>>>;
+
+    fn handle(&mut self, msg: GetRefreshToken, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let token = refresh_tokens::table
+                .filter(refresh_tokens::token_hash.eq(msg.token_hash))
+                .first::<RefreshTokens>(&mut conn.await?)
+                .await
+                .optional()?;
+            Ok(token)
+        };
+        tracing::info!("Getting refresh token");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<IsTokenRevoked> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<bool>>;
+
+    fn handle(&mut self, msg: IsTokenRevoked, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let revoked = revoked_tokens::table
+                .count()
+                .filter(revoked_tokens::token_id.eq(msg.token_id))
+                .first::<i64>(&mut conn.await?)
+                .await?;
+            Ok(revoked > 0)
+        };
+        tracing::info!("Checking token denylist");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<RevokeToken> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: RevokeToken, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let token = RevokedTokens {
+                token_id: msg.token_id,
+                expires_at: msg.expires_at,
+                revoked_at: chrono::Utc::now(),
+            };
+
+            let _ = diesel::insert_into(revoked_tokens::table)
+                .values(token)
+                .on_conflict_do_nothing()
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Adding token to the denylist");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CreateOauthClient> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateOauthClient, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let client_id = msg.client_id.clone();
+        let query = async move {
+            let client = OauthClients {
+                client_id: msg.client_id,
+                name: msg.name,
+                client_secret_hash: msg.client_secret_hash,
+                grant_types: msg.grant_types,
+                redirect_uris: msg.redirect_uris,
+                allowed_scopes: msg.allowed_scopes,
+                access_token_ttl: msg.access_token_ttl,
+                refresh_token_ttl: msg.refresh_token_ttl,
+                created_at: chrono::Utc::now(),
+                updated_at: None,
+            };
+
+            let _ = diesel::insert_into(oauth_clients::table)
+                .values(client)
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Creating OAuth client {}", client_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetOauthClient> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<OauthClients>>>;
+
+    fn handle(&mut self, msg: GetOauthClient, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let client = oauth_clients::table
+                .filter(oauth_clients::client_id.eq(msg.client_id))
+                .first::<OauthClients>(&mut conn.await?)
+                .await
+                .optional()?;
+            Ok(client)
+        };
+        tracing::info!("Getting OAuth client");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetOauthClients> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Vec<OauthClients>>>;
+
+    fn handle(&mut self, _: GetOauthClients, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let clients = oauth_clients::table
+                .order(oauth_clients::client_id.asc())
+                .load::<OauthClients>(&mut conn.await?)
+                .await?;
+            Ok(clients)
+        };
+        tracing::info!("Getting OAuth clients");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<UpdateOauthClient> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<bool>>;
+
+    fn handle(&mut self, msg: UpdateOauthClient, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let client_id = msg.client_id.clone();
+        let query = async move {
+            let updated = diesel::update(
+                oauth_clients::table.filter(oauth_clients::client_id.eq(msg.client_id)),
+            )
+            .set((
+                oauth_clients::name.eq(msg.name),
+                oauth_clients::grant_types.eq(msg.grant_types),
+                oauth_clients::redirect_uris.eq(msg.redirect_uris),
+                oauth_clients::allowed_scopes.eq(msg.allowed_scopes),
+                oauth_clients::access_token_ttl.eq(msg.access_token_ttl),
+                oauth_clients::refresh_token_ttl.eq(msg.refresh_token_ttl),
+                oauth_clients::updated_at.eq(Some(chrono::Utc::now())),
+            ))
+            .execute(&mut conn.await?)
+            .await?;
+            Ok(updated > 0)
+        };
+        tracing::info!("Updating OAuth client {}", client_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<UpdateOauthClientSecret> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<bool>>;
+
+    fn handle(&mut self, msg: UpdateOauthClientSecret, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let client_id = msg.client_id.clone();
+        let query = async move {
+            let updated = diesel::update(
+                oauth_clients::table
+                    .filter(oauth_clients::client_id.eq(msg.client_id))
+                    .filter(oauth_clients::client_secret_hash.is_not_null()),
+            )
+            .set((
+                oauth_clients::client_secret_hash.eq(Some(msg.client_secret_hash)),
+                oauth_clients::updated_at.eq(Some(chrono::Utc::now())),
+            ))
+            .execute(&mut conn.await?)
+            .await?;
+            Ok(updated > 0)
+        };
+        tracing::info!("Rotating secret of OAuth client {}", client_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<DeleteOauthClient> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<bool>>;
+
+    fn handle(&mut self, msg: DeleteOauthClient, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let client_id = msg.client_id.clone();
+        let query = async move {
+            let mut conn = conn.await?;
+            conn.transaction::<_, crate::errors::Error, _>(|conn| {
+                async move {
+                    diesel::delete(
+                        authorization_codes::table
+                            .filter(authorization_codes::client_id.eq(&msg.client_id)),
+                    )
+                    .execute(conn)
+                    .await?;
+                    diesel::delete(
+                        refresh_tokens::table.filter(refresh_tokens::client_id.eq(&msg.client_id)),
+                    )
+                    .execute(conn)
+                    .await?;
+                    let deleted = diesel::delete(
+                        oauth_clients::table.filter(oauth_clients::client_id.eq(&msg.client_id)),
+                    )
+                    .execute(conn)
+                    .await?;
+                    Ok(deleted > 0)
+                }
+                .scope_boxed()
+            })
+            .await
+        };
+        tracing::info!("Deleting OAuth client {}", client_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CreateApiKey> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateApiKey, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let prefix = msg.prefix.clone();
+        let query = async move {
+            let (user_id, client_id) = match msg.owner {
+                ApiKeyOwner::User(user_id) => (Some(user_id), None),
+                ApiKeyOwner::Client(client_id) => (None, Some(client_id)),
+            };
+            let key = ApiKeys {
+                id: msg.id,
+                prefix: msg.prefix,
+                secret_hash: msg.secret_hash,
+                name: msg.name,
+                user_id,
+                client_id,
+                scope: msg.scope,
+                expires_at: msg.expires_at,
+                last_used_at: None,
+                created_at: chrono::Utc::now(),
+                revoked_at: None,
+            };
+
+            let _ = diesel::insert_into(api_keys::table)
+                .values(key)
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Creating API key {}", prefix);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetApiKeyByPrefix> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<ApiKeys>>>;
+
+    fn handle(&mut self, msg: GetApiKeyByPrefix, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let key = api_keys::table
+                .filter(api_keys::prefix.eq(msg.prefix))
+                .first::<ApiKeys>(&mut conn.await?)
+                .await
+                .optional()?;
+            Ok(key)
+        };
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetApiKeys> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Vec<ApiKeys>>>;
+
+    fn handle(&mut self, msg: GetApiKeys, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let query = api_keys::table
+                .filter(api_keys::revoked_at.is_null())
+                .order(api_keys::created_at.desc())
+                .into_boxed();
+            let query = match msg.owner {
+                ApiKeyOwner::User(user_id) => query.filter(api_keys::user_id.eq(user_id)),
+                ApiKeyOwner::Client(client_id) => query.filter(api_keys::client_id.eq(client_id)),
+            };
+
+            let keys = query.load::<ApiKeys>(&mut conn.await?).await?;
+            Ok(keys)
+        };
+        tracing::info!("Getting API keys");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<TouchApiKey> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: TouchApiKey, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let now = chrono::Utc::now();
+            let _ = diesel::update(
+                api_keys::table.filter(api_keys::id.eq(msg.id)).filter(
+                    api_keys::last_used_at
+                        .is_null()
+                        .or(api_keys::last_used_at.lt(now - msg.resolution)),
+                ),
+            )
+            .set(api_keys::last_used_at.eq(Some(now)))
+            .execute(&mut conn.await?)
+            .await?;
+            Ok(())
+        };
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<RevokeApiKey> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<bool>>;
+
+    fn handle(&mut self, msg: RevokeApiKey, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let id = msg.id;
+        let query = async move {
+            let target = api_keys::table
+                .filter(api_keys::id.eq(msg.id))
+                .filter(api_keys::revoked_at.is_null());
+            let revoked_at = api_keys::revoked_at.eq(Some(chrono::Utc::now()));
+
+            let mut conn = conn.await?;
+            let updated = match msg.owner {
+                ApiKeyOwner::User(user_id) => {
+                    diesel::update(target.filter(api_keys::user_id.eq(user_id)))
+                        .set(revoked_at)
+                        .execute(&mut conn)
+                        .await?
+                }
+                ApiKeyOwner::Client(client_id) => {
+                    diesel::update(target.filter(api_keys::client_id.eq(client_id)))
+                        .set(revoked_at)
+                        .execute(&mut conn)
+                        .await?
+                }
+            };
+            Ok(updated > 0)
+        };
+        tracing::info!("Revoking API key {}", id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CreateSession> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateSession, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let now = chrono::Utc::now();
+            let session = Sessions {
+                id: msg.id,
+                token_hash: msg.token_hash,
+                user_id: msg.user_id,
+                user_agent: msg.user_agent,
+                ip_address: msg.ip_address,
+                created_at: now,
+                last_seen_at: now,
+                expires_at: msg.expires_at,
+                geo_hint: msg.geo_hint,
+            };
+
+            let _ = diesel::insert_into(sessions::table)
+                .values(session)
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+        tracing::info!("Creating session for user {}", user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetSession> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Option<Sessions>>>;
+
+    fn handle(&mut self, msg: GetSession, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let session = sessions::table
+                .filter(sessions::token_hash.eq(msg.token_hash))
+                .first::<Sessions>(&mut conn.await?)
+                .await
+                .optional()?;
+            Ok(session)
+        };
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<TouchSession> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: TouchSession, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let _ = diesel::update(sessions::table.filter(sessions::id.eq(msg.id)))
+                .set(sessions::last_seen_at.eq(msg.last_seen_at))
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<DeleteSession> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<bool>>;
+
+    fn handle(&mut self, msg: DeleteSession, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let deleted =
+                diesel::delete(sessions::table.filter(sessions::token_hash.eq(msg.token_hash)))
+                    .execute(&mut conn.await?)
+                    .await?;
+            Ok(deleted > 0)
+        };
+        tracing::info!("Deleting session");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetUserSessions> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Vec<Sessions>>>;
+
+    fn handle(&mut self, msg: GetUserSessions, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let sessions = sessions::table
+                .filter(sessions::user_id.eq(msg.user_id))
+                .filter(sessions::expires_at.gt(chrono::Utc::now()))
+                .order(sessions::last_seen_at.desc())
+                .load::<Sessions>(&mut conn.await?)
+                .await?;
+            Ok(sessions)
+        };
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<DeleteUserSessions> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Vec<String>>>;
+
+    fn handle(&mut self, msg: DeleteUserSessions, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let user_id = msg.user_id.clone();
+        let query = async move {
+            let mut query = diesel::delete(sessions::table)
+                .filter(sessions::user_id.eq(msg.user_id))
+                .into_boxed();
+            if let Some(id) = msg.id {
+                query = query.filter(sessions::id.eq(id));
+            }
+            if let Some(keep) = msg.keep {
+                query = query.filter(sessions::id.ne(keep));
+            }
+
+            // Refresh tokens and codes of the sessions go with them through
+            // `ON DELETE CASCADE`.
+            let token_hashes = query
+                .returning(sessions::token_hash)
+                .get_results::<String>(&mut conn.await?)
+                .await?;
+            Ok(token_hashes)
+        };
+        tracing::info!("Deleting sessions of user {}", user_id);
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CreateAuditEvent> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, msg: CreateAuditEvent, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            diesel::insert_into(audit_events::table)
+                .values(msg.event)
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<GetAuditEvents> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<Vec<AuditEvents>>>;
+
+    fn handle(&mut self, msg: GetAuditEvents, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let mut query = audit_events::table.into_boxed();
+            if let Some(action) = msg.action {
+                query = query.filter(audit_events::action.eq(action));
+            }
+            if let Some(outcome) = msg.outcome {
+                query = query.filter(audit_events::outcome.eq(outcome));
+            }
+            if let Some(actor) = msg.actor {
+                query = query.filter(audit_events::actor.eq(actor));
+            }
+            if let Some(subject) = msg.subject {
+                query = query.filter(audit_events::subject.eq(subject));
+            }
+            if let Some(since) = msg.since {
+                query = query.filter(audit_events::occurred_at.ge(since));
+            }
+            if let Some(until) = msg.until {
+                query = query.filter(audit_events::occurred_at.lt(until));
+            }
+            if let Some(before) = msg.before {
+                query = query.filter(audit_events::id.lt(before));
+            }
+
+            let events = query
+                .order(audit_events::id.desc())
+                .limit(msg.limit)
+                .load::<AuditEvents>(&mut conn.await?)
+                .await?;
+            Ok(events)
+        };
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<Ping> for DbService {
+    type Result = ();
+
+    fn handle(&mut self, _: Ping, _: &mut Self::Context) -> Self::Result {}
+}
+
+impl Handler<PurgeExpired> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<usize>>;
+
+    fn handle(&mut self, _: PurgeExpired, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            let mut conn = conn.await?;
+            let now = chrono::Utc::now();
+            // Sessions go first, their codes and refresh tokens cascade.
+            let purged = [
+                diesel::delete(sessions::table.filter(sessions::expires_at.lt(now)))
+                    .execute(&mut conn)
+                    .await?,
+                diesel::delete(
+                    authorization_codes::table.filter(authorization_codes::expires_at.lt(now)),
+                )
+                .execute(&mut conn)
+                .await?,
+                diesel::delete(refresh_tokens::table.filter(refresh_tokens::expires_at.lt(now)))
+                    .execute(&mut conn)
+                    .await?,
+                // A revoked token past its expiry is rejected anyway.
+                diesel::delete(revoked_tokens::table.filter(revoked_tokens::expires_at.lt(now)))
+                    .execute(&mut conn)
+                    .await?,
+                diesel::delete(mfa_challenges::table.filter(mfa_challenges::expires_at.lt(now)))
+                    .execute(&mut conn)
+                    .await?,
+                diesel::delete(
+                    webauthn_challenges::table.filter(webauthn_challenges::expires_at.lt(now)),
+                )
+                .execute(&mut conn)
+                .await?,
+                diesel::delete(
+                    oauth_authorization_requests::table
+                        .filter(oauth_authorization_requests::expires_at.lt(now)),
+                )
+                .execute(&mut conn)
+                .await?,
+            ];
+            Ok(purged.iter().sum())
+        };
+        tracing::info!("Purging expired rows");
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<CheckDatabase> for DbService {
+    type Result = AtomicResponse<Self, crate::errors::Result<()>>;
+
+    fn handle(&mut self, _: CheckDatabase, _: &mut Self::Context) -> Self::Result {
+        let db = self.clone();
+        let conn = async move { db.pool.get().await };
+        let query = async move {
+            diesel::sql_query("SELECT 1")
+                .execute(&mut conn.await?)
+                .await?;
+            Ok(())
+        };
+
+        let db = self.clone();
+        AtomicResponse::new(Box::pin(query.into_actor(&db)))
+    }
+}
+
+impl Handler<Stop> for DbService {
+    type Result = ();
+
+    fn handle(&mut self, _: Stop, ctx: &mut Self::Context) -> Self::Result {
+        tracing::info!("Stopping the database service");
+        ctx.stop();
+    }
+}
@@ -1,5 +1,12 @@
+use crate::services::db::tables::{
+    ApiKeys, AuditEvents, AuthorizationCodes, MfaChallenges, MfaFactors, NewAuditEvent,
+    OauthAuthorizationRequests, OauthClients, RefreshTokens, Sessions, SigningKeys, UserIdentities,
+    Users, WebauthnChallenges, WebauthnCredentials,
+};
 use actix::Message;
+use chrono::{DateTime, Utc};
 use serde::Serialize;
+use uuid::Uuid;
 
 #[derive(Message, Serialize, Clone)]
 #[rtype(result = "crate::errors::Result<()>")]
@@ -48,3 +55,479 @@ pub(crate) struct CheckIfRegisteredUser {
     pub username: String,
     pub email: String,
 }
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<Users>>")]
+pub(crate) struct GetUser {
+    pub id: String,
+}
+
+/// Looks up the only user with `email`. Emails aren't unique, so this fails
+/// with `Error::Conflict` rather than picking one of several accounts.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<Users>>")]
+pub(crate) struct GetUserByEmail {
+    pub email: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateMfaFactor {
+    pub user_id: String,
+    pub encrypted_secret: Vec<u8>,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<MfaFactors>>")]
+pub(crate) struct GetMfaFactor {
+    pub user_id: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct ConfirmMfaFactor {
+    pub user_id: String,
+    pub step: i64,
+}
+
+/// Records the TOTP time step that was just accepted. Returns `false` when the
+/// step is not newer than the last accepted one, so a code cannot be replayed.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<bool>")]
+pub(crate) struct UpdateMfaLastUsedStep {
+    pub user_id: String,
+    pub step: i64,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct ReplaceRecoveryCodes {
+    pub user_id: String,
+    pub code_hashes: Vec<String>,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<bool>")]
+pub(crate) struct UseRecoveryCode {
+    pub user_id: String,
+    pub code_hash: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateMfaChallenge {
+    pub token_hash: String,
+    pub user_id: String,
+    pub encrypted_access_token: Vec<u8>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Counts one attempt against a challenge and returns it, or `None` when the
+/// challenge does not exist or has used up `max_attempts`.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<MfaChallenges>>")]
+pub(crate) struct ClaimMfaChallengeAttempt {
+    pub token_hash: String,
+    pub max_attempts: i32,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct DeleteMfaChallenge {
+    pub token_hash: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateWebauthnChallenge {
+    pub challenge: String,
+    pub user_id: Option<String>,
+    pub ceremony: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Removes a ceremony challenge and returns it, so each challenge is answered once.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<WebauthnChallenges>>")]
+pub(crate) struct TakeWebauthnChallenge {
+    pub challenge: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateWebauthnCredential {
+    pub credential_id: String,
+    pub user_id: String,
+    pub public_key: Vec<u8>,
+    pub alg: i32,
+    pub sign_count: i64,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<WebauthnCredentials>>")]
+pub(crate) struct GetWebauthnCredential {
+    pub credential_id: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Vec<WebauthnCredentials>>")]
+pub(crate) struct GetUserWebauthnCredentials {
+    pub user_id: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct UpdateWebauthnSignCount {
+    pub credential_id: String,
+    pub sign_count: i64,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateAuthorizationRequest {
+    pub state: String,
+    pub code_verifier: String,
+    pub nonce: String,
+    pub connection: Option<String>,
+    /// Set when the flow was started by one of our own OAuth clients, which get
+    /// an authorization code back instead of the Auth0 tokens.
+    pub client: Option<ClientAuthorization>,
+    /// Set when a signed-in user links a social identity to their account.
+    pub link_user_id: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// The parameters an OAuth client sent to `/authorize`.
+pub(crate) struct ClientAuthorization {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub nonce: Option<String>,
+    pub scope: String,
+}
+
+/// Removes a pending authorization request and returns it, so each `state` is used once.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<OauthAuthorizationRequests>>")]
+pub(crate) struct TakeAuthorizationRequest {
+    pub state: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<UserIdentities>>")]
+pub(crate) struct GetUserIdentity {
+    pub provider: String,
+    pub provider_user_id: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateUserIdentity {
+    pub provider: String,
+    pub provider_user_id: String,
+    pub user_id: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateAuthorizationCode {
+    pub code_hash: String,
+    pub client_id: String,
+    pub user_id: String,
+    pub redirect_uri: String,
+    pub code_challenge: Option<String>,
+    pub nonce: Option<String>,
+    pub scope: String,
+    pub session_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Removes an authorization code and returns it, so each code is redeemed once.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<AuthorizationCodes>>")]
+pub(crate) struct TakeAuthorizationCode {
+    pub code_hash: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateRefreshToken {
+    pub token_hash: String,
+    pub client_id: String,
+    pub user_id: String,
+    pub scope: String,
+    pub session_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Removes a refresh token of `client_id` and returns it, so a rotated token
+/// can't be replayed. Tokens of other clients are left alone.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<RefreshTokens>>")]
+pub(crate) struct TakeRefreshToken {
+    pub token_hash: String,
+    pub client_id: String,
+}
+
+/// A freshly generated key, not yet stored.
+pub(crate) struct NewSigningKey {
+    pub kid: String,
+    pub alg: String,
+    pub encrypted_private_key: Vec<u8>,
+}
+
+/// Moves the signing keys along their lifecycle in one transaction, under an
+/// advisory lock so instances and the admin CLI never rotate concurrently:
+/// `candidate` is published as pending when no key is pending and the active
+/// key was activated before `publish_before`, a pending key created before
+/// `activate_before` becomes the active key, and retiring keys retired before
+/// `retired_before` are revoked. Without an active key the pending key is
+/// activated right away.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<RotationOutcome>")]
+pub(crate) struct RotateSigningKeys {
+    pub candidate: Option<NewSigningKey>,
+    pub publish_before: DateTime<Utc>,
+    pub activate_before: DateTime<Utc>,
+    pub retired_before: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct RotationOutcome {
+    pub published: Option<String>,
+    pub activated: Option<String>,
+    pub revoked: usize,
+}
+
+/// Returns all signing keys that are not revoked.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Vec<SigningKeys>>")]
+pub(crate) struct GetSigningKeys;
+
+/// Revokes a key in one transaction, under the same advisory lock as
+/// `RotateSigningKeys`. If the key is active, `rotation` runs first so another
+/// key takes over; revoking the active key fails without one. Returns whether
+/// the key existed and was not revoked yet.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<bool>")]
+pub(crate) struct RevokeSigningKey {
+    pub kid: String,
+    pub rotation: Option<RotateSigningKeys>,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<RefreshTokens>>")]
+pub(crate) struct GetRefreshToken {
+    pub token_hash: String,
+}
+
+/// Checks the access token denylist.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<bool>")]
+pub(crate) struct IsTokenRevoked {
+    pub token_id: String,
+}
+
+/// Adds an access token to the denylist until it expires.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct RevokeToken {
+    pub token_id: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateOauthClient {
+    pub client_id: String,
+    pub name: String,
+    pub client_secret_hash: Option<String>,
+    pub grant_types: Vec<String>,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub access_token_ttl: Option<i64>,
+    pub refresh_token_ttl: Option<i64>,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<OauthClients>>")]
+pub(crate) struct GetOauthClient {
+    pub client_id: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Vec<OauthClients>>")]
+pub(crate) struct GetOauthClients;
+
+/// Replaces the policy of a client, leaving its secret as is.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<bool>")]
+pub(crate) struct UpdateOauthClient {
+    pub client_id: String,
+    pub name: String,
+    pub grant_types: Vec<String>,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub access_token_ttl: Option<i64>,
+    pub refresh_token_ttl: Option<i64>,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<bool>")]
+pub(crate) struct UpdateOauthClientSecret {
+    pub client_id: String,
+    pub client_secret_hash: String,
+}
+
+/// Deletes a client together with its outstanding codes and refresh tokens.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<bool>")]
+pub(crate) struct DeleteOauthClient {
+    pub client_id: String,
+}
+
+/// Who an API key belongs to.
+#[derive(Debug, Clone)]
+pub(crate) enum ApiKeyOwner {
+    User(String),
+    Client(String),
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateApiKey {
+    pub id: Uuid,
+    pub prefix: String,
+    pub secret_hash: String,
+    pub name: String,
+    pub owner: ApiKeyOwner,
+    pub scope: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<ApiKeys>>")]
+pub(crate) struct GetApiKeyByPrefix {
+    pub prefix: String,
+}
+
+/// Returns the keys of an owner that are not revoked.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Vec<ApiKeys>>")]
+pub(crate) struct GetApiKeys {
+    pub owner: ApiKeyOwner,
+}
+
+/// Records that a key was used, at most once per `resolution`.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct TouchApiKey {
+    pub id: Uuid,
+    pub resolution: chrono::Duration,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<bool>")]
+pub(crate) struct RevokeApiKey {
+    pub id: Uuid,
+    pub owner: ApiKeyOwner,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateSession {
+    pub id: Uuid,
+    pub token_hash: String,
+    pub user_id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub geo_hint: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Option<Sessions>>")]
+pub(crate) struct GetSession {
+    pub token_hash: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct TouchSession {
+    pub id: Uuid,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<bool>")]
+pub(crate) struct DeleteSession {
+    pub token_hash: String,
+}
+
+/// Sessions of a user that have not expired, most recently used first.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Vec<Sessions>>")]
+pub(crate) struct GetUserSessions {
+    pub user_id: String,
+}
+
+/// Deletes sessions of a user, together with the refresh tokens issued from
+/// them. Either the one with `id`, or all but `keep` when `id` is `None`.
+/// Returns the token hashes of the deleted sessions.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<Vec<String>>")]
+pub(crate) struct DeleteUserSessions {
+    pub user_id: String,
+    pub id: Option<Uuid>,
+    pub keep: Option<Uuid>,
+}
+
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CreateAuditEvent {
+    pub event: NewAuditEvent,
+}
+
+/// Audit events matching all the set filters, newest first.
+#[derive(Message, Clone)]
+#[rtype(result = "crate::errors::Result<Vec<AuditEvents>>")]
+pub(crate) struct GetAuditEvents {
+    pub action: Option<String>,
+    pub outcome: Option<String>,
+    pub actor: Option<String>,
+    pub subject: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Only events with a smaller id, to page through the results.
+    pub before: Option<i64>,
+    pub limit: i64,
+}
+
+/// Does nothing; the time it takes to be handled is the time spent queued
+/// behind other messages.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct Ping;
+
+/// Runs a trivial query, to tell whether the database answers.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<()>")]
+pub(crate) struct CheckDatabase;
+
+/// Deletes tokens, sessions, codes and challenges past their `expires_at`,
+/// returning how many rows went.
+#[derive(Message)]
+#[rtype(result = "crate::errors::Result<usize>")]
+pub(crate) struct PurgeExpired;
+
+/// Sent when `signing_keys` changed, so the issuer picks up the new state
+/// without waiting for the next rotation check.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct ReloadSigningKeys;
+
+/// Stops the receiving actor once the messages queued before it are handled.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct Stop;
@@ -1,2 +1,3 @@
 pub mod handlers;
 pub mod messages;
+pub mod traced;
@@ -0,0 +1,29 @@
+use crate::services::db::postgres_db::DbService;
+use actix::{Addr, Handler, MailboxError, Message};
+use std::any::type_name;
+use std::future::Future;
+use tracing::Instrument;
+
+pub(crate) trait TracedSend {
+    /// Sends a message to `DbService` in a span named after the message, a
+    /// child of the current one. The span covers the time spent waiting in
+    /// the mailbox as well as handling.
+    fn send_traced<M>(&self, message: M) -> impl Future<Output = Result<M::Result, MailboxError>>
+    where
+        M: Message + Send + 'static,
+        M::Result: Send,
+        DbService: Handler<M>;
+}
+
+impl TracedSend for Addr<DbService> {
+    fn send_traced<M>(&self, message: M) -> impl Future<Output = Result<M::Result, MailboxError>>
+    where
+        M: Message + Send + 'static,
+        M::Result: Send,
+        DbService: Handler<M>,
+    {
+        let name = type_name::<M>().rsplit("::").next().unwrap_or_default();
+        let span = tracing::info_span!("db_message", otel.name = name, db.message = name);
+        self.send(message).instrument(span)
+    }
+}
@@ -0,0 +1,63 @@
+use crate::errors::{Error, Result};
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::services::oidc::consts::SCOPE_ADMIN;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Decides who may call the `/admin` endpoints.
+#[derive(Clone)]
+pub struct AdminService {
+    users: Arc<HashSet<String>>,
+    client_cert_required: bool,
+}
+
+impl AdminService {
+    pub fn new(users: Vec<String>, client_cert_required: bool) -> Self {
+        AdminService {
+            users: Arc::new(users.into_iter().collect()),
+            client_cert_required,
+        }
+    }
+
+    /// Whether admin requests must come over a connection with a verified
+    /// client certificate.
+    pub fn client_cert_required(&self) -> bool {
+        self.client_cert_required
+    }
+
+    /// Fails unless the caller is one of the configured admins and its
+    /// credential allows the `admin` scope. Credentials owned by an OAuth
+    /// client never act as admins.
+    pub fn authorize(&self, user: &AuthenticatedUser) -> Result<()> {
+        if user.is_client() || !self.users.contains(&user.user_id) {
+            tracing::warn!("User {} is not an admin", user.user_id);
+            return Err(Error::Forbidden);
+        }
+        user.require_scope(SCOPE_ADMIN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn api_key(scope: &str) -> AuthenticatedUser {
+        AuthenticatedUser {
+            user_id: "admin".to_string(),
+            client_id: None,
+            scope: Some(scope.to_string()),
+            api_key_id: Some(Uuid::new_v4()),
+            session_id: None,
+        }
+    }
+
+    #[test]
+    fn refuses_api_keys_without_the_admin_scope() {
+        let admins = AdminService::new(vec!["admin".to_string()], false);
+
+        assert!(admins.authorize(&api_key("")).is_err());
+        assert!(admins.authorize(&api_key("user:read user:write")).is_err());
+        assert!(admins.authorize(&api_key("admin")).is_ok());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::errors::{Error, Result};
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::services::actors::messages::{
+    CheckUser, GetApiKeyByPrefix, GetOauthClient, TouchApiKey,
+};
+use crate::services::actors::traced::TracedSend;
+use crate::services::api_keys::consts::{API_KEY_MARKER, API_KEY_PREFIX_LEN, LAST_USED_RESOLUTION};
+use crate::services::db::postgres_db::DbService;
+use crate::services::oauth::pkce::random_token;
+use crate::services::oidc::oidc_service::OidcService;
+use actix::Addr;
+use data_encoding::HEXLOWER;
+
+/// A freshly generated key. `key` is shown to the owner once; only `prefix`
+/// and `secret_hash` are stored.
+pub struct GeneratedApiKey {
+    pub key: String,
+    pub prefix: String,
+    pub secret_hash: String,
+}
+
+#[derive(Clone)]
+pub struct ApiKeyService {
+    db: Addr<DbService>,
+}
+
+impl ApiKeyService {
+    pub fn new(db: Addr<DbService>) -> Self {
+        ApiKeyService { db }
+    }
+
+    pub fn generate() -> Result<GeneratedApiKey> {
+        let mut prefix = [0u8; API_KEY_PREFIX_LEN / 2];
+        openssl::rand::rand_bytes(&mut prefix)?;
+        let prefix = HEXLOWER.encode(&prefix);
+        let secret = random_token()?;
+
+        Ok(GeneratedApiKey {
+            key: format!("{}_{}_{}", API_KEY_MARKER, prefix, secret),
+            secret_hash: OidcService::hash_token(&secret),
+            prefix,
+        })
+    }
+
+    /// Resolves the owner of a key presented by a caller.
+    pub async fn authenticate(&self, key: &str) -> Result<AuthenticatedUser> {
+        let (prefix, secret) = key
+            .strip_prefix(API_KEY_MARKER)
+            .and_then(|key| key.strip_prefix('_'))
+            .and_then(|key| key.split_once('_'))
+            .ok_or(Error::Unauthorized)?;
+
+        let api_key = self
+            .db
+            .send_traced(GetApiKeyByPrefix {
+                prefix: prefix.to_string(),
+            })
+            .await??
+            .ok_or(Error::Unauthorized)?;
+
+        let secret_hash = OidcService::hash_token(secret);
+        if api_key.revoked_at.is_some()
+            || api_key
+                .expires_at
+                .is_some_and(|expires_at| expires_at <= chrono::Utc::now())
+            || !openssl::memcmp::eq(api_key.secret_hash.as_bytes(), secret_hash.as_bytes())
+        {
+            return Err(Error::Unauthorized);
+        }
+
+        // Keys outlive the user or client they were issued to, so the owner
+        // is looked up on every use.
+        let owner_exists = match (&api_key.user_id, &api_key.client_id) {
+            (Some(user_id), _) => {
+                self.db
+                    .send_traced(CheckUser {
+                        id: user_id.clone(),
+                    })
+                    .await??
+            }
+            (None, Some(client_id)) => self
+                .db
+                .send_traced(GetOauthClient {
+                    client_id: client_id.clone(),
+                })
+                .await??
+                .is_some(),
+            (None, None) => false,
+        };
+        if !owner_exists {
+            return Err(Error::Unauthorized);
+        }
+
+        self.db
+            .send_traced(TouchApiKey {
+                id: api_key.id,
+                resolution: chrono::Duration::seconds(LAST_USED_RESOLUTION),
+            })
+            .await??;
+
+        // A key is only ever as strong as its scopes: an empty one grants nothing.
+        let scope = Some(api_key.scope);
+        let (user_id, client_id) = match (api_key.user_id, api_key.client_id) {
+            (Some(user_id), _) => (user_id, None),
+            (None, Some(client_id)) => (OidcService::client_subject(&client_id), Some(client_id)),
+            (None, None) => return Err(Error::Unauthorized),
+        };
+
+        Ok(AuthenticatedUser {
+            user_id,
+            client_id,
+            scope,
+            api_key_id: Some(api_key.id),
+            session_id: None,
+        })
+    }
+}
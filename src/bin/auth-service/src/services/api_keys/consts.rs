@@ -0,0 +1,7 @@
+pub const API_KEY_HEADER: &str = "X-API-Key";
+pub const API_KEY_AUTH_SCHEME: &str = "ApiKey ";
+/// Keys look like `ak_<prefix>_<secret>`.
+pub const API_KEY_MARKER: &str = "ak";
+pub const API_KEY_PREFIX_LEN: usize = 8;
+/// `last_used_at` is only written when older than this many seconds.
+pub const LAST_USED_RESOLUTION: i64 = 60;
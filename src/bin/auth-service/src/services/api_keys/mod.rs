@@ -0,0 +1,2 @@
+pub mod api_key_service;
+pub mod consts;
@@ -0,0 +1,42 @@
+use crate::services::audit::audit_sink::AuditSink;
+use crate::services::audit::models::AuditEvent;
+use actix_web::{HttpMessage, HttpRequest};
+use std::sync::Arc;
+
+/// The subject of an audited request, for handlers that only learn it from
+/// the request body.
+#[derive(Debug, Clone)]
+pub struct AuditSubject(pub String);
+
+/// Records audit events to the configured sink.
+#[derive(Clone)]
+pub struct AuditService {
+    sink: Arc<dyn AuditSink>,
+}
+
+impl AuditService {
+    pub fn new(sink: Arc<dyn AuditSink>) -> Self {
+        AuditService { sink }
+    }
+
+    pub fn record(&self, event: AuditEvent) {
+        tracing::info!(
+            "Audit: {} {} by {} on {}",
+            event.action,
+            event.outcome,
+            event.actor.as_deref().unwrap_or("-"),
+            event.subject.as_deref().unwrap_or("-")
+        );
+        self.sink.record(event);
+    }
+
+    /// Stops recording and waits until the events recorded so far are written.
+    pub async fn close(&self) {
+        self.sink.close().await;
+    }
+
+    /// Names whom the request acts on, in case it gets audited.
+    pub fn set_subject(req: &HttpRequest, subject: impl Into<String>) {
+        req.extensions_mut().insert(AuditSubject(subject.into()));
+    }
+}
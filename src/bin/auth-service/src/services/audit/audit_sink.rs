@@ -0,0 +1,87 @@
+use crate::services::actors::messages::CreateAuditEvent;
+use crate::services::audit::models::AuditEvent;
+use crate::services::db::postgres_db::DbService;
+use actix::Addr;
+use futures_util::future::BoxFuture;
+use std::sync::{Mutex, PoisonError};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::task::JoinHandle;
+
+/// Where audit events are written. Recording never blocks or fails the
+/// request that caused the event.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: AuditEvent);
+
+    /// Stops taking events and resolves once the ones recorded so far are
+    /// written.
+    fn close(&self) -> BoxFuture<'static, ()>;
+}
+
+/// Appends events to the `audit_events` table, in order, from a background
+/// task.
+pub struct DbAuditSink {
+    // `None` once closed, which ends the writer after the queue is drained.
+    sender: Mutex<Option<UnboundedSender<AuditEvent>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl DbAuditSink {
+    /// Must be called from within the actix runtime, which runs the writer.
+    pub fn new(db: Addr<DbService>) -> Self {
+        let (sender, mut receiver) = unbounded_channel::<AuditEvent>();
+
+        let writer = actix::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                let action = event.action;
+                let result = db
+                    .send(CreateAuditEvent {
+                        event: event.into(),
+                    })
+                    .await;
+                match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => tracing::error!("Failed to write audit event {}: {}", action, e),
+                    Err(e) => tracing::error!("Failed to write audit event {}: {}", action, e),
+                }
+            }
+        });
+
+        DbAuditSink {
+            sender: Mutex::new(Some(sender)),
+            writer: Mutex::new(Some(writer)),
+        }
+    }
+}
+
+impl AuditSink for DbAuditSink {
+    fn record(&self, event: AuditEvent) {
+        let sender = self.sender.lock().unwrap_or_else(PoisonError::into_inner);
+        let result = match sender.as_ref() {
+            Some(sender) => sender.send(event).map_err(|e| e.0),
+            None => Err(event),
+        };
+        if let Err(event) = result {
+            tracing::error!("Audit writer has stopped, dropping event {}", event.action);
+        }
+    }
+
+    fn close(&self) -> BoxFuture<'static, ()> {
+        self.sender
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        let writer = self
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+
+        Box::pin(async move {
+            if let Some(writer) = writer {
+                if let Err(e) = writer.await {
+                    tracing::error!("Audit writer failed: {}", e);
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,78 @@
+pub const OUTCOME_SUCCESS: &str = "success";
+pub const OUTCOME_FAILURE: &str = "failure";
+
+/// Requests that are recorded, by method and route pattern, with the action
+/// they are recorded as.
+pub const AUDITED_ROUTES: &[(&str, &str, &str)] = &[
+    ("POST", "/register", "user.register"),
+    ("POST", "/login", "user.login"),
+    ("POST", "/login/mfa", "user.login_mfa"),
+    ("POST", "/passwordless/verify", "user.login_passwordless"),
+    ("POST", "/webauthn/login/verify", "user.login_webauthn"),
+    ("GET", "/callback", "user.login_social"),
+    ("POST", "/logout", "user.logout"),
+    (
+        "POST",
+        "/user/change_password",
+        "user.password_change_requested",
+    ),
+    ("POST", "/user/mfa/totp/confirm", "user.mfa_enrolled"),
+    (
+        "POST",
+        "/user/mfa/recovery_codes",
+        "user.recovery_codes_regenerated",
+    ),
+    (
+        "POST",
+        "/user/webauthn/register/verify",
+        "user.webauthn_registered",
+    ),
+    (
+        "POST",
+        "/user/identities/link",
+        "user.identity_link_started",
+    ),
+    ("POST", "/user/api_keys", "user.api_key_created"),
+    ("DELETE", "/user/api_keys/{id}", "user.api_key_revoked"),
+    ("DELETE", "/user/sessions", "user.sessions_revoked"),
+    ("DELETE", "/user/sessions/{id}", "user.session_revoked"),
+    ("POST", "/admin/clients", "admin.client_created"),
+    ("PUT", "/admin/clients/{client_id}", "admin.client_updated"),
+    (
+        "DELETE",
+        "/admin/clients/{client_id}",
+        "admin.client_deleted",
+    ),
+    (
+        "POST",
+        "/admin/clients/{client_id}/secret",
+        "admin.client_secret_rotated",
+    ),
+    (
+        "POST",
+        "/admin/clients/{client_id}/api_keys",
+        "admin.api_key_created",
+    ),
+    (
+        "DELETE",
+        "/admin/clients/{client_id}/api_keys/{id}",
+        "admin.api_key_revoked",
+    ),
+    (
+        "DELETE",
+        "/admin/users/{user_id}/sessions",
+        "admin.sessions_revoked",
+    ),
+    ("GET", "/admin/audit", "admin.audit_viewed"),
+    ("GET", "/admin/audit/export", "admin.audit_exported"),
+];
+
+/// Path parameters naming whom an action was taken on, in order of preference.
+pub const SUBJECT_PARAMS: &[&str] = &["user_id", "client_id"];
+
+pub const DEFAULT_AUDIT_LIMIT: i64 = 100;
+pub const MAX_AUDIT_LIMIT: i64 = 1000;
+/// Events fetched per query while streaming an export.
+pub const EXPORT_PAGE_SIZE: i64 = 500;
+
+pub const MAX_USER_AGENT_LEN: usize = 512;
@@ -0,0 +1,4 @@
+pub mod audit_service;
+pub mod audit_sink;
+pub mod consts;
+pub mod models;
@@ -0,0 +1,35 @@
+use crate::services::db::tables::NewAuditEvent;
+use chrono::{DateTime, Utc};
+
+/// Who did what to whom, from where, and whether it worked.
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub occurred_at: DateTime<Utc>,
+    pub action: &'static str,
+    pub outcome: &'static str,
+    /// The error code when the action failed.
+    pub reason: Option<String>,
+    /// The authenticated caller, if any.
+    pub actor: Option<String>,
+    /// The user or client the action was taken on.
+    pub subject: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl From<AuditEvent> for NewAuditEvent {
+    fn from(event: AuditEvent) -> Self {
+        NewAuditEvent {
+            occurred_at: event.occurred_at,
+            action: event.action.to_string(),
+            outcome: event.outcome.to_string(),
+            reason: event.reason,
+            actor: event.actor,
+            subject: event.subject,
+            ip_address: event.ip_address,
+            user_agent: event.user_agent,
+            request_id: event.request_id,
+        }
+    }
+}
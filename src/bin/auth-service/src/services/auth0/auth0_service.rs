@@ -1,16 +1,25 @@
-use crate::consts::{ACCESS_TOKEN, APPLICATION_JSON, AUTHORIZATION, CONTENT_TYPE, GRANT_TYPE_PASS};
+use crate::consts::{ACCESS_TOKEN, APPLICATION_JSON, CONTENT_TYPE, GRANT_TYPE_PASS};
 use crate::errors::{Error, Result};
+use crate::opts::cmd_opts::Auth0Opts;
 use crate::services::actix_requests::models::{LoginUserResponse, RegisteredUserData, UserData};
 use crate::services::auth0::consts::{
-    CHANGE_PASSWORD_URL, GET_PROFILE_URL, LOGIN_URL, REGISTRATION_URL, SCOPE,
+    AUTHORIZATION_SCOPE, AUTHORIZE_URL, CHANGE_PASSWORD_URL, CODE_CHALLENGE_METHOD_S256,
+    GRANT_TYPE_AUTHORIZATION_CODE, GRANT_TYPE_PASSWORDLESS_OTP, JWKS_URL, LOGIN_URL,
+    PASSWORDLESS_SEND_CODE, PASSWORDLESS_SEND_LINK, PASSWORDLESS_START_URL, REGISTRATION_URL,
+    RESPONSE_TYPE_CODE, SCOPE,
 };
+use crate::services::auth0::errors::Auth0Error;
+use crate::services::auth0::http_client::{IdpClient, Retry};
 use crate::services::auth0::models::{
-    Auth0LoginResponse, Auth0RegisterResponse, Auth0Request, Auth0RequestBuilder, ChangePassFlow,
-    Claims, LoginFlow, SignupRequest, SignupRequestBuilder,
+    Auth0LoginResponse, Auth0RegisterResponse, Auth0Request, Auth0RequestBuilder,
+    AuthorizationCodeRequest, AuthorizationCodeRequestBuilder, ChangePassFlow, Claims,
+    IdTokenClaims, LoginFlow, MagicLinkParams, PasswordlessAuthParams, PasswordlessOtpRequest,
+    PasswordlessOtpRequestBuilder, PasswordlessStartRequest, PasswordlessStartRequestBuilder,
+    SignupRequest, SignupRequestBuilder,
 };
 use http::Method;
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
-use reqwest::Client;
+use reqwest::{Response, Url};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json::Value;
@@ -21,28 +30,29 @@ pub struct Auth0Service {
     client_id: Box<str>,
     client_secret: String,
     connection: String,
+    passwordless_connection: String,
+    social_connections: Vec<String>,
     client_url: String,
+    callback_url: String,
     audience: String,
     decoding_key: DecodingKey,
+    http: IdpClient,
 }
 
 impl Auth0Service {
-    pub fn new(
-        client_id: Box<str>,
-        client_secret: String,
-        connection: String,
-        client_url: String,
-        audience: String,
-        decoding_key: DecodingKey,
-    ) -> Self {
-        Auth0Service {
-            client_id,
-            client_secret,
-            connection,
-            client_url,
-            audience,
+    pub fn new(opts: Auth0Opts, decoding_key: DecodingKey) -> Result<Self> {
+        Ok(Auth0Service {
+            http: IdpClient::new(opts.http)?,
+            client_id: opts.client_id,
+            client_secret: opts.client_secret,
+            connection: opts.connection,
+            passwordless_connection: opts.passwordless_connection,
+            social_connections: opts.social_connections,
+            client_url: opts.client,
+            callback_url: opts.callback_url,
+            audience: opts.audience,
             decoding_key,
-        }
+        })
     }
 
     fn build_base_request<T: Serialize + DeserializeOwned + Debug + Default>(
@@ -105,118 +115,150 @@ impl Auth0Service {
         body.build()
     }
 
-    pub async fn send_request_to_change_pass(&self, user_id: String, email: String) -> Result<()> {
-        let client = Client::new();
+    fn build_body_for_passwordless_start(
+        &self,
+        email: String,
+        link: Option<MagicLinkParams>,
+    ) -> Result<PasswordlessStartRequest> {
+        let body = PasswordlessStartRequestBuilder::new();
+
+        let (send, scope) = match link {
+            Some(_) => (PASSWORDLESS_SEND_LINK, AUTHORIZATION_SCOPE),
+            None => (PASSWORDLESS_SEND_CODE, SCOPE),
+        };
+        let body = body
+            .client_id(self.client_id.to_string())
+            .client_secret(self.client_secret.clone())
+            .connection(self.passwordless_connection.clone())
+            .email(email)
+            .send(send.to_string())
+            .auth_params(PasswordlessAuthParams {
+                scope: scope.to_string(),
+                link,
+            });
+
+        body.build()
+    }
+
+    fn build_body_for_passwordless_otp(
+        &self,
+        email: String,
+        otp: String,
+    ) -> Result<PasswordlessOtpRequest> {
+        let body = PasswordlessOtpRequestBuilder::new();
+
+        let body = body
+            .grant_type(GRANT_TYPE_PASSWORDLESS_OTP.to_string())
+            .client_id(self.client_id.to_string())
+            .client_secret(self.client_secret.clone())
+            .username(email)
+            .otp(otp)
+            .realm(self.passwordless_connection.clone())
+            .audience(self.audience.clone())
+            .scope(SCOPE.to_string());
+
+        body.build()
+    }
+
+    fn build_body_for_authorization_code(
+        &self,
+        code: String,
+        code_verifier: String,
+    ) -> Result<AuthorizationCodeRequest> {
+        let body = AuthorizationCodeRequestBuilder::new();
+
+        let body = body
+            .grant_type(GRANT_TYPE_AUTHORIZATION_CODE.to_string())
+            .client_id(self.client_id.to_string())
+            .client_secret(self.client_secret.clone())
+            .code(code)
+            .code_verifier(code_verifier)
+            .redirect_uri(self.callback_url.clone());
 
+        body.build()
+    }
+
+    pub async fn send_request_to_change_pass(&self, user_id: String, email: String) -> Result<()> {
         let url = format!("{}/{}", self.client_url, CHANGE_PASSWORD_URL);
 
         let body = self.build_body_for_change_password(user_id, email)?;
 
-        client
+        let request = self
+            .http
+            .client()
             .request(Method::POST, &url)
             .header(CONTENT_TYPE, APPLICATION_JSON)
-            .json(&body)
-            .send()
+            .json(&body);
+        let result = self
+            .http
+            .send("change_password", request, Retry::ConnectOnly)
             .await?;
 
+        error_for_status(result).await?;
         Ok(())
     }
 
     pub async fn register_user(&self, user: UserData) -> Result<Auth0RegisterResponse> {
-        let client = Client::new();
-
         let url = format!("{}/{}", self.client_url, REGISTRATION_URL);
 
         let body = self.build_body_for_register(user.password, user.email, user.username)?;
 
-        let result = client.post(&url).json(&body).send().await?;
+        let request = self.http.client().post(&url).json(&body);
+        let result = self
+            .http
+            .send("signup", request, Retry::ConnectOnly)
+            .await?;
 
-        if result.status().is_success() {
-            let result = result.json::<Value>().await?;
-            let result = serde_json::from_value::<Auth0RegisterResponse>(result)?;
-            Ok(result)
-        } else {
-            Err(Error::StringError(result.text().await?))
-        }
+        let result = error_for_status(result).await?.json::<Value>().await?;
+        let result = serde_json::from_value::<Auth0RegisterResponse>(result)?;
+        Ok(result)
     }
 
     pub async fn get_jwt_user_token(&self, user: RegisteredUserData) -> Result<String> {
-        let client = Client::new();
-
         let url = format!("{}/{}", self.client_url, LOGIN_URL);
 
         let body = self.build_body_for_login(user)?;
 
-        let response = client
+        let request = self
+            .http
+            .client()
             .request(Method::POST, &url)
             .header(CONTENT_TYPE, APPLICATION_JSON)
-            .json(&body)
-            .send()
-            .await?;
+            .json(&body);
+        let response = self.http.send("login", request, Retry::ConnectOnly).await?;
 
+        let response = error_for_status(response).await?;
         let response: Result<Auth0LoginResponse> = response.json().await.map_err(Error::from);
         match response {
             Ok(value) => Ok(value.access_token),
             Err(e) => {
-                log::error!("Error: {}", e);
+                tracing::error!("Error: {}", e);
                 Err(Error::InvalidToken)
             }
         }
     }
 
-    pub async fn send_request_to_get_profile(&self, access_token: &str) -> Result<String> {
-        let client = Client::new();
-
-        let url = format!("{}/{}", self.client_url, GET_PROFILE_URL);
-        log::info!("Requesting profile from URL: {}", &url);
-
-        let result = client
-            .get(&url)
-            .header(AUTHORIZATION, format!("Bearer {}", access_token))
-            .send()
-            .await;
-
-        match result {
-            Ok(response) => {
-                log::info!("Response status: {}", response.status());
-                if response.status().is_success() {
-                    let body = response.text().await?;
-                    log::info!("Response body: {:?}", body);
-                    Ok(body)
-                } else {
-                    let error_body = response.text().await?;
-                    log::error!("Error response body: {:?}", error_body);
-                    Err(Error::StringError(error_body))
-                }
-            }
-            Err(e) => {
-                log::error!("Request failed: {:?}", e);
-                Err(Error::StringError(e.to_string()))
-            }
-        }
-    }
-
     pub async fn send_request_to_login(
         &self,
         user: RegisteredUserData,
     ) -> Result<LoginUserResponse> {
-        let client = Client::new();
-
         let url = format!("{}/{}", self.client_url, LOGIN_URL);
 
         let body = self.build_body_for_login(user)?;
 
-        let response = client
+        let request = self
+            .http
+            .client()
             .request(Method::POST, &url)
             .header(CONTENT_TYPE, APPLICATION_JSON)
-            .json(&body)
-            .send()
-            .await?;
+            .json(&body);
+        let response = self.http.send("login", request, Retry::ConnectOnly).await?;
 
+        let response = error_for_status(response).await?;
         let response: Result<Value> = response.json().await.map_err(Error::from);
         match response {
             Ok(value) => {
-                log::info!("Response: {:?}", value);
+                tracing::info!("Received tokens from Auth0");
                 let response = LoginUserResponse {
                     token: value[ACCESS_TOKEN]
                         .as_str()
@@ -226,12 +268,182 @@ impl Auth0Service {
                 Ok(response)
             }
             Err(e) => {
-                log::error!("Error: {}", e);
+                tracing::error!("Error: {}", e);
                 Err(Error::InvalidToken)
             }
         }
     }
 
+    /// Asks Auth0 to email a one-time code to `email`, or a magic link that
+    /// completes the authorization request in `link`.
+    pub async fn send_passwordless_start(
+        &self,
+        email: String,
+        link: Option<MagicLinkParams>,
+    ) -> Result<()> {
+        let url = format!("{}/{}", self.client_url, PASSWORDLESS_START_URL);
+
+        let body = self.build_body_for_passwordless_start(email, link)?;
+
+        let request = self
+            .http
+            .client()
+            .request(Method::POST, &url)
+            .header(CONTENT_TYPE, APPLICATION_JSON)
+            .json(&body);
+        let result = self
+            .http
+            .send("passwordless_start", request, Retry::ConnectOnly)
+            .await?;
+
+        error_for_status(result).await?;
+        Ok(())
+    }
+
+    /// Exchanges an emailed one-time code for tokens using the passwordless OTP grant.
+    pub async fn verify_passwordless_otp(&self, email: String, otp: String) -> Result<()> {
+        let url = format!("{}/{}", self.client_url, LOGIN_URL);
+
+        let body = self.build_body_for_passwordless_otp(email, otp)?;
+
+        let request = self
+            .http
+            .client()
+            .request(Method::POST, &url)
+            .header(CONTENT_TYPE, APPLICATION_JSON)
+            .json(&body);
+        let result = self
+            .http
+            .send("passwordless_otp", request, Retry::ConnectOnly)
+            .await?;
+
+        error_for_status(result).await?;
+        Ok(())
+    }
+
+    /// Fetches Auth0's signing keys and returns how many it publishes, which
+    /// tells whether the tenant is reachable and still has keys to verify with.
+    pub async fn fetch_jwks_key_count(&self) -> Result<usize> {
+        let url = format!("{}/{}", self.client_url, JWKS_URL);
+
+        let request = self.http.client().get(&url);
+        let result = self.http.send("jwks", request, Retry::Idempotent).await?;
+
+        let jwks = error_for_status(result).await?.json::<Value>().await?;
+        Ok(jwks["keys"].as_array().map_or(0, Vec::len))
+    }
+
+    /// Whether `/authorize` may send users to the given Auth0 connection.
+    pub fn is_allowed_connection(&self, connection: &str) -> bool {
+        connection == self.connection
+            || self
+                .social_connections
+                .iter()
+                .any(|social| social == connection)
+    }
+
+    /// Builds the Auth0 `/authorize` URL for the authorization code flow with PKCE,
+    /// optionally skipping the Auth0 login page for a specific connection.
+    pub fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+        connection: Option<&str>,
+    ) -> Result<String> {
+        let mut url = Url::parse_with_params(
+            &format!("{}/{}", self.client_url, AUTHORIZE_URL),
+            &[
+                ("response_type", RESPONSE_TYPE_CODE),
+                ("client_id", &self.client_id),
+                ("redirect_uri", &self.callback_url),
+                ("scope", AUTHORIZATION_SCOPE),
+                ("audience", &self.audience),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", code_challenge),
+                ("code_challenge_method", CODE_CHALLENGE_METHOD_S256),
+            ],
+        )
+        .map_err(|e| Error::StringError(e.to_string()))?;
+
+        if let Some(connection) = connection {
+            url.query_pairs_mut().append_pair("connection", connection);
+        }
+
+        Ok(url.to_string())
+    }
+
+    /// The authorization parameters for a magic link, the passwordless
+    /// counterpart of `authorization_url`.
+    pub fn magic_link_params(
+        &self,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> MagicLinkParams {
+        MagicLinkParams {
+            response_type: RESPONSE_TYPE_CODE.to_string(),
+            redirect_uri: self.callback_url.clone(),
+            audience: self.audience.clone(),
+            state: state.to_string(),
+            nonce: nonce.to_string(),
+            code_challenge: code_challenge.to_string(),
+            code_challenge_method: CODE_CHALLENGE_METHOD_S256.to_string(),
+        }
+    }
+
+    /// Exchanges an authorization code and its PKCE verifier for tokens.
+    pub async fn exchange_authorization_code(
+        &self,
+        code: String,
+        code_verifier: String,
+    ) -> Result<Auth0LoginResponse> {
+        let url = format!("{}/{}", self.client_url, LOGIN_URL);
+
+        let body = self.build_body_for_authorization_code(code, code_verifier)?;
+
+        let request = self
+            .http
+            .client()
+            .request(Method::POST, &url)
+            .header(CONTENT_TYPE, APPLICATION_JSON)
+            .json(&body);
+        let result = self
+            .http
+            .send("authorization_code", request, Retry::ConnectOnly)
+            .await?;
+
+        Ok(error_for_status(result)
+            .await?
+            .json::<Auth0LoginResponse>()
+            .await?)
+    }
+
+    /// Validates an `id_token` returned by the code exchange, including that it
+    /// answers the `nonce` sent with the authorization request.
+    pub fn validate_id_token(&self, id_token: &str, nonce: &str) -> Result<IdTokenClaims> {
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_issuer(&[format!("{}/", self.client_url)]);
+
+        let token_data = decode::<IdTokenClaims>(id_token, &self.decoding_key, &validation)
+            .map_err(|e| {
+                tracing::error!("Invalid id_token: {}", e);
+                Error::Unauthorized
+            })?;
+
+        match &token_data.claims.nonce {
+            Some(claimed) if openssl::memcmp::eq(claimed.as_bytes(), nonce.as_bytes()) => {
+                Ok(token_data.claims)
+            }
+            _ => {
+                tracing::error!("id_token nonce mismatch");
+                Err(Error::Unauthorized)
+            }
+        }
+    }
+
     pub fn decoding_key(&self) -> &DecodingKey {
         &self.decoding_key
     }
@@ -240,19 +452,45 @@ impl Auth0Service {
         &self.audience
     }
 
+    /// Validates an Auth0 access token and returns all of its claims.
+    pub fn decode_access_token(&self, token: &str) -> Result<serde_json::Map<String, Value>> {
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[&self.audience]);
+
+        let token_data =
+            decode::<serde_json::Map<String, Value>>(token, &self.decoding_key, &validation)?;
+        Ok(token_data.claims)
+    }
+
     pub fn extract_user_id(&self, token: &str) -> Result<String> {
         let mut validation = Validation::new(Algorithm::RS256);
 
         validation.set_audience(&[&self.audience]);
 
-        log::info!("Starting to decode token");
+        tracing::info!("Starting to decode token");
 
         let token_data = decode::<Claims>(token, &self.decoding_key, &validation)?;
 
-        log::info!("Token: {:?}", token_data.claims);
+        tracing::info!("Token decoded");
 
         let user_id = token_data.claims.sub.trim_start_matches("auth0|");
 
         Ok(user_id.to_string())
     }
 }
+
+/// Turns a non-2xx Auth0 response into an `Auth0Error`.
+async fn error_for_status(response: Response) -> Result<Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+
+    let error = Auth0Error::from_response(response).await;
+    tracing::error!(
+        "Auth0 request failed with status {}: {} ({})",
+        error.status,
+        error.error,
+        error.description
+    );
+    Err(error.into())
+}
@@ -0,0 +1,209 @@
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Stops calling the identity provider after repeated failures, so requests
+/// fail fast instead of piling up behind timeouts. After `reset_timeout` one
+/// trial call is let through; its outcome closes or reopens the breaker.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    reset_timeout: Duration,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    trial_in_flight: bool,
+}
+
+/// Lets one call go out. Resolved with `success` or `failure` once the call
+/// returns; a permit dropped unresolved, because the caller went away
+/// mid-call, says nothing about the provider and only frees the trial slot.
+#[must_use]
+pub struct CallPermit {
+    breaker: CircuitBreaker,
+    trial: bool,
+    resolved: bool,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, reset_timeout: Duration) -> Self {
+        CircuitBreaker {
+            failure_threshold: failure_threshold.max(1),
+            reset_timeout,
+            state: Arc::new(Mutex::new(BreakerState::default())),
+        }
+    }
+
+    /// A permit when a call may go out now.
+    pub fn allow(&self) -> Option<CallPermit> {
+        let mut state = self.lock();
+        let trial = match state.open_until {
+            None => false,
+            Some(open_until) if Instant::now() < open_until => return None,
+            Some(_) if state.trial_in_flight => return None,
+            Some(_) => {
+                state.trial_in_flight = true;
+                true
+            }
+        };
+        Some(CallPermit {
+            breaker: self.clone(),
+            trial,
+            resolved: false,
+        })
+    }
+
+    fn record_success(&self) {
+        let mut state = self.lock();
+        if state.open_until.is_some() {
+            tracing::info!("Identity provider recovered, closing circuit breaker");
+        }
+        *state = BreakerState::default();
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.lock();
+        state.consecutive_failures += 1;
+        let was_trial = std::mem::take(&mut state.trial_in_flight);
+
+        if was_trial || state.consecutive_failures >= self.failure_threshold {
+            if state.open_until.is_none() || was_trial {
+                tracing::warn!(
+                    "Identity provider failed {} times in a row, opening circuit breaker for {:?}",
+                    state.consecutive_failures,
+                    self.reset_timeout
+                );
+            }
+            state.open_until = Some(Instant::now() + self.reset_timeout);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl CallPermit {
+    pub fn success(mut self) {
+        self.resolved = true;
+        self.breaker.record_success();
+    }
+
+    pub fn failure(mut self) {
+        self.resolved = true;
+        self.breaker.record_failure();
+    }
+}
+
+impl Drop for CallPermit {
+    fn drop(&mut self) {
+        if self.trial && !self.resolved {
+            self.breaker.lock().trial_in_flight = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::{Error, Result};
+
+    enum Step {
+        Success,
+        Failure,
+        Abandon,
+        Wait,
+    }
+
+    const RESET_TIMEOUT: Duration = Duration::from_millis(50);
+
+    /// Runs the steps in order, each on a freshly allowed call, and checks
+    /// whether the call after the last one is let through.
+    fn allowed_after(threshold: u32, steps: &[Step]) -> Result<bool> {
+        let breaker = CircuitBreaker::new(threshold, RESET_TIMEOUT);
+        for step in steps {
+            if let Step::Wait = step {
+                std::thread::sleep(RESET_TIMEOUT + Duration::from_millis(10));
+                continue;
+            }
+            let permit = breaker
+                .allow()
+                .ok_or(Error::StringError("call was rejected".to_string()))?;
+            match step {
+                Step::Success => permit.success(),
+                Step::Failure => permit.failure(),
+                Step::Abandon => drop(permit),
+                Step::Wait => unreachable!(),
+            }
+        }
+        Ok(breaker.allow().is_some())
+    }
+
+    #[test]
+    fn moves_between_states() -> Result<()> {
+        use Step::*;
+        let cases: &[(&str, u32, &[Step], bool)] = &[
+            ("closed", 3, &[], true),
+            ("below threshold", 3, &[Failure, Failure], true),
+            ("opens at threshold", 3, &[Failure, Failure, Failure], false),
+            (
+                "success resets count",
+                2,
+                &[Failure, Success, Failure],
+                true,
+            ),
+            ("threshold of one opens at once", 1, &[Failure], false),
+            ("half-open after timeout", 1, &[Failure, Wait], true),
+            (
+                "trial success closes",
+                1,
+                &[Failure, Wait, Success, Success],
+                true,
+            ),
+            (
+                "trial failure reopens",
+                3,
+                &[Failure, Failure, Failure, Wait, Failure],
+                false,
+            ),
+            (
+                "reopened breaker trials again",
+                1,
+                &[Failure, Wait, Failure, Wait],
+                true,
+            ),
+            (
+                "abandoned trial frees the slot",
+                1,
+                &[Failure, Wait, Abandon],
+                true,
+            ),
+            ("abandoned call keeps it closed", 1, &[Abandon], true),
+        ];
+
+        for (name, threshold, steps, allowed) in cases {
+            assert_eq!(allowed_after(*threshold, steps)?, *allowed, "{}", name);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn lets_one_trial_through() -> Result<()> {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        let rejected = |what: &str| Error::StringError(format!("{} was rejected", what));
+        breaker.allow().ok_or(rejected("call"))?.failure();
+
+        let trial = breaker.allow().ok_or(rejected("trial"))?;
+        assert!(breaker.allow().is_none());
+
+        drop(trial);
+        let trial = breaker.allow().ok_or(rejected("second trial"))?;
+        trial.success();
+        assert!(breaker.allow().is_some());
+        assert!(breaker.allow().is_some());
+        Ok(())
+    }
+}
@@ -1,5 +1,14 @@
 pub const REGISTRATION_URL: &str = "dbconnections/signup";
 pub const LOGIN_URL: &str = "oauth/token";
 pub const CHANGE_PASSWORD_URL: &str = "dbconnections/change_password";
-pub const GET_PROFILE_URL: &str = "userinfo";
 pub const SCOPE: &str = "openid";
+pub const PASSWORDLESS_START_URL: &str = "passwordless/start";
+pub const GRANT_TYPE_PASSWORDLESS_OTP: &str = "http://auth0.com/oauth/grant-type/passwordless/otp";
+pub const PASSWORDLESS_SEND_LINK: &str = "link";
+pub const PASSWORDLESS_SEND_CODE: &str = "code";
+pub const AUTHORIZE_URL: &str = "authorize";
+pub const AUTHORIZATION_SCOPE: &str = "openid profile email";
+pub const GRANT_TYPE_AUTHORIZATION_CODE: &str = "authorization_code";
+pub const RESPONSE_TYPE_CODE: &str = "code";
+pub const CODE_CHALLENGE_METHOD_S256: &str = "S256";
+pub const JWKS_URL: &str = ".well-known/jwks.json";
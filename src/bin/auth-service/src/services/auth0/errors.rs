@@ -1,3 +1,8 @@
+use crate::errors::ErrorCode;
+use reqwest::Response;
+use serde::Deserialize;
+use serde_json::Value;
+
 #[derive(Debug, thiserror::Error)]
 pub enum BuildError {
     #[error("Missing client id")]
@@ -22,4 +27,106 @@ pub enum BuildError {
     MissingUsername,
     #[error("Missing scope")]
     MissingScope,
+    #[error("Missing send")]
+    MissingSend,
+    #[error("Missing auth params")]
+    MissingAuthParams,
+    #[error("Missing otp")]
+    MissingOtp,
+    #[error("Missing realm")]
+    MissingRealm,
+    #[error("Missing code")]
+    MissingCode,
+    #[error("Missing code verifier")]
+    MissingCodeVerifier,
+    #[error("Missing redirect uri")]
+    MissingRedirectUri,
+    #[error("Missing token")]
+    MissingToken,
+}
+
+/// An error answered by Auth0. The Authentication API reports
+/// `error`/`error_description`, signup reports `code`/`description` and the
+/// Management API `error`/`message`.
+#[derive(Debug, thiserror::Error)]
+#[error("{description}")]
+pub struct Auth0Error {
+    pub status: u16,
+    pub error: String,
+    pub description: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Auth0ErrorBody {
+    error: Option<String>,
+    error_description: Option<String>,
+    code: Option<String>,
+    name: Option<String>,
+    description: Option<Value>,
+    message: Option<String>,
+}
+
+impl Auth0Error {
+    pub async fn from_response(response: Response) -> Self {
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        Self::from_body(status, &body)
+    }
+
+    pub fn from_body(status: u16, body: &str) -> Self {
+        let parsed = serde_json::from_str::<Auth0ErrorBody>(body).unwrap_or_default();
+
+        let error = parsed
+            .code
+            .or(parsed.error)
+            .or(parsed.name)
+            .unwrap_or_else(|| format!("http_{}", status));
+        // Password policy errors carry an object describing the failed rules.
+        let description = parsed
+            .error_description
+            .or(parsed.message)
+            .or_else(|| match parsed.description {
+                Some(Value::String(description)) => Some(description),
+                Some(Value::Object(description)) => description
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                _ => None,
+            })
+            .unwrap_or_else(|| "Identity provider request failed".to_string());
+
+        Auth0Error {
+            status,
+            error,
+            description,
+        }
+    }
+
+    /// The client-facing error code, see `Error::code`.
+    pub fn code(&self) -> ErrorCode {
+        match self.error.as_str() {
+            "invalid_grant" | "invalid_user_password" | "wrong_email_or_password" => {
+                ErrorCode::InvalidCredentials
+            }
+            "user_exists" | "username_exists" => ErrorCode::UserExists,
+            "invalid_password"
+            | "password_strength_error"
+            | "password_dictionary_error"
+            | "password_no_user_info_error"
+            | "password_leaked" => ErrorCode::WeakPassword,
+            "too_many_attempts" | "too_many_requests" => ErrorCode::TooManyAttempts,
+            "mfa_required" => ErrorCode::MfaRequired,
+            "blocked_user" => ErrorCode::UserBlocked,
+            "unauthorized" | "access_denied" => ErrorCode::AccessDenied,
+            "invalid_signup" | "bad.email" | "bad.username" | "invalid_request" => {
+                ErrorCode::InvalidRequest
+            }
+            "invalid_token" | "expired_token" => ErrorCode::InvalidToken,
+            _ if self.status == 429 => ErrorCode::TooManyAttempts,
+            _ if self.status == 503 => ErrorCode::IdpUnavailable,
+            // Anything else means we sent something Auth0 did not expect or
+            // Auth0 itself failed, neither of which the client can fix.
+            _ => ErrorCode::IdpError,
+        }
+    }
 }
@@ -0,0 +1,199 @@
+use crate::errors::{Error, Result};
+use crate::opts::cmd_opts::HttpClientOpts;
+use crate::services::auth0::circuit_breaker::{CallPermit, CircuitBreaker};
+use crate::telemetry::inject_context;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+
+/// Whether a call may be sent again after it possibly reached the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retry {
+    /// Safe to repeat, e.g. reads and revocations.
+    Idempotent,
+    /// Only retried when the connection could not be made at all.
+    ConnectOnly,
+}
+
+/// The pooled HTTP client for identity provider calls, with timeouts,
+/// retries, a circuit breaker and latency metrics.
+#[derive(Clone)]
+pub struct IdpClient {
+    client: Client,
+    max_retries: u32,
+    retry_base_delay: Duration,
+    breaker: CircuitBreaker,
+}
+
+impl IdpClient {
+    pub fn new(opts: HttpClientOpts) -> Result<Self> {
+        let client = Client::builder()
+            .connect_timeout(Duration::from_millis(opts.connect_timeout))
+            .read_timeout(Duration::from_millis(opts.read_timeout))
+            .build()?;
+
+        Ok(IdpClient {
+            client,
+            max_retries: opts.max_retries,
+            retry_base_delay: Duration::from_millis(opts.retry_base_delay),
+            breaker: CircuitBreaker::new(
+                opts.breaker_failure_threshold,
+                Duration::from_secs(opts.breaker_reset_timeout),
+            ),
+        })
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Sends a request built on `client()` in its own span, retries included.
+    /// `call` names the operation in metrics and logs.
+    pub async fn send(
+        &self,
+        call: &'static str,
+        request: RequestBuilder,
+        retry: Retry,
+    ) -> Result<Response> {
+        let span = tracing::info_span!(
+            "idp_request",
+            otel.name = call,
+            otel.kind = "client",
+            otel.status_code = Empty,
+            idp.call = call,
+            idp.retries = Empty,
+            http.response.status_code = Empty,
+        );
+
+        async move {
+            let result = self.send_with_retries(call, request, retry).await;
+
+            let span = Span::current();
+            match &result {
+                Ok(response) => {
+                    span.record("http.response.status_code", response.status().as_u16());
+                    if response.status().is_server_error() {
+                        span.record("otel.status_code", "ERROR");
+                    }
+                }
+                Err(_) => {
+                    span.record("otel.status_code", "ERROR");
+                }
+            }
+            result
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn send_with_retries(
+        &self,
+        call: &'static str,
+        request: RequestBuilder,
+        retry: Retry,
+    ) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let Some(permit) = self.breaker.allow() else {
+                metrics::counter!("idp_requests_rejected_total", "call" => call).increment(1);
+                return Err(Error::IdpUnavailable);
+            };
+
+            let Some(this_attempt) = request.try_clone() else {
+                // Streaming bodies can't be replayed, so they go out once.
+                return self.send_once(call, request, permit).await;
+            };
+            let result = self.send_once(call, this_attempt, permit).await;
+
+            if attempt >= self.max_retries || !should_retry(&result, retry) {
+                return result;
+            }
+
+            attempt += 1;
+            Span::current().record("idp.retries", attempt);
+            metrics::counter!("idp_request_retries_total", "call" => call).increment(1);
+            let delay = self.backoff(attempt);
+            tracing::warn!(
+                "Retrying identity provider call {} in {:?} (attempt {})",
+                call,
+                delay,
+                attempt
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Sends one attempt. The permit is only resolved once the call returns,
+    /// so an attempt cancelled mid-flight leaves the breaker as it was.
+    async fn send_once(
+        &self,
+        call: &'static str,
+        request: RequestBuilder,
+        permit: CallPermit,
+    ) -> Result<Response> {
+        let mut headers = HeaderMap::new();
+        inject_context(&mut headers);
+
+        let started = Instant::now();
+        let result = request.headers(headers).send().await;
+
+        let outcome = match &result {
+            Ok(response) if response.status().is_server_error() => {
+                permit.failure();
+                "5xx"
+            }
+            Ok(response) => {
+                permit.success();
+                if response.status().is_client_error() {
+                    "4xx"
+                } else {
+                    "2xx"
+                }
+            }
+            Err(_) => {
+                permit.failure();
+                "error"
+            }
+        };
+        metrics::histogram!(
+            "idp_request_duration_seconds",
+            "call" => call,
+            "outcome" => outcome
+        )
+        .record(started.elapsed().as_secs_f64());
+        if matches!(outcome, "5xx" | "error") {
+            metrics::counter!("idp_request_errors_total", "call" => call, "outcome" => outcome)
+                .increment(1);
+        }
+
+        Ok(result?)
+    }
+
+    /// Full jitter: a random delay up to the base delay doubled per attempt.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let max = self
+            .retry_base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1));
+        let mut random = [0u8; 4];
+        let fraction = match openssl::rand::rand_bytes(&mut random) {
+            Ok(()) => u32::from_le_bytes(random) as f64 / u32::MAX as f64,
+            Err(_) => 1.0,
+        };
+        max.mul_f64(fraction)
+    }
+}
+
+fn should_retry(result: &Result<Response>, retry: Retry) -> bool {
+    match result {
+        Err(Error::ReqwestError(e)) if e.is_connect() => true,
+        _ if retry == Retry::ConnectOnly => false,
+        Err(Error::ReqwestError(e)) => e.is_timeout(),
+        Ok(response) => matches!(
+            response.status(),
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+        ),
+        Err(_) => false,
+    }
+}
@@ -1,4 +1,6 @@
 pub mod auth0_service;
+pub mod circuit_breaker;
 pub mod consts;
 pub mod errors;
+pub mod http_client;
 pub mod models;
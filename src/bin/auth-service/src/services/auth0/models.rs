@@ -55,9 +55,74 @@ pub struct ChangePassFlow {
     pub email: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PasswordlessAuthParams {
+    pub scope: String,
+    #[serde(flatten)]
+    pub link: Option<MagicLinkParams>,
+}
+
+/// The authorization request a magic link completes. Auth0 sends the browser
+/// to `redirect_uri` with a code, as it does after `/authorize`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MagicLinkParams {
+    pub response_type: String,
+    pub redirect_uri: String,
+    pub audience: String,
+    pub state: String,
+    pub nonce: String,
+    pub code_challenge: String,
+    pub code_challenge_method: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, Builder)]
+pub struct PasswordlessStartRequest {
+    pub client_id: String,
+    pub client_secret: String,
+    pub connection: String,
+    pub email: String,
+    pub send: String,
+    #[serde(rename = "authParams")]
+    pub auth_params: PasswordlessAuthParams,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, Builder)]
+pub struct PasswordlessOtpRequest {
+    pub grant_type: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub username: String,
+    pub otp: String,
+    pub realm: String,
+    pub audience: String,
+    pub scope: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, Builder)]
+pub struct AuthorizationCodeRequest {
+    pub grant_type: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub code: String,
+    pub code_verifier: String,
+    pub redirect_uri: String,
+}
+
+/// Claims of an Auth0 `id_token` that the service relies on.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub nickname: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Claims {
     pub sub: String,
+    #[serde(default)]
+    pub jti: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
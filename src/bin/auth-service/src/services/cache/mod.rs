@@ -0,0 +1 @@
+pub mod ttl_cache;
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+const MAX_ENTRIES: usize = 10000;
+
+/// In-memory cache whose entries expire after a ttl, shared by clones. When
+/// full, expired entries are dropped to make room, and new entries are not
+/// cached while none have expired. A zero ttl caches nothing.
+#[derive(Clone)]
+pub struct TtlCache<T> {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, (Instant, T)>>>,
+}
+
+impl<T: Clone> TtlCache<T> {
+    pub fn new(ttl: u64) -> Self {
+        TtlCache {
+            ttl: Duration::from_secs(ttl),
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        match entries.get(key) {
+            Some((expires_at, value)) if *expires_at > Instant::now() => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, value: T) {
+        self.insert_for(key, value, self.ttl);
+    }
+
+    /// Caches `value` for `ttl`, never longer than the cache's own ttl.
+    pub fn insert_for(&self, key: String, value: T, ttl: Duration) {
+        let ttl = ttl.min(self.ttl);
+        if ttl.is_zero() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, (expires_at, _)| *expires_at > now);
+        }
+        if entries.len() < MAX_ENTRIES {
+            entries.insert(key, (now + ttl, value));
+        }
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expires_entries() {
+        let cache = TtlCache::new(60);
+        cache.insert("kept".to_string(), 1);
+        cache.insert_for("short".to_string(), 2, Duration::from_millis(1));
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(cache.get("kept"), Some(1));
+        assert_eq!(cache.get("short"), None);
+
+        cache.remove("kept");
+        assert_eq!(cache.get("kept"), None);
+    }
+
+    #[test]
+    fn caches_nothing_without_a_ttl() {
+        let cache = TtlCache::new(0);
+        cache.insert("key".to_string(), 1);
+        cache.insert_for("other".to_string(), 2, Duration::MAX);
+
+        assert_eq!(cache.get("key"), None);
+        assert_eq!(cache.get("other"), None);
+    }
+}
@@ -1,5 +1,7 @@
 pub mod postgres_db;
 
+pub mod purge_service;
+
 pub mod tables;
 
 pub mod schema;
@@ -0,0 +1,49 @@
+use crate::services::actors::messages::{PurgeExpired, Stop};
+use crate::services::actors::traced::TracedSend;
+use crate::services::db::postgres_db::DbService;
+use actix::{Actor, ActorContext, Addr, AsyncContext, Context, Handler, WrapFuture};
+use std::time::Duration;
+
+/// Periodically deletes rows nothing reads once they expire: revoked token
+/// ids, sessions, authorization codes, refresh tokens and challenges.
+pub struct PurgeService {
+    db: Addr<DbService>,
+    interval: Duration,
+}
+
+impl PurgeService {
+    pub fn new(db: Addr<DbService>, interval: u64) -> Self {
+        PurgeService {
+            db,
+            interval: Duration::from_secs(interval),
+        }
+    }
+}
+
+impl Actor for PurgeService {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(self.interval, |service, ctx| {
+            let db = service.db.clone();
+            ctx.spawn(
+                async move {
+                    match db.send_traced(PurgeExpired).await {
+                        Ok(Ok(purged)) => tracing::info!("Purged {} expired rows", purged),
+                        Ok(Err(e)) => tracing::error!("Failed to purge expired rows: {}", e),
+                        Err(e) => tracing::error!("Failed to reach the database actor: {}", e),
+                    }
+                }
+                .into_actor(service),
+            );
+        });
+    }
+}
+
+impl Handler<Stop> for PurgeService {
+    type Result = ();
+
+    fn handle(&mut self, _: Stop, ctx: &mut Self::Context) -> Self::Result {
+        ctx.stop();
+    }
+}
@@ -7,3 +7,161 @@ diesel::table!(users {
     created_at -> Timestamptz,
     updated_at -> Nullable<Timestamptz>
 });
+
+diesel::table!(mfa_factors (user_id) {
+    user_id -> Varchar,
+    encrypted_secret -> Bytea,
+    is_confirmed -> Bool,
+    last_used_step -> Nullable<Int8>,
+    created_at -> Timestamptz,
+    confirmed_at -> Nullable<Timestamptz>
+});
+
+diesel::table!(mfa_recovery_codes (code_hash) {
+    code_hash -> Varchar,
+    user_id -> Varchar,
+    created_at -> Timestamptz,
+    used_at -> Nullable<Timestamptz>
+});
+
+diesel::table!(mfa_challenges (token_hash) {
+    token_hash -> Varchar,
+    user_id -> Varchar,
+    encrypted_access_token -> Bytea,
+    attempts -> Int4,
+    created_at -> Timestamptz,
+    expires_at -> Timestamptz
+});
+
+diesel::table!(webauthn_credentials (credential_id) {
+    credential_id -> Varchar,
+    user_id -> Varchar,
+    public_key -> Bytea,
+    alg -> Int4,
+    sign_count -> Int8,
+    created_at -> Timestamptz,
+    last_used_at -> Nullable<Timestamptz>
+});
+
+diesel::table!(webauthn_challenges (challenge) {
+    challenge -> Varchar,
+    user_id -> Nullable<Varchar>,
+    ceremony -> Varchar,
+    created_at -> Timestamptz,
+    expires_at -> Timestamptz
+});
+
+diesel::table!(oauth_authorization_requests (state) {
+    state -> Varchar,
+    code_verifier -> Varchar,
+    nonce -> Varchar,
+    created_at -> Timestamptz,
+    expires_at -> Timestamptz,
+    connection -> Nullable<Varchar>,
+    client_id -> Nullable<Varchar>,
+    redirect_uri -> Nullable<Varchar>,
+    client_state -> Nullable<Varchar>,
+    client_code_challenge -> Nullable<Varchar>,
+    client_nonce -> Nullable<Varchar>,
+    scope -> Nullable<Varchar>,
+    link_user_id -> Nullable<Varchar>
+});
+
+diesel::table!(user_identities (provider, provider_user_id) {
+    provider -> Varchar,
+    provider_user_id -> Varchar,
+    user_id -> Varchar,
+    linked_at -> Timestamptz
+});
+
+diesel::table!(authorization_codes (code_hash) {
+    code_hash -> Varchar,
+    client_id -> Varchar,
+    user_id -> Varchar,
+    redirect_uri -> Varchar,
+    code_challenge -> Nullable<Varchar>,
+    nonce -> Nullable<Varchar>,
+    scope -> Varchar,
+    created_at -> Timestamptz,
+    expires_at -> Timestamptz,
+    session_id -> Nullable<Uuid>
+});
+
+diesel::table!(refresh_tokens (token_hash) {
+    token_hash -> Varchar,
+    client_id -> Varchar,
+    user_id -> Varchar,
+    scope -> Varchar,
+    created_at -> Timestamptz,
+    expires_at -> Timestamptz,
+    session_id -> Nullable<Uuid>
+});
+
+diesel::table!(signing_keys (kid) {
+    kid -> Varchar,
+    alg -> Varchar,
+    state -> Varchar,
+    encrypted_private_key -> Bytea,
+    created_at -> Timestamptz,
+    activated_at -> Nullable<Timestamptz>,
+    retired_at -> Nullable<Timestamptz>,
+    revoked_at -> Nullable<Timestamptz>
+});
+
+diesel::table!(revoked_tokens (token_id) {
+    token_id -> Varchar,
+    expires_at -> Timestamptz,
+    revoked_at -> Timestamptz
+});
+
+diesel::table!(oauth_clients (client_id) {
+    client_id -> Varchar,
+    name -> Varchar,
+    client_secret_hash -> Nullable<Varchar>,
+    grant_types -> Array<Text>,
+    redirect_uris -> Array<Text>,
+    allowed_scopes -> Array<Text>,
+    access_token_ttl -> Nullable<Int8>,
+    refresh_token_ttl -> Nullable<Int8>,
+    created_at -> Timestamptz,
+    updated_at -> Nullable<Timestamptz>
+});
+
+diesel::table!(api_keys (id) {
+    id -> Uuid,
+    prefix -> Varchar,
+    secret_hash -> Varchar,
+    name -> Varchar,
+    user_id -> Nullable<Varchar>,
+    client_id -> Nullable<Varchar>,
+    scope -> Varchar,
+    expires_at -> Nullable<Timestamptz>,
+    last_used_at -> Nullable<Timestamptz>,
+    created_at -> Timestamptz,
+    revoked_at -> Nullable<Timestamptz>
+});
+
+diesel::table!(sessions (id) {
+    id -> Uuid,
+    token_hash -> Varchar,
+    user_id -> Varchar,
+    user_agent -> Nullable<Varchar>,
+    ip_address -> Nullable<Varchar>,
+    created_at -> Timestamptz,
+    last_seen_at -> Timestamptz,
+    expires_at -> Timestamptz,
+    geo_hint -> Nullable<Varchar>
+});
+
+diesel::table!(audit_events (id) {
+    id -> Int8,
+    occurred_at -> Timestamptz,
+    action -> Varchar,
+    outcome -> Varchar,
+    reason -> Nullable<Varchar>,
+    actor -> Nullable<Varchar>,
+    subject -> Nullable<Varchar>,
+    ip_address -> Nullable<Varchar>,
+    user_agent -> Nullable<Varchar>,
+    request_id -> Nullable<Varchar>
+});
@@ -1,6 +1,11 @@
-use crate::services::db::schema::users;
+use crate::services::db::schema::{
+    api_keys, audit_events, authorization_codes, mfa_challenges, mfa_factors, mfa_recovery_codes,
+    oauth_authorization_requests, oauth_clients, refresh_tokens, revoked_tokens, sessions,
+    signing_keys, user_identities, users, webauthn_challenges, webauthn_credentials,
+};
 use chrono::{DateTime, Utc};
 use diesel::{Insertable, Queryable};
+use uuid::Uuid;
 
 #[derive(Debug, Clone, Queryable, Insertable)]
 #[diesel(table_name = users)]
@@ -12,3 +17,212 @@ pub struct Users {
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = mfa_factors)]
+pub struct MfaFactors {
+    pub user_id: String,
+    pub encrypted_secret: Vec<u8>,
+    pub is_confirmed: bool,
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = mfa_recovery_codes)]
+pub struct MfaRecoveryCodes {
+    pub code_hash: String,
+    pub user_id: String,
+    pub created_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = mfa_challenges)]
+pub struct MfaChallenges {
+    pub token_hash: String,
+    pub user_id: String,
+    pub encrypted_access_token: Vec<u8>,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = webauthn_credentials)]
+pub struct WebauthnCredentials {
+    pub credential_id: String,
+    pub user_id: String,
+    pub public_key: Vec<u8>,
+    pub alg: i32,
+    pub sign_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = webauthn_challenges)]
+pub struct WebauthnChallenges {
+    pub challenge: String,
+    pub user_id: Option<String>,
+    pub ceremony: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = oauth_authorization_requests)]
+pub struct OauthAuthorizationRequests {
+    pub state: String,
+    pub code_verifier: String,
+    pub nonce: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub connection: Option<String>,
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub client_state: Option<String>,
+    pub client_code_challenge: Option<String>,
+    pub client_nonce: Option<String>,
+    pub scope: Option<String>,
+    pub link_user_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = user_identities)]
+pub struct UserIdentities {
+    pub provider: String,
+    pub provider_user_id: String,
+    pub user_id: String,
+    pub linked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = authorization_codes)]
+pub struct AuthorizationCodes {
+    pub code_hash: String,
+    pub client_id: String,
+    pub user_id: String,
+    pub redirect_uri: String,
+    pub code_challenge: Option<String>,
+    pub nonce: Option<String>,
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub session_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = refresh_tokens)]
+pub struct RefreshTokens {
+    pub token_hash: String,
+    pub client_id: String,
+    pub user_id: String,
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// The browser session the grant came from; the token dies with it.
+    pub session_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = signing_keys)]
+pub struct SigningKeys {
+    pub kid: String,
+    pub alg: String,
+    pub state: String,
+    pub encrypted_private_key: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    pub activated_at: Option<DateTime<Utc>>,
+    pub retired_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = revoked_tokens)]
+pub struct RevokedTokens {
+    pub token_id: String,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: DateTime<Utc>,
+}
+
+/// A registered OAuth client. Clients without a secret are public and must use
+/// PKCE; empty `allowed_scopes` allows every supported scope and unset token
+/// lifetimes fall back to the configured defaults.
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = oauth_clients)]
+pub struct OauthClients {
+    pub client_id: String,
+    pub name: String,
+    pub client_secret_hash: Option<String>,
+    pub grant_types: Vec<String>,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub access_token_ttl: Option<i64>,
+    pub refresh_token_ttl: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A long-lived credential owned by either a user or an OAuth client. Only
+/// the hash of the secret part is stored; `prefix` identifies the key.
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = api_keys)]
+pub struct ApiKeys {
+    pub id: Uuid,
+    pub prefix: String,
+    pub secret_hash: String,
+    pub name: String,
+    pub user_id: Option<String>,
+    pub client_id: Option<String>,
+    pub scope: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// A browser session. The cookie holds the opaque token, only its hash is stored.
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = sessions)]
+pub struct Sessions {
+    pub id: Uuid,
+    pub token_hash: String,
+    pub user_id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub geo_hint: Option<String>,
+}
+
+/// A recorded security-relevant event. Rows are never updated or deleted.
+#[derive(Debug, Clone, Queryable)]
+pub struct AuditEvents {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub action: String,
+    pub outcome: String,
+    pub reason: Option<String>,
+    pub actor: Option<String>,
+    pub subject: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = audit_events)]
+pub struct NewAuditEvent {
+    pub occurred_at: DateTime<Utc>,
+    pub action: String,
+    pub outcome: String,
+    pub reason: Option<String>,
+    pub actor: Option<String>,
+    pub subject: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
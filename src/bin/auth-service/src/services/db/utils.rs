@@ -1,10 +1,24 @@
 use crate::errors::Result;
+use crate::services::health::models::PoolHealth;
 use diesel_async::pooled_connection::deadpool::Pool;
 use diesel_async::pooled_connection::AsyncDieselConnectionManager;
 use diesel_async::AsyncPgConnection;
 
 pub type DatabasePool = Pool<AsyncPgConnection>;
 
+impl From<&DatabasePool> for PoolHealth {
+    fn from(pool: &DatabasePool) -> Self {
+        let status = pool.status();
+        PoolHealth {
+            max_size: status.max_size,
+            size: status.size,
+            // deadpool reports waiters as negative availability.
+            available: status.available.max(0) as usize,
+            waiting: (-status.available).max(0) as usize,
+        }
+    }
+}
+
 pub async fn create_connection_pool(database_url: String) -> Result<DatabasePool> {
     let manager = AsyncDieselConnectionManager::new(database_url);
     let pool = Pool::builder(manager).build()?;
@@ -0,0 +1,7 @@
+pub const STATUS_UP: &str = "up";
+pub const STATUS_DOWN: &str = "down";
+
+/// Reported instead of an error code when a check didn't finish in time.
+pub const ERROR_TIMEOUT: &str = "timeout";
+/// Reported when the identity provider publishes no signing keys.
+pub const ERROR_NO_SIGNING_KEYS: &str = "no_signing_keys";
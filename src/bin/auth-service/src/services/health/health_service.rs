@@ -0,0 +1,161 @@
+use crate::errors::Result;
+use crate::opts::cmd_opts::HealthOpts;
+use crate::services::actors::messages::CheckDatabase;
+use crate::services::actors::traced::TracedSend;
+use crate::services::auth0::auth0_service::Auth0Service;
+use crate::services::db::postgres_db::DbService;
+use crate::services::db::utils::DatabasePool;
+use crate::services::health::consts::{
+    ERROR_NO_SIGNING_KEYS, ERROR_TIMEOUT, STATUS_DOWN, STATUS_UP,
+};
+use crate::services::health::models::{
+    ComponentHealth, PoolHealth, ReadinessComponents, ReadinessResponse,
+};
+use actix::Addr;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Checks the components the service can't work without. Results are kept
+/// for a while so frequent probes don't turn into load on them.
+#[derive(Clone)]
+pub struct HealthService {
+    db: Addr<DbService>,
+    pool: DatabasePool,
+    auth0: Auth0Service,
+    check_timeout: Duration,
+    idp_required: bool,
+    database: Arc<CachedCheck>,
+    idp: Arc<CachedCheck>,
+}
+
+impl HealthService {
+    pub fn new(
+        db: Addr<DbService>,
+        pool: DatabasePool,
+        auth0: Auth0Service,
+        opts: HealthOpts,
+    ) -> Self {
+        let cache_ttl = Duration::from_secs(opts.cache_ttl);
+        HealthService {
+            db,
+            pool,
+            auth0,
+            check_timeout: Duration::from_millis(opts.check_timeout),
+            idp_required: opts.idp_required,
+            database: Arc::new(CachedCheck::new(cache_ttl)),
+            idp: Arc::new(CachedCheck::new(cache_ttl)),
+        }
+    }
+
+    pub async fn readiness(&self) -> ReadinessResponse {
+        let (database, idp) = futures_util::join!(
+            self.database.get(|| self.check_database()),
+            self.idp.get(|| self.check_idp()),
+        );
+
+        let ready = database.status == STATUS_UP && (idp.status == STATUS_UP || !idp.required);
+        ReadinessResponse {
+            status: if ready { STATUS_UP } else { STATUS_DOWN },
+            components: ReadinessComponents { database, idp },
+        }
+    }
+
+    /// Gets a connection from the pool and runs a query through `DbService`,
+    /// so a stuck actor shows up as well.
+    async fn check_database(&self) -> ComponentHealth {
+        let started = Instant::now();
+        let result = self
+            .timed(async { self.db.send_traced(CheckDatabase).await? })
+            .await;
+
+        let mut health = component(true, started, result);
+        health.pool = Some(PoolHealth::from(&self.pool));
+        health
+    }
+
+    /// Fetches the identity provider's JWKS, which fails fast while the
+    /// circuit breaker is open.
+    async fn check_idp(&self) -> ComponentHealth {
+        let started = Instant::now();
+        let result = self.timed(self.auth0.fetch_jwks_key_count()).await;
+
+        let keys = result.as_ref().ok().copied();
+        let result = result.and_then(|keys| match keys {
+            0 => Err(ERROR_NO_SIGNING_KEYS),
+            _ => Ok(()),
+        });
+        let mut health = component(self.idp_required, started, result);
+        health.jwks_keys = keys;
+        health
+    }
+
+    /// Runs a check, turning its error into the code reported for it.
+    async fn timed<T>(
+        &self,
+        check: impl Future<Output = Result<T>>,
+    ) -> std::result::Result<T, &'static str> {
+        match tokio::time::timeout(self.check_timeout, check).await {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(e)) => {
+                tracing::warn!("Readiness check failed: {}", e);
+                Err(e.code().as_str())
+            }
+            Err(_) => Err(ERROR_TIMEOUT),
+        }
+    }
+}
+
+fn component(
+    required: bool,
+    started: Instant,
+    result: std::result::Result<(), &'static str>,
+) -> ComponentHealth {
+    ComponentHealth {
+        status: if result.is_ok() {
+            STATUS_UP
+        } else {
+            STATUS_DOWN
+        },
+        required,
+        checked_at: chrono::Utc::now().timestamp(),
+        latency_ms: started.elapsed().as_millis() as u64,
+        error: result.err(),
+        pool: None,
+        jwks_keys: None,
+    }
+}
+
+/// The last result of a check. Probes arriving while it runs wait for it
+/// rather than starting their own.
+struct CachedCheck {
+    ttl: Duration,
+    last: Mutex<Option<(Instant, ComponentHealth)>>,
+}
+
+impl CachedCheck {
+    fn new(ttl: Duration) -> Self {
+        CachedCheck {
+            ttl,
+            last: Mutex::new(None),
+        }
+    }
+
+    async fn get<F, Fut>(&self, check: F) -> ComponentHealth
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = ComponentHealth>,
+    {
+        let mut last = self.last.lock().await;
+        if let Some((checked, health)) = last.as_ref() {
+            if checked.elapsed() < self.ttl {
+                return health.clone();
+            }
+        }
+
+        let health = check().await;
+        *last = Some((Instant::now(), health.clone()));
+        health
+    }
+}
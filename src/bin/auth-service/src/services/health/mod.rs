@@ -0,0 +1,3 @@
+pub mod consts;
+pub mod health_service;
+pub mod models;
@@ -0,0 +1,50 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LivenessResponse {
+    /// Always `up`; a process that can answer is alive.
+    pub status: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReadinessResponse {
+    /// `up` when every required component is.
+    pub status: &'static str,
+    pub components: ReadinessComponents,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReadinessComponents {
+    pub database: ComponentHealth,
+    pub idp: ComponentHealth,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ComponentHealth {
+    /// `up` or `down`.
+    pub status: &'static str,
+    /// Whether the component being down makes the service unready.
+    pub required: bool,
+    /// Unix timestamp of the check this result comes from; results are cached.
+    pub checked_at: i64,
+    /// Milliseconds the check took.
+    pub latency_ms: u64,
+    /// Error code when the component is down.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool: Option<PoolHealth>,
+    /// Keys in the identity provider's JWKS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwks_keys: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PoolHealth {
+    pub max_size: usize,
+    pub size: usize,
+    pub available: usize,
+    /// Requests waiting for a connection.
+    pub waiting: usize,
+}
@@ -0,0 +1,24 @@
+pub const HTTP_REQUESTS_TOTAL: &str = "http_requests_total";
+pub const HTTP_REQUEST_DURATION_SECONDS: &str = "http_request_duration_seconds";
+pub const AUTH_LOGINS_TOTAL: &str = "auth_logins_total";
+pub const AUTH_REGISTRATIONS_TOTAL: &str = "auth_registrations_total";
+pub const DB_POOL_MAX_SIZE: &str = "db_pool_max_size";
+pub const DB_POOL_SIZE: &str = "db_pool_size";
+pub const DB_POOL_AVAILABLE: &str = "db_pool_available";
+pub const DB_POOL_WAITING: &str = "db_pool_waiting";
+pub const DB_SERVICE_MAILBOX_DELAY_SECONDS: &str = "db_service_mailbox_delay_seconds";
+
+/// Routes that finish a login, with the login method they are counted under.
+pub const LOGIN_ROUTES: &[(&str, &str)] = &[
+    ("/login", "password"),
+    ("/login/mfa", "mfa"),
+    ("/passwordless/verify", "passwordless"),
+    ("/webauthn/login/verify", "webauthn"),
+    ("/callback", "authorization_code"),
+];
+pub const REGISTRATION_ROUTE: &str = "/register";
+
+/// Histogram buckets for latencies, in seconds.
+pub const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
@@ -0,0 +1,78 @@
+use crate::services::actors::messages::{Ping, Stop};
+use crate::services::db::postgres_db::DbService;
+use crate::services::db::utils::DatabasePool;
+use crate::services::health::models::PoolHealth;
+use crate::services::metrics::consts::{
+    DB_POOL_AVAILABLE, DB_POOL_MAX_SIZE, DB_POOL_SIZE, DB_POOL_WAITING,
+    DB_SERVICE_MAILBOX_DELAY_SECONDS,
+};
+use crate::services::metrics::metrics_service::MetricsService;
+use actix::{Actor, ActorContext, Addr, AsyncContext, Context, Handler, WrapFuture};
+use std::time::{Duration, Instant};
+
+/// Periodically samples gauges that nothing updates on its own: the database
+/// pool and the backlog of the `DbService` mailbox.
+pub struct MetricsCollector {
+    metrics: MetricsService,
+    pool: DatabasePool,
+    db: Addr<DbService>,
+    interval: Duration,
+}
+
+impl MetricsCollector {
+    pub fn new(
+        metrics: MetricsService,
+        pool: DatabasePool,
+        db: Addr<DbService>,
+        interval: u64,
+    ) -> Self {
+        MetricsCollector {
+            metrics,
+            pool,
+            db,
+            interval: Duration::from_secs(interval),
+        }
+    }
+
+    fn record_pool(&self) {
+        let pool = PoolHealth::from(&self.pool);
+        metrics::gauge!(DB_POOL_MAX_SIZE).set(pool.max_size as f64);
+        metrics::gauge!(DB_POOL_SIZE).set(pool.size as f64);
+        metrics::gauge!(DB_POOL_AVAILABLE).set(pool.available as f64);
+        metrics::gauge!(DB_POOL_WAITING).set(pool.waiting as f64);
+    }
+}
+
+impl Actor for MetricsCollector {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(self.interval, |collector, ctx| {
+            collector.metrics.run_upkeep();
+            collector.record_pool();
+
+            // actix doesn't expose the mailbox length, so the backlog is
+            // measured as the time a no-op message waits to be handled.
+            let db = collector.db.clone();
+            ctx.spawn(
+                async move {
+                    let sent = Instant::now();
+                    match db.send(Ping).await {
+                        Ok(()) => metrics::gauge!(DB_SERVICE_MAILBOX_DELAY_SECONDS)
+                            .set(sent.elapsed().as_secs_f64()),
+                        Err(e) => tracing::error!("Failed to reach the database actor: {}", e),
+                    }
+                }
+                .into_actor(collector),
+            );
+        });
+    }
+}
+
+impl Handler<Stop> for MetricsCollector {
+    type Result = ();
+
+    fn handle(&mut self, _: Stop, ctx: &mut Self::Context) -> Self::Result {
+        ctx.stop();
+    }
+}
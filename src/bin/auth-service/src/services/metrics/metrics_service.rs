@@ -0,0 +1,30 @@
+use crate::errors::{Error, Result};
+use crate::services::metrics::consts::LATENCY_BUCKETS;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+/// The process-wide Prometheus recorder behind `/metrics`.
+#[derive(Clone)]
+pub struct MetricsService {
+    handle: PrometheusHandle,
+}
+
+impl MetricsService {
+    /// Installs the global recorder; only the first call in a process succeeds.
+    pub fn install() -> Result<Self> {
+        let handle = PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), LATENCY_BUCKETS)
+            .and_then(PrometheusBuilder::install_recorder)
+            .map_err(|e| Error::StringError(e.to_string()))?;
+
+        Ok(MetricsService { handle })
+    }
+
+    pub fn render(&self) -> String {
+        self.handle.render()
+    }
+
+    /// Drains histogram buckets, which otherwise grow until the next scrape.
+    pub fn run_upkeep(&self) {
+        self.handle.run_upkeep();
+    }
+}
@@ -0,0 +1,3 @@
+pub mod consts;
+pub mod metrics_collector;
+pub mod metrics_service;
@@ -0,0 +1,11 @@
+pub const TOTP_DIGITS: u32 = 6;
+pub const TOTP_PERIOD: u64 = 30;
+pub const TOTP_SECRET_LEN: usize = 20;
+pub const TOTP_ALGORITHM: &str = "SHA1";
+pub const TOTP_ALLOWED_DRIFT: i64 = 1;
+pub const RECOVERY_CODE_LEN: usize = 5;
+pub const CHALLENGE_TOKEN_LEN: usize = 32;
+/// Prefixes the challenge token when deriving the key for the access token it
+/// holds back, so the key differs from the stored token hash.
+pub const CHALLENGE_KEY_CONTEXT: &str = "mfa-challenge-key:";
+pub const FACTOR_SECRET_CONTEXT: &str = "mfa-factor:";
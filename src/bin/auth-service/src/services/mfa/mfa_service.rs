@@ -0,0 +1,177 @@
+use crate::errors::{Error, Result};
+use crate::services::db::tables::MfaFactors;
+use crate::services::mfa::consts::{
+    CHALLENGE_KEY_CONTEXT, CHALLENGE_TOKEN_LEN, FACTOR_SECRET_CONTEXT, RECOVERY_CODE_LEN,
+    TOTP_ALGORITHM, TOTP_ALLOWED_DRIFT, TOTP_DIGITS, TOTP_PERIOD, TOTP_SECRET_LEN,
+};
+use crate::services::mfa::totp::{format_code, hotp, time_step};
+use crate::services::token::key_cipher::KeyCipher;
+use data_encoding::{BASE32_NOPAD, BASE64URL_NOPAD, HEXLOWER};
+use reqwest::Url;
+
+#[derive(Clone)]
+pub struct MfaService {
+    issuer: String,
+    recovery_codes: usize,
+    challenge_ttl: i64,
+    challenge_max_attempts: i32,
+    cipher: KeyCipher,
+}
+
+impl MfaService {
+    pub fn new(
+        issuer: String,
+        recovery_codes: usize,
+        challenge_ttl: i64,
+        challenge_max_attempts: i32,
+        cipher: KeyCipher,
+    ) -> Self {
+        MfaService {
+            issuer,
+            recovery_codes,
+            challenge_ttl,
+            challenge_max_attempts,
+            cipher,
+        }
+    }
+
+    pub fn challenge_ttl(&self) -> i64 {
+        self.challenge_ttl
+    }
+
+    pub fn challenge_max_attempts(&self) -> i32 {
+        self.challenge_max_attempts
+    }
+
+    /// Generates a new base32 encoded TOTP secret.
+    pub fn generate_secret(&self) -> Result<String> {
+        let mut secret = [0u8; TOTP_SECRET_LEN];
+        openssl::rand::rand_bytes(&mut secret)?;
+        Ok(BASE32_NOPAD.encode(&secret))
+    }
+
+    /// Encrypts a TOTP secret for storage. The ciphertext is bound to the
+    /// user, so it can't be copied onto another account.
+    pub fn encrypt_secret(&self, user_id: &str, secret: &str) -> Result<Vec<u8>> {
+        self.cipher.encrypt(
+            &format!("{}{}", FACTOR_SECRET_CONTEXT, user_id),
+            secret.as_bytes(),
+        )
+    }
+
+    /// Decrypts the base32 secret of a factor.
+    pub fn factor_secret(&self, factor: &MfaFactors) -> Result<String> {
+        let secret = self.cipher.decrypt(
+            &format!("{}{}", FACTOR_SECRET_CONTEXT, factor.user_id),
+            &factor.encrypted_secret,
+        )?;
+        String::from_utf8(secret).map_err(|e| Error::StringError(e.to_string()))
+    }
+
+    /// Builds the `otpauth://` URI authenticator apps read from the enrollment QR code.
+    pub fn otpauth_uri(&self, account: &str, secret: &str) -> Result<String> {
+        let label = format!("otpauth://totp/{}:{}", self.issuer, account);
+        let url = Url::parse_with_params(
+            &label,
+            &[
+                ("secret", secret),
+                ("issuer", self.issuer.as_str()),
+                ("algorithm", TOTP_ALGORITHM),
+                ("digits", &TOTP_DIGITS.to_string()),
+                ("period", &TOTP_PERIOD.to_string()),
+            ],
+        )
+        .map_err(|e| Error::StringError(e.to_string()))?;
+
+        Ok(url.to_string())
+    }
+
+    /// Checks `code` against the secret, allowing for one step of clock drift.
+    /// Returns the matched time step, or `None` if the code is wrong or the step
+    /// was already used.
+    pub fn verify_code(
+        &self,
+        secret: &str,
+        code: &str,
+        last_used_step: Option<i64>,
+    ) -> Result<Option<i64>> {
+        let secret = BASE32_NOPAD
+            .decode(secret.as_bytes())
+            .map_err(|e| Error::StringError(e.to_string()))?;
+        let code = code.trim();
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let current = time_step(now) as i64;
+
+        for drift in -TOTP_ALLOWED_DRIFT..=TOTP_ALLOWED_DRIFT {
+            let step = current + drift;
+            if last_used_step.is_some_and(|last| step <= last) {
+                continue;
+            }
+
+            let expected = format_code(hotp(&secret, step as u64)?);
+            if openssl::memcmp::eq(expected.as_bytes(), code.as_bytes()) {
+                return Ok(Some(step));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Generates a fresh set of one-time recovery codes in `xxxxx-xxxxx` form.
+    pub fn generate_recovery_codes(&self) -> Result<Vec<String>> {
+        (0..self.recovery_codes)
+            .map(|_| {
+                let mut bytes = [0u8; RECOVERY_CODE_LEN];
+                openssl::rand::rand_bytes(&mut bytes)?;
+                let code = HEXLOWER.encode(&bytes);
+                Ok(format!(
+                    "{}-{}",
+                    &code[..RECOVERY_CODE_LEN],
+                    &code[RECOVERY_CODE_LEN..]
+                ))
+            })
+            .collect()
+    }
+
+    pub fn generate_challenge_token(&self) -> Result<String> {
+        let mut bytes = [0u8; CHALLENGE_TOKEN_LEN];
+        openssl::rand::rand_bytes(&mut bytes)?;
+        Ok(BASE64URL_NOPAD.encode(&bytes))
+    }
+
+    /// Encrypts the access token a challenge holds back. The key is derived
+    /// from the challenge token, which only the client has, so the stored
+    /// row alone doesn't give the access token away.
+    pub fn encrypt_access_token(mfa_token: &str, access_token: &str) -> Result<Vec<u8>> {
+        Self::challenge_cipher(mfa_token)
+            .encrypt(&Self::hash_token(mfa_token), access_token.as_bytes())
+    }
+
+    pub fn decrypt_access_token(mfa_token: &str, encrypted: &[u8]) -> Result<String> {
+        let access_token =
+            Self::challenge_cipher(mfa_token).decrypt(&Self::hash_token(mfa_token), encrypted)?;
+        String::from_utf8(access_token).map_err(|e| Error::StringError(e.to_string()))
+    }
+
+    fn challenge_cipher(mfa_token: &str) -> KeyCipher {
+        KeyCipher::new(openssl::sha::sha256(
+            format!("{}{}", CHALLENGE_KEY_CONTEXT, mfa_token).as_bytes(),
+        ))
+    }
+
+    /// Hashes a challenge token for storage.
+    pub fn hash_token(token: &str) -> String {
+        HEXLOWER.encode(&openssl::sha::sha256(token.as_bytes()))
+    }
+
+    /// Hashes a recovery code for storage, ignoring case, spaces and dashes.
+    pub fn hash_recovery_code(code: &str) -> String {
+        let normalized = code
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .collect::<String>()
+            .to_lowercase();
+        Self::hash_token(&normalized)
+    }
+}
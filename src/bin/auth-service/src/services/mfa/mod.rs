@@ -0,0 +1,3 @@
+pub mod consts;
+pub mod mfa_service;
+pub mod totp;
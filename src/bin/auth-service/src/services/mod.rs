@@ -1,4 +1,16 @@
 pub mod actix_requests;
 pub mod actors;
+pub mod admin;
+pub mod api_keys;
+pub mod audit;
 pub mod auth0;
+pub mod cache;
 pub mod db;
+pub mod health;
+pub mod metrics;
+pub mod mfa;
+pub mod oauth;
+pub mod oidc;
+pub mod sessions;
+pub mod token;
+pub mod webauthn;
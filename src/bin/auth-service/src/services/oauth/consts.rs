@@ -0,0 +1,13 @@
+/// How long a pending `/authorize` request may take to come back to `/callback`, in seconds.
+pub const AUTHORIZATION_REQUEST_TTL: i64 = 600;
+
+/// Subject prefix Auth0 uses for users of the username/password database connection.
+pub const DATABASE_PROVIDER: &str = "auth0";
+
+/// Subject prefix Auth0 uses for users of the passwordless email connection.
+pub const PASSWORDLESS_PROVIDER: &str = "email";
+
+/// Ties a pending `/authorize` request to the browser that started it, so a
+/// callback URL can't be replayed in another browser.
+pub const STATE_COOKIE_NAME: &str = "oauth_state";
+pub const STATE_COOKIE_PATH: &str = "/callback";
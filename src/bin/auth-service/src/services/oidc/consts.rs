@@ -0,0 +1,56 @@
+pub const GRANT_TYPE_AUTHORIZATION_CODE: &str = "authorization_code";
+pub const GRANT_TYPE_PASSWORD: &str = "password";
+pub const GRANT_TYPE_REFRESH_TOKEN: &str = "refresh_token";
+pub const GRANT_TYPE_CLIENT_CREDENTIALS: &str = "client_credentials";
+pub const SUPPORTED_GRANT_TYPES: [&str; 4] = [
+    GRANT_TYPE_AUTHORIZATION_CODE,
+    GRANT_TYPE_PASSWORD,
+    GRANT_TYPE_REFRESH_TOKEN,
+    GRANT_TYPE_CLIENT_CREDENTIALS,
+];
+
+pub const SCOPE_OPENID: &str = "openid";
+pub const SCOPE_EMAIL: &str = "email";
+/// Lets a token read the user's own account under `/user`.
+pub const SCOPE_USER_READ: &str = "user:read";
+/// Lets a token change the user's own account under `/user`.
+pub const SCOPE_USER_WRITE: &str = "user:write";
+/// Lets a credential call `/admin`. OAuth clients can't request it, so only
+/// logins and API keys created with it carry it.
+pub const SCOPE_ADMIN: &str = "admin";
+pub const SUPPORTED_SCOPES: [&str; 6] = [
+    SCOPE_OPENID,
+    "profile",
+    SCOPE_EMAIL,
+    "offline_access",
+    SCOPE_USER_READ,
+    SCOPE_USER_WRITE,
+];
+
+pub const RESPONSE_TYPE_CODE: &str = "code";
+pub const CODE_CHALLENGE_METHOD_S256: &str = "S256";
+pub const SUBJECT_TYPE_PUBLIC: &str = "public";
+/// Starts the subject of tokens and API keys owned by a client, so that no
+/// client id can be chosen to collide with a user id.
+pub const CLIENT_SUBJECT_PREFIX: &str = "client:";
+pub const TOKEN_ENDPOINT_AUTH_METHODS: [&str; 3] =
+    ["client_secret_basic", "client_secret_post", "none"];
+
+pub const AUTHORIZE_PATH: &str = "/authorize";
+pub const TOKEN_PATH: &str = "/oauth/token";
+pub const INTROSPECT_PATH: &str = "/oauth/introspect";
+pub const REVOKE_PATH: &str = "/oauth/revoke";
+pub const JWKS_PATH: &str = "/.well-known/jwks.json";
+
+pub const TOKEN_TYPE_HINT_ACCESS_TOKEN: &str = "access_token";
+pub const TOKEN_TYPE_HINT_REFRESH_TOKEN: &str = "refresh_token";
+
+// Error codes from RFC 6749 §4.1.2.1 and §5.2, and OpenID Connect Core §3.1.2.6.
+pub const ERROR_INVALID_REQUEST: &str = "invalid_request";
+pub const ERROR_INVALID_CLIENT: &str = "invalid_client";
+pub const ERROR_INVALID_GRANT: &str = "invalid_grant";
+pub const ERROR_INVALID_SCOPE: &str = "invalid_scope";
+pub const ERROR_UNAUTHORIZED_CLIENT: &str = "unauthorized_client";
+pub const ERROR_UNSUPPORTED_GRANT_TYPE: &str = "unsupported_grant_type";
+pub const ERROR_ACCESS_DENIED: &str = "access_denied";
+pub const ERROR_INTERACTION_REQUIRED: &str = "interaction_required";
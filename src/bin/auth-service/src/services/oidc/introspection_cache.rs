@@ -0,0 +1,46 @@
+use crate::services::cache::ttl_cache::TtlCache;
+use crate::services::oidc::models::IntrospectionResponse;
+use std::time::Duration;
+
+/// Short-lived cache of active introspection results, keyed by token hash, so
+/// resource servers polling the same token don't hit the database every time.
+#[derive(Clone)]
+pub struct IntrospectionCache {
+    entries: TtlCache<IntrospectionResponse>,
+}
+
+impl IntrospectionCache {
+    pub fn new(ttl: u64) -> Self {
+        IntrospectionCache {
+            entries: TtlCache::new(ttl),
+        }
+    }
+
+    pub fn get(&self, token_hash: &str) -> Option<IntrospectionResponse> {
+        self.entries.get(token_hash)
+    }
+
+    /// Caches an active response, never beyond the token's own expiry.
+    pub fn insert(&self, token_hash: String, response: IntrospectionResponse) {
+        if !response.active {
+            return;
+        }
+
+        let ttl = match response.exp {
+            Some(exp) => {
+                let remaining = exp - chrono::Utc::now().timestamp();
+                if remaining <= 0 {
+                    return;
+                }
+                Duration::from_secs(remaining as u64)
+            }
+            None => Duration::MAX,
+        };
+        self.entries.insert_for(token_hash, response, ttl);
+    }
+
+    /// Drops a cached result, e.g. after the token was revoked.
+    pub fn remove(&self, token_hash: &str) {
+        self.entries.remove(token_hash);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod consts;
+pub mod introspection_cache;
+pub mod models;
+pub mod oidc_service;
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+
+/// Form body of `POST /oauth/token` (RFC 6749 §4).
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+    /// `authorization_code` grant.
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    /// `password` grant, the username is the user's email.
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// `refresh_token` grant.
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// OpenID Provider metadata (OpenID Connect Discovery §3).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DiscoveryDocument {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub introspection_endpoint: String,
+    pub revocation_endpoint: String,
+    pub jwks_uri: String,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    pub scopes_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+}
+
+/// Form body of `POST /oauth/introspect` (RFC 7662 §2.1).
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    /// `access_token` or `refresh_token`.
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Introspection response (RFC 7662 §2.2). Inactive tokens only carry `active`.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    /// Any other claims of the token, e.g. `aud` or namespaced Auth0 claims.
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub claims: Map<String, Value>,
+}
+
+/// Form body of `POST /oauth/revoke` (RFC 7009 §2.1).
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct RevocationRequest {
+    pub token: String,
+    /// `access_token` or `refresh_token`.
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
@@ -0,0 +1,328 @@
+use crate::errors::{Error, Result};
+use crate::opts::cmd_opts::OidcOpts;
+use crate::services::actors::messages::GetOauthClient;
+use crate::services::actors::traced::TracedSend;
+use crate::services::db::postgres_db::DbService;
+use crate::services::db::tables::OauthClients;
+use crate::services::oauth::pkce::random_token;
+use crate::services::oidc::consts::{
+    AUTHORIZE_PATH, CLIENT_SUBJECT_PREFIX, CODE_CHALLENGE_METHOD_S256, ERROR_INVALID_CLIENT,
+    ERROR_INVALID_SCOPE, ERROR_UNAUTHORIZED_CLIENT, ERROR_UNSUPPORTED_GRANT_TYPE,
+    GRANT_TYPE_AUTHORIZATION_CODE, GRANT_TYPE_CLIENT_CREDENTIALS, INTROSPECT_PATH, JWKS_PATH,
+    RESPONSE_TYPE_CODE, REVOKE_PATH, SUBJECT_TYPE_PUBLIC, SUPPORTED_GRANT_TYPES, SUPPORTED_SCOPES,
+    TOKEN_ENDPOINT_AUTH_METHODS, TOKEN_PATH,
+};
+use crate::services::oidc::introspection_cache::IntrospectionCache;
+use crate::services::oidc::models::DiscoveryDocument;
+use actix::Addr;
+use data_encoding::HEXLOWER;
+use reqwest::Url;
+
+/// Client registry and protocol rules for the service acting as an OpenID
+/// Connect provider to our own applications.
+#[derive(Clone)]
+pub struct OidcService {
+    issuer: String,
+    authorization_code_ttl: i64,
+    refresh_token_ttl: i64,
+    db: Addr<DbService>,
+    introspection_cache: IntrospectionCache,
+}
+
+impl OidcService {
+    pub fn new(issuer: String, opts: OidcOpts, db: Addr<DbService>) -> Self {
+        OidcService {
+            issuer,
+            authorization_code_ttl: opts.authorization_code_ttl,
+            refresh_token_ttl: opts.refresh_token_ttl,
+            db,
+            introspection_cache: IntrospectionCache::new(opts.introspection_cache_ttl),
+        }
+    }
+
+    pub fn introspection_cache(&self) -> &IntrospectionCache {
+        &self.introspection_cache
+    }
+
+    pub fn authorization_code_ttl(&self) -> i64 {
+        self.authorization_code_ttl
+    }
+
+    pub fn refresh_token_ttl(&self, client: &OauthClients) -> i64 {
+        client.refresh_token_ttl.unwrap_or(self.refresh_token_ttl)
+    }
+
+    pub fn access_token_ttl(client: &OauthClients, default: i64) -> i64 {
+        client.access_token_ttl.unwrap_or(default)
+    }
+
+    pub fn discovery_document(&self, signing_algorithms: Vec<String>) -> DiscoveryDocument {
+        let to_strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+
+        DiscoveryDocument {
+            issuer: self.issuer.clone(),
+            authorization_endpoint: format!("{}{}", self.issuer, AUTHORIZE_PATH),
+            token_endpoint: format!("{}{}", self.issuer, TOKEN_PATH),
+            introspection_endpoint: format!("{}{}", self.issuer, INTROSPECT_PATH),
+            revocation_endpoint: format!("{}{}", self.issuer, REVOKE_PATH),
+            jwks_uri: format!("{}{}", self.issuer, JWKS_PATH),
+            response_types_supported: to_strings(&[RESPONSE_TYPE_CODE]),
+            grant_types_supported: to_strings(&SUPPORTED_GRANT_TYPES),
+            subject_types_supported: to_strings(&[SUBJECT_TYPE_PUBLIC]),
+            id_token_signing_alg_values_supported: signing_algorithms,
+            scopes_supported: to_strings(&SUPPORTED_SCOPES),
+            token_endpoint_auth_methods_supported: to_strings(&TOKEN_ENDPOINT_AUTH_METHODS),
+            code_challenge_methods_supported: to_strings(&[CODE_CHALLENGE_METHOD_S256]),
+        }
+    }
+
+    pub async fn client(&self, client_id: &str) -> Result<Option<OauthClients>> {
+        self.db
+            .send_traced(GetOauthClient {
+                client_id: client_id.to_string(),
+            })
+            .await?
+    }
+
+    /// Authenticates the client of a token request. Confidential clients must
+    /// present their secret; public clients only identify themselves.
+    pub async fn authenticate_client(
+        &self,
+        client_id: Option<&str>,
+        client_secret: Option<&str>,
+    ) -> Result<OauthClients> {
+        let client = match client_id {
+            Some(client_id) => self.client(client_id).await?,
+            None => None,
+        }
+        .ok_or_else(|| oauth_error(ERROR_INVALID_CLIENT, "Unknown client"))?;
+
+        match (&client.client_secret_hash, client_secret) {
+            (None, _) => Ok(client),
+            (Some(expected), Some(given)) if secret_matches(expected, given) => Ok(client),
+            _ => Err(oauth_error(
+                ERROR_INVALID_CLIENT,
+                "Client authentication failed",
+            )),
+        }
+    }
+
+    /// Authenticates a client that calls the endpoints meant for resource
+    /// servers, which public clients can't use.
+    pub async fn authenticate_confidential_client(
+        &self,
+        client_id: Option<&str>,
+        client_secret: Option<&str>,
+    ) -> Result<OauthClients> {
+        let client = self.authenticate_client(client_id, client_secret).await?;
+        if client.client_secret_hash.is_none() {
+            return Err(oauth_error(
+                ERROR_UNAUTHORIZED_CLIENT,
+                "Public clients may not use this endpoint",
+            ));
+        }
+        Ok(client)
+    }
+
+    pub fn check_grant_type(&self, client: &OauthClients, grant_type: &str) -> Result<()> {
+        if !SUPPORTED_GRANT_TYPES.contains(&grant_type) {
+            return Err(oauth_error(
+                ERROR_UNSUPPORTED_GRANT_TYPE,
+                &format!("Unsupported grant type {}", grant_type),
+            ));
+        }
+        if !client
+            .grant_types
+            .iter()
+            .any(|allowed| allowed == grant_type)
+            || (grant_type == GRANT_TYPE_CLIENT_CREDENTIALS && client.client_secret_hash.is_none())
+        {
+            return Err(oauth_error(
+                ERROR_UNAUTHORIZED_CLIENT,
+                &format!("Client may not use the {} grant", grant_type),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the policy of a client being registered or updated.
+    pub fn check_client_policy(
+        &self,
+        is_confidential: bool,
+        grant_types: &[String],
+        redirect_uris: &[String],
+        allowed_scopes: &[String],
+    ) -> Result<()> {
+        if grant_types.is_empty() {
+            return Err(Error::InvalidInput(
+                "At least one grant type is required".to_string(),
+            ));
+        }
+        if let Some(unknown) = grant_types
+            .iter()
+            .find(|grant| !SUPPORTED_GRANT_TYPES.contains(&grant.as_str()))
+        {
+            return Err(Error::InvalidInput(format!(
+                "Unsupported grant type {}",
+                unknown
+            )));
+        }
+        if let Some(unknown) = allowed_scopes
+            .iter()
+            .find(|scope| !SUPPORTED_SCOPES.contains(&scope.as_str()))
+        {
+            return Err(Error::InvalidInput(format!("Unknown scope {}", unknown)));
+        }
+        if grant_types
+            .iter()
+            .any(|grant| grant == GRANT_TYPE_AUTHORIZATION_CODE)
+            && redirect_uris.is_empty()
+        {
+            return Err(Error::InvalidInput(
+                "The authorization_code grant requires a redirect URI".to_string(),
+            ));
+        }
+        if let Some(invalid) = redirect_uris.iter().find(|uri| Url::parse(uri).is_err()) {
+            return Err(Error::InvalidInput(format!(
+                "Invalid redirect URI {}",
+                invalid
+            )));
+        }
+        if !is_confidential
+            && grant_types
+                .iter()
+                .any(|grant| grant == GRANT_TYPE_CLIENT_CREDENTIALS)
+        {
+            return Err(Error::InvalidInput(
+                "Public clients may not use the client_credentials grant".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns a new client secret and the hash stored in `oauth_clients`. The
+    /// secret itself is only shown once.
+    pub fn generate_client_secret() -> Result<(String, String)> {
+        let secret = random_token()?;
+        let hash = Self::hash_token(&secret);
+        Ok((secret, hash))
+    }
+
+    /// Validates the client parameters of an `/authorize` request.
+    pub async fn check_authorization_request(
+        &self,
+        client_id: &str,
+        redirect_uri: &str,
+        response_type: Option<&str>,
+        code_challenge: Option<&str>,
+        code_challenge_method: Option<&str>,
+    ) -> Result<OauthClients> {
+        let client = self
+            .client(client_id)
+            .await?
+            .ok_or(Error::InvalidInput("Unknown client".to_string()))?;
+
+        if !client.redirect_uris.iter().any(|uri| uri == redirect_uri) {
+            return Err(Error::InvalidInput(
+                "redirect_uri is not registered for the client".to_string(),
+            ));
+        }
+        if response_type != Some(RESPONSE_TYPE_CODE) {
+            return Err(Error::InvalidInput(
+                "Only the code response type is supported".to_string(),
+            ));
+        }
+        if !client
+            .grant_types
+            .iter()
+            .any(|grant| grant == GRANT_TYPE_AUTHORIZATION_CODE)
+        {
+            return Err(Error::InvalidInput(
+                "Client may not use the authorization_code grant".to_string(),
+            ));
+        }
+        if code_challenge.is_none() && client.client_secret_hash.is_none() {
+            return Err(Error::InvalidInput(
+                "Public clients must use PKCE".to_string(),
+            ));
+        }
+        if code_challenge.is_some()
+            && code_challenge_method.unwrap_or(CODE_CHALLENGE_METHOD_S256)
+                != CODE_CHALLENGE_METHOD_S256
+        {
+            return Err(Error::InvalidInput(
+                "Only the S256 code challenge method is supported".to_string(),
+            ));
+        }
+        Ok(client)
+    }
+
+    /// Checks a requested scope against the supported scopes and the scopes the
+    /// client may use, and normalizes its spacing, falling back to `default`
+    /// when no scope was requested.
+    pub fn normalize_scope(
+        &self,
+        client: &OauthClients,
+        scope: Option<&str>,
+        default: &str,
+    ) -> Result<String> {
+        let scope = scope.unwrap_or(default);
+        let scopes: Vec<&str> = scope.split_whitespace().collect();
+
+        if let Some(unknown) = scopes.iter().find(|s| !SUPPORTED_SCOPES.contains(s)) {
+            return Err(oauth_error(
+                ERROR_INVALID_SCOPE,
+                &format!("Unknown scope {}", unknown),
+            ));
+        }
+        if !client.allowed_scopes.is_empty() {
+            if let Some(denied) = scopes
+                .iter()
+                .find(|s| !client.allowed_scopes.iter().any(|allowed| allowed == *s))
+            {
+                return Err(oauth_error(
+                    ERROR_INVALID_SCOPE,
+                    &format!("Client may not request scope {}", denied),
+                ));
+            }
+        }
+        Ok(scopes.join(" "))
+    }
+
+    /// Subject of the tokens and API keys a client holds for itself.
+    pub fn client_subject(client_id: &str) -> String {
+        format!("{}{}", CLIENT_SUBJECT_PREFIX, client_id)
+    }
+
+    pub fn hash_token(token: &str) -> String {
+        HEXLOWER.encode(&openssl::sha::sha256(token.as_bytes()))
+    }
+
+    /// Key of an access token in the denylist: its `jti`, or a hash of the
+    /// token for issuers that don't set one.
+    pub fn revocation_id(jti: Option<&str>, token: &str) -> String {
+        match jti {
+            Some(jti) => jti.to_string(),
+            None => Self::hash_token(token),
+        }
+    }
+}
+
+/// Whether a space separated scope list contains `scope`.
+pub fn has_scope(scopes: &str, scope: &str) -> bool {
+    scopes.split_whitespace().any(|s| s == scope)
+}
+
+pub fn oauth_error(error: &'static str, description: &str) -> Error {
+    Error::OAuth {
+        error,
+        description: description.to_string(),
+    }
+}
+
+// Both sides are hex digests of the same length, so the comparison is constant time.
+fn secret_matches(expected_hash: &str, given: &str) -> bool {
+    let given_hash = OidcService::hash_token(given);
+    expected_hash.len() == given_hash.len()
+        && openssl::memcmp::eq(expected_hash.as_bytes(), given_hash.as_bytes())
+}
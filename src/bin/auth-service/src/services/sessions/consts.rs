@@ -0,0 +1,11 @@
+pub const SESSION_COOKIE_PATH: &str = "/";
+/// `last_seen_at` is only written when older than this many seconds.
+pub const LAST_SEEN_RESOLUTION: i64 = 60;
+pub const SAME_SITE_STRICT: &str = "strict";
+pub const SAME_SITE_LAX: &str = "lax";
+pub const SAME_SITE_NONE: &str = "none";
+/// Readable by scripts, so browser apps can echo it in `CSRF_HEADER`.
+pub const CSRF_COOKIE_NAME: &str = "csrf_token";
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+/// Separates CSRF tokens from the session token hashes stored in the database.
+pub const CSRF_TOKEN_CONTEXT: &str = "csrf:";
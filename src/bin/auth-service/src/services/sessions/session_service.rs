@@ -0,0 +1,264 @@
+use crate::errors::{Error, Result};
+use crate::middleware::authenticated_user::AuthenticatedUser;
+use crate::middleware::trusted_proxies::TrustedProxies;
+use crate::opts::cmd_opts::SessionOpts;
+use crate::services::actors::messages::{
+    CreateSession, DeleteSession, DeleteUserSessions, GetSession, GetUserSessions, TouchSession,
+};
+use crate::services::actors::traced::TracedSend;
+use crate::services::cache::ttl_cache::TtlCache;
+use crate::services::db::postgres_db::DbService;
+use crate::services::db::tables::Sessions;
+use crate::services::oauth::pkce::random_token;
+use crate::services::oidc::oidc_service::OidcService;
+use crate::services::sessions::consts::{
+    CSRF_COOKIE_NAME, CSRF_TOKEN_CONTEXT, LAST_SEEN_RESOLUTION, SAME_SITE_LAX, SAME_SITE_NONE,
+    SAME_SITE_STRICT, SESSION_COOKIE_PATH,
+};
+use actix::Addr;
+use actix_web::cookie::{time, Cookie, SameSite};
+use actix_web::http::header::USER_AGENT;
+use actix_web::HttpRequest;
+use uuid::Uuid;
+
+/// Server-side sessions for browser clients, identified by an opaque token in
+/// an HttpOnly cookie.
+#[derive(Clone)]
+pub struct SessionService {
+    db: Addr<DbService>,
+    enabled: bool,
+    cookie_name: String,
+    secure: bool,
+    same_site: SameSite,
+    idle_timeout: i64,
+    absolute_timeout: i64,
+    geo_header: Option<String>,
+    /// Sessions recently read from the database, keyed by token hash, so every
+    /// cookie-authenticated request doesn't need a query. Sessions deleted by
+    /// another instance stay usable here for at most the ttl.
+    cache: TtlCache<Sessions>,
+}
+
+/// A session that was just started, with the cookies that carry it.
+pub struct NewSession {
+    pub id: Uuid,
+    pub cookies: Vec<Cookie<'static>>,
+}
+
+impl SessionService {
+    pub fn new(db: Addr<DbService>, opts: SessionOpts) -> Result<Self> {
+        let same_site = match opts.same_site.to_lowercase().as_str() {
+            SAME_SITE_STRICT => SameSite::Strict,
+            SAME_SITE_LAX => SameSite::Lax,
+            SAME_SITE_NONE => SameSite::None,
+            other => {
+                return Err(Error::InvalidInput(format!(
+                    "Unknown session.same_site {}",
+                    other
+                )))
+            }
+        };
+        if same_site == SameSite::None && !opts.secure {
+            return Err(Error::InvalidInput(
+                "session.same_site none requires session.secure".to_string(),
+            ));
+        }
+
+        Ok(SessionService {
+            db,
+            enabled: opts.enabled,
+            cookie_name: opts.cookie_name,
+            secure: opts.secure,
+            same_site,
+            idle_timeout: opts.idle_timeout,
+            absolute_timeout: opts.absolute_timeout,
+            geo_header: opts.geo_header,
+            cache: TtlCache::new(opts.cache_ttl),
+        })
+    }
+
+    pub fn cookie_name(&self) -> &str {
+        &self.cookie_name
+    }
+
+    /// Whether cookies are only sent over HTTPS, for the other cookies the
+    /// service sets.
+    pub fn secure_cookies(&self) -> bool {
+        self.secure
+    }
+
+    /// Starts a session for a user who just logged in, or returns `None` when
+    /// sessions are disabled.
+    pub async fn start(&self, req: &HttpRequest, user_id: &str) -> Result<Option<NewSession>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
+        let token = random_token()?;
+        let user_agent = req
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|user_agent| user_agent.to_str().ok())
+            .map(|user_agent| user_agent.chars().take(512).collect());
+        let geo_hint = self
+            .geo_header
+            .as_ref()
+            .and_then(|header| req.headers().get(header.as_str()))
+            .and_then(|geo_hint| geo_hint.to_str().ok())
+            .map(|geo_hint| geo_hint.chars().take(64).collect());
+
+        let id = Uuid::new_v4();
+        self.db
+            .send_traced(CreateSession {
+                id,
+                token_hash: OidcService::hash_token(&token),
+                user_id: user_id.to_string(),
+                user_agent,
+                ip_address: TrustedProxies::client_ip(req),
+                geo_hint,
+                expires_at: chrono::Utc::now() + chrono::Duration::seconds(self.absolute_timeout),
+            })
+            .await??;
+
+        let max_age = time::Duration::seconds(self.absolute_timeout);
+        let mut session_cookie = self.cookie(self.cookie_name.clone(), token.clone(), true);
+        session_cookie.set_max_age(max_age);
+        let mut csrf_cookie = self.cookie(
+            CSRF_COOKIE_NAME.to_string(),
+            Self::csrf_token(&token),
+            false,
+        );
+        csrf_cookie.set_max_age(max_age);
+
+        Ok(Some(NewSession {
+            id,
+            cookies: vec![session_cookie, csrf_cookie],
+        }))
+    }
+
+    /// The CSRF token of a session. It is derived from the session token, so it
+    /// needs no storage and can't be computed by anyone who can't read the
+    /// HttpOnly session cookie.
+    pub fn csrf_token(session_token: &str) -> String {
+        OidcService::hash_token(&format!("{}{}", CSRF_TOKEN_CONTEXT, session_token))
+    }
+
+    pub fn verify_csrf(session_token: &str, csrf_token: &str) -> bool {
+        let expected = Self::csrf_token(session_token);
+        expected.len() == csrf_token.len()
+            && openssl::memcmp::eq(expected.as_bytes(), csrf_token.as_bytes())
+    }
+
+    /// Resolves the user of a session cookie, ending sessions that timed out.
+    pub async fn authenticate(&self, token: &str) -> Result<AuthenticatedUser> {
+        let token_hash = OidcService::hash_token(token);
+        let mut session = match self.cache.get(&token_hash) {
+            Some(session) => session,
+            None => self
+                .db
+                .send_traced(GetSession {
+                    token_hash: token_hash.clone(),
+                })
+                .await??
+                .ok_or(Error::Unauthorized)?,
+        };
+
+        let now = chrono::Utc::now();
+        if session.expires_at <= now
+            || now - session.last_seen_at >= chrono::Duration::seconds(self.idle_timeout)
+        {
+            self.cache.remove(&token_hash);
+            self.db.send_traced(DeleteSession { token_hash }).await??;
+            return Err(Error::Unauthorized);
+        }
+
+        if now - session.last_seen_at >= chrono::Duration::seconds(LAST_SEEN_RESOLUTION) {
+            self.db
+                .send_traced(TouchSession {
+                    id: session.id,
+                    last_seen_at: now,
+                })
+                .await??;
+            session.last_seen_at = now;
+        }
+
+        let user = AuthenticatedUser {
+            user_id: session.user_id.clone(),
+            client_id: None,
+            scope: None,
+            api_key_id: None,
+            session_id: Some(session.id),
+        };
+        self.cache.insert(session.token_hash.clone(), session);
+        Ok(user)
+    }
+
+    pub async fn end(&self, token: &str) -> Result<()> {
+        let token_hash = OidcService::hash_token(token);
+        self.cache.remove(&token_hash);
+        self.db.send_traced(DeleteSession { token_hash }).await??;
+        Ok(())
+    }
+
+    pub async fn list(&self, user_id: &str) -> Result<Vec<Sessions>> {
+        self.db
+            .send_traced(GetUserSessions {
+                user_id: user_id.to_string(),
+            })
+            .await?
+    }
+
+    /// Signs a user out of one session. Returns false for an unknown session.
+    pub async fn revoke(&self, user_id: &str, id: Uuid) -> Result<bool> {
+        let revoked = self
+            .delete(DeleteUserSessions {
+                user_id: user_id.to_string(),
+                id: Some(id),
+                keep: None,
+            })
+            .await?;
+        Ok(revoked > 0)
+    }
+
+    /// Signs a user out of every session except `keep`. Returns how many
+    /// sessions ended.
+    pub async fn revoke_all(&self, user_id: &str, keep: Option<Uuid>) -> Result<usize> {
+        self.delete(DeleteUserSessions {
+            user_id: user_id.to_string(),
+            id: None,
+            keep,
+        })
+        .await
+    }
+
+    async fn delete(&self, msg: DeleteUserSessions) -> Result<usize> {
+        let token_hashes = self.db.send_traced(msg).await??;
+        for token_hash in &token_hashes {
+            self.cache.remove(token_hash);
+        }
+        Ok(token_hashes.len())
+    }
+
+    /// Cookies that make the browser drop the session and CSRF cookies.
+    pub fn removal_cookies(&self) -> Vec<Cookie<'static>> {
+        [
+            self.cookie(self.cookie_name.clone(), String::new(), true),
+            self.cookie(CSRF_COOKIE_NAME.to_string(), String::new(), false),
+        ]
+        .into_iter()
+        .map(|mut cookie| {
+            cookie.make_removal();
+            cookie
+        })
+        .collect()
+    }
+
+    fn cookie(&self, name: String, value: String, http_only: bool) -> Cookie<'static> {
+        Cookie::build(name, value)
+            .path(SESSION_COOKIE_PATH)
+            .http_only(http_only)
+            .secure(self.secure)
+            .same_site(self.same_site)
+            .finish()
+    }
+}
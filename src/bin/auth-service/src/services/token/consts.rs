@@ -0,0 +1,30 @@
+pub const KEY_TYPE_RSA: &str = "RSA";
+pub const KEY_TYPE_EC: &str = "EC";
+pub const KEY_TYPE_OKP: &str = "OKP";
+pub const KEY_USE_SIGNATURE: &str = "sig";
+pub const CURVE_P256: &str = "P-256";
+pub const CURVE_ED25519: &str = "Ed25519";
+pub const TOKEN_TYPE_BEARER: &str = "Bearer";
+
+pub const ALG_RS256: &str = "RS256";
+pub const ALG_ES256: &str = "ES256";
+pub const ALG_EDDSA: &str = "EdDSA";
+
+// Lifecycle of a row in `signing_keys`.
+pub const KEY_STATE_PENDING: &str = "pending";
+pub const KEY_STATE_ACTIVE: &str = "active";
+pub const KEY_STATE_RETIRING: &str = "retiring";
+pub const KEY_STATE_REVOKED: &str = "revoked";
+
+/// Advisory lock held while the signing keys change state.
+pub const SIGNING_KEYS_LOCK_ID: i64 = 0x7369_676e_6b65_7973;
+/// Notified by a trigger whenever `signing_keys` changes.
+pub const SIGNING_KEYS_CHANNEL: &str = "signing_keys";
+/// Wait before listening for key changes again after the connection dropped.
+pub const SIGNING_KEYS_LISTEN_RETRY: u64 = 5;
+
+pub const RSA_KEY_BITS: u32 = 2048;
+pub const KEY_ENCRYPTION_KEY_LEN: usize = 32;
+pub const KEY_ENCRYPTION_NONCE_LEN: usize = 12;
+pub const KEY_ENCRYPTION_TAG_LEN: usize = 16;
+pub const KID_LEN: usize = 8;
@@ -0,0 +1,163 @@
+use crate::errors::{Error, Result};
+use crate::services::token::consts::{
+    KEY_ENCRYPTION_KEY_LEN, KEY_ENCRYPTION_NONCE_LEN, KEY_ENCRYPTION_TAG_LEN,
+};
+use data_encoding::BASE64;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+
+/// Encrypts secrets at rest with AES-256-GCM. The caller passes what the
+/// value belongs to, e.g. the kid of a signing key, as associated data, so a
+/// ciphertext can't be moved to another row.
+#[derive(Clone)]
+pub struct KeyCipher {
+    key: [u8; KEY_ENCRYPTION_KEY_LEN],
+}
+
+impl KeyCipher {
+    pub fn new(key: [u8; KEY_ENCRYPTION_KEY_LEN]) -> Self {
+        KeyCipher { key }
+    }
+
+    /// Builds the cipher from a base64 encoded 32 byte key.
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let decoded = BASE64
+            .decode(encoded.trim().as_bytes())
+            .map_err(|_| Error::InvalidInput("Key encryption key is not base64".to_string()))?;
+        let key = decoded.try_into().map_err(|_| {
+            Error::InvalidInput(format!(
+                "Key encryption key must be {} bytes",
+                KEY_ENCRYPTION_KEY_LEN
+            ))
+        })?;
+
+        Ok(KeyCipher { key })
+    }
+
+    /// Returns `nonce || tag || ciphertext`.
+    pub fn encrypt(&self, associated_data: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; KEY_ENCRYPTION_NONCE_LEN];
+        openssl::rand::rand_bytes(&mut nonce)?;
+        let mut tag = [0u8; KEY_ENCRYPTION_TAG_LEN];
+
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.key,
+            Some(&nonce),
+            associated_data.as_bytes(),
+            plaintext,
+            &mut tag,
+        )?;
+
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend_from_slice(&tag);
+        encrypted.extend_from_slice(&ciphertext);
+        Ok(encrypted)
+    }
+
+    pub fn decrypt(&self, associated_data: &str, encrypted: &[u8]) -> Result<Vec<u8>> {
+        if encrypted.len() < KEY_ENCRYPTION_NONCE_LEN + KEY_ENCRYPTION_TAG_LEN {
+            return Err(Error::StringError(format!(
+                "Encrypted value for {} is truncated",
+                associated_data
+            )));
+        }
+        let (nonce, rest) = encrypted.split_at(KEY_ENCRYPTION_NONCE_LEN);
+        let (tag, ciphertext) = rest.split_at(KEY_ENCRYPTION_TAG_LEN);
+
+        Ok(decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.key,
+            Some(nonce),
+            associated_data.as_bytes(),
+            ciphertext,
+            tag,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> KeyCipher {
+        KeyCipher::new([7u8; KEY_ENCRYPTION_KEY_LEN])
+    }
+
+    #[test]
+    fn round_trips() -> Result<()> {
+        let cipher = cipher();
+
+        for plaintext in [&b""[..], b"secret", &[0u8; 4096]] {
+            let encrypted = cipher.encrypt("kid-1", plaintext)?;
+            assert_eq!(cipher.decrypt("kid-1", &encrypted)?, plaintext);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn nonces_differ() -> Result<()> {
+        let cipher = cipher();
+        assert_ne!(
+            cipher.encrypt("kid-1", b"secret")?,
+            cipher.encrypt("kid-1", b"secret")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_tampering() -> Result<()> {
+        let cipher = cipher();
+        let encrypted = cipher.encrypt("kid-1", b"secret")?;
+        let flipped = |index: usize| {
+            let mut encrypted = encrypted.clone();
+            encrypted[index] ^= 1;
+            encrypted
+        };
+
+        let cases = [
+            ("nonce", "kid-1", flipped(0)),
+            ("tag", "kid-1", flipped(KEY_ENCRYPTION_NONCE_LEN)),
+            ("ciphertext", "kid-1", flipped(encrypted.len() - 1)),
+            ("associated data", "kid-2", encrypted.clone()),
+            (
+                "truncated",
+                "kid-1",
+                encrypted[..KEY_ENCRYPTION_NONCE_LEN].to_vec(),
+            ),
+        ];
+
+        for (name, associated_data, encrypted) in cases {
+            assert!(
+                cipher.decrypt(associated_data, &encrypted).is_err(),
+                "{}",
+                name
+            );
+        }
+
+        let other = KeyCipher::new([8u8; KEY_ENCRYPTION_KEY_LEN]);
+        assert!(other.decrypt("kid-1", &encrypted).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_base64_keys() {
+        let cases = [
+            (BASE64.encode(&[1u8; KEY_ENCRYPTION_KEY_LEN]), true),
+            (
+                format!(" {}\n", BASE64.encode(&[1u8; KEY_ENCRYPTION_KEY_LEN])),
+                true,
+            ),
+            (BASE64.encode(&[1u8; 16]), false),
+            ("not base64!".to_string(), false),
+        ];
+
+        for (encoded, valid) in cases {
+            assert_eq!(
+                KeyCipher::from_base64(&encoded).is_ok(),
+                valid,
+                "{:?}",
+                encoded
+            );
+        }
+    }
+}
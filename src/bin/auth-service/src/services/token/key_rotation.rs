@@ -0,0 +1,315 @@
+use crate::errors::{Error, Result};
+use crate::opts::cmd_opts::TokenOpts;
+use crate::services::actors::messages::{
+    GetSigningKeys, NewSigningKey, ReloadSigningKeys, RevokeSigningKey, RotateSigningKeys, Stop,
+};
+use crate::services::actors::traced::TracedSend;
+use crate::services::db::postgres_db::DbService;
+use crate::services::db::tables::SigningKeys;
+use crate::services::token::consts::{
+    KEY_STATE_ACTIVE, KEY_STATE_PENDING, KEY_STATE_RETIRING, SIGNING_KEYS_CHANNEL,
+    SIGNING_KEYS_LISTEN_RETRY,
+};
+use crate::services::token::key_cipher::KeyCipher;
+use crate::services::token::key_store::{KeyStore, SigningKey};
+use crate::services::token::token_issuer::TokenIssuer;
+use actix::{Actor, ActorContext, Addr, AsyncContext, Context, Handler, WrapFuture};
+use futures_util::StreamExt;
+use std::time::Duration;
+use tokio_postgres::{AsyncMessage, NoTls};
+
+/// Lifecycle operations on the `signing_keys` table, shared by the rotation
+/// actor and the admin CLI.
+#[derive(Clone)]
+pub struct KeyManager {
+    db: Addr<DbService>,
+    cipher: KeyCipher,
+    algorithm: String,
+    rotation_interval: i64,
+    publication_period: i64,
+    retirement_period: i64,
+}
+
+impl KeyManager {
+    pub fn new(db: Addr<DbService>, cipher: KeyCipher, opts: &TokenOpts) -> Result<Self> {
+        if opts.publication_period >= opts.rotation_interval {
+            return Err(Error::InvalidInput(
+                "token.publication_period must be shorter than token.rotation_interval".to_string(),
+            ));
+        }
+
+        Ok(KeyManager {
+            db,
+            cipher,
+            algorithm: opts.signing_algorithm.clone(),
+            rotation_interval: opts.rotation_interval,
+            publication_period: opts.publication_period,
+            retirement_period: opts.retirement_period,
+        })
+    }
+
+    pub async fn list(&self) -> Result<Vec<SigningKeys>> {
+        self.db.send_traced(GetSigningKeys).await?
+    }
+
+    /// Publishes a new key, which becomes the active one once it has been in
+    /// the JWKS for the publication period. The previous active key keeps
+    /// verifying tokens until its retirement period ends.
+    pub async fn rotate(&self, alg: Option<&str>) -> Result<String> {
+        let now = chrono::Utc::now();
+        let outcome = self
+            .db
+            .send_traced(RotateSigningKeys {
+                candidate: Some(self.generate(alg)?),
+                publish_before: now,
+                activate_before: now - chrono::Duration::seconds(self.publication_period),
+                retired_before: now - chrono::Duration::seconds(self.retirement_period),
+            })
+            .await??;
+
+        outcome.published.ok_or(Error::InvalidInput(
+            "A signing key is already pending".to_string(),
+        ))
+    }
+
+    /// Revokes a key immediately. The active key is replaced right away,
+    /// without waiting for the publication period, by the pending key if
+    /// there is one or else a new key.
+    pub async fn revoke(&self, kid: &str) -> Result<()> {
+        // Generating a key is expensive, so a replacement is only prepared
+        // for a key that looks active; the revocation checks again.
+        let is_active = self
+            .list()
+            .await?
+            .iter()
+            .any(|key| key.kid == kid && key.state == KEY_STATE_ACTIVE);
+        let rotation = if is_active {
+            let now = chrono::Utc::now();
+            Some(RotateSigningKeys {
+                candidate: Some(self.generate(None)?),
+                publish_before: now,
+                activate_before: chrono::DateTime::<chrono::Utc>::MAX_UTC,
+                retired_before: now - chrono::Duration::seconds(self.retirement_period),
+            })
+        } else {
+            None
+        };
+
+        if !self
+            .db
+            .send_traced(RevokeSigningKey {
+                kid: kid.to_string(),
+                rotation,
+            })
+            .await??
+        {
+            return Err(Error::InvalidInput(format!("Unknown signing key {}", kid)));
+        }
+        Ok(())
+    }
+
+    /// Publishes a new key a publication period before the active key is due
+    /// for rotation, activates it once that period passed, revokes retiring
+    /// keys past their overlap window and returns the keys the issuer should
+    /// use from now on.
+    pub async fn run_rotation(&self) -> Result<KeyStore> {
+        let now = chrono::Utc::now();
+        let publish_before =
+            now - chrono::Duration::seconds(self.rotation_interval - self.publication_period);
+
+        // Generating a key is expensive, so only when one is likely to be
+        // published. Another instance may still get there first.
+        let keys = self.list().await?;
+        let publish = !keys.iter().any(|key| key.state == KEY_STATE_PENDING)
+            && keys
+                .iter()
+                .find(|key| key.state == KEY_STATE_ACTIVE)
+                .is_none_or(|key| key.activated_at.unwrap_or(key.created_at) <= publish_before);
+        let candidate = match publish {
+            true => Some(self.generate(None)?),
+            false => None,
+        };
+
+        let outcome = self
+            .db
+            .send_traced(RotateSigningKeys {
+                candidate,
+                publish_before,
+                activate_before: now - chrono::Duration::seconds(self.publication_period),
+                retired_before: now - chrono::Duration::seconds(self.retirement_period),
+            })
+            .await??;
+        if let Some(kid) = &outcome.published {
+            tracing::info!("Published signing key {}", kid);
+        }
+        if let Some(kid) = &outcome.activated {
+            tracing::info!("Activated signing key {}", kid);
+        }
+        if outcome.revoked > 0 {
+            tracing::info!("Revoked {} retired signing keys", outcome.revoked);
+        }
+
+        self.load_key_store().await
+    }
+
+    fn generate(&self, alg: Option<&str>) -> Result<NewSigningKey> {
+        let alg = alg.unwrap_or(&self.algorithm);
+        let (key, pem) = SigningKey::generate(alg)?;
+
+        Ok(NewSigningKey {
+            encrypted_private_key: self.cipher.encrypt(&key.kid, &pem)?,
+            kid: key.kid,
+            alg: alg.to_string(),
+        })
+    }
+
+    async fn load_key_store(&self) -> Result<KeyStore> {
+        let keys = self.list().await?;
+
+        let mut active = None;
+        let mut retiring = Vec::new();
+        let mut pending = Vec::new();
+        for key in keys {
+            let pem = self.cipher.decrypt(&key.kid, &key.encrypted_private_key)?;
+            let signing_key = SigningKey::from_pem(key.kid, &key.alg, &pem)?;
+
+            match key.state.as_str() {
+                KEY_STATE_ACTIVE => active = Some(signing_key),
+                KEY_STATE_RETIRING => retiring.push(signing_key),
+                KEY_STATE_PENDING => pending.push(signing_key),
+                _ => {}
+            }
+        }
+
+        let active = active.ok_or(Error::StringError("No active signing key".to_string()))?;
+        Ok(KeyStore::with_retiring(active, retiring).with_pending(pending))
+    }
+}
+
+/// Periodically runs the key rotation and reloads the issuer's keys. Changes
+/// made with the admin CLI or by other instances are picked up as soon as
+/// they are committed, through a `LISTEN` on `signing_keys`.
+pub struct KeyRotationService {
+    manager: KeyManager,
+    token_issuer: TokenIssuer,
+    check_interval: Duration,
+    database_url: String,
+}
+
+impl KeyRotationService {
+    pub fn new(
+        manager: KeyManager,
+        token_issuer: TokenIssuer,
+        check_interval: u64,
+        database_url: String,
+    ) -> Self {
+        KeyRotationService {
+            manager,
+            token_issuer,
+            check_interval: Duration::from_secs(check_interval),
+            database_url,
+        }
+    }
+}
+
+impl Actor for KeyRotationService {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(self.check_interval, |service, ctx| {
+            let manager = service.manager.clone();
+            let token_issuer = service.token_issuer.clone();
+
+            ctx.spawn(
+                async move {
+                    match manager.run_rotation().await {
+                        Ok(keys) => token_issuer.replace_keys(keys),
+                        Err(e) => tracing::error!("Signing key rotation failed: {}", e),
+                    }
+                }
+                .into_actor(service),
+            );
+        });
+
+        // Spawned on the actor, so it ends when the actor stops.
+        let database_url = self.database_url.clone();
+        let addr = ctx.address();
+        ctx.spawn(
+            async move {
+                loop {
+                    if let Err(e) = listen_for_key_changes(&database_url, &addr).await {
+                        tracing::error!("Listening for signing key changes failed: {}", e);
+                    }
+                    tokio::time::sleep(Duration::from_secs(SIGNING_KEYS_LISTEN_RETRY)).await;
+                }
+            }
+            .into_actor(self),
+        );
+    }
+}
+
+impl Handler<ReloadSigningKeys> for KeyRotationService {
+    type Result = ();
+
+    fn handle(&mut self, _: ReloadSigningKeys, ctx: &mut Self::Context) -> Self::Result {
+        let manager = self.manager.clone();
+        let token_issuer = self.token_issuer.clone();
+
+        ctx.spawn(
+            async move {
+                match manager.load_key_store().await {
+                    Ok(keys) => {
+                        token_issuer.replace_keys(keys);
+                        tracing::info!("Reloaded signing keys");
+                    }
+                    Err(e) => tracing::error!("Failed to reload signing keys: {}", e),
+                }
+            }
+            .into_actor(self),
+        );
+    }
+}
+
+impl Handler<Stop> for KeyRotationService {
+    type Result = ();
+
+    fn handle(&mut self, _: Stop, ctx: &mut Self::Context) -> Self::Result {
+        ctx.stop();
+    }
+}
+
+/// Asks the service to reload its keys on every change notification until the
+/// connection drops. Also reloads once listening, as changes made while the
+/// connection was down would be missed otherwise.
+async fn listen_for_key_changes(
+    database_url: &str,
+    service: &Addr<KeyRotationService>,
+) -> Result<()> {
+    let (client, mut connection) = tokio_postgres::connect(database_url, NoTls).await?;
+    let (changed, mut changes) = tokio::sync::mpsc::unbounded_channel();
+
+    // The connection has to be polled for the client's queries to complete.
+    let connection = async move {
+        let mut messages = futures_util::stream::poll_fn(|cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            if let AsyncMessage::Notification(_) = message? {
+                let _ = changed.send(());
+            }
+        }
+        Ok::<_, Error>(())
+    };
+    let listener = async move {
+        client
+            .batch_execute(&format!("LISTEN {}", SIGNING_KEYS_CHANNEL))
+            .await?;
+        service.do_send(ReloadSigningKeys);
+
+        while changes.recv().await.is_some() {
+            service.do_send(ReloadSigningKeys);
+        }
+        Ok::<_, Error>(())
+    };
+
+    futures_util::try_join!(connection, listener)?;
+    Err(Error::StringError("Database connection closed".to_string()))
+}
@@ -0,0 +1,202 @@
+use crate::errors::{Error, Result};
+use crate::services::token::consts::{
+    ALG_EDDSA, ALG_ES256, ALG_RS256, CURVE_ED25519, CURVE_P256, KEY_TYPE_EC, KEY_TYPE_OKP,
+    KEY_TYPE_RSA, KEY_USE_SIGNATURE, KID_LEN, RSA_KEY_BITS,
+};
+use crate::services::token::models::{Jwk, JwkSet};
+use data_encoding::{BASE64URL_NOPAD, HEXLOWER};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+
+/// A key the service signs its own tokens with, together with its public JWK.
+#[derive(Clone)]
+pub struct SigningKey {
+    pub kid: String,
+    pub alg: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwk: Jwk,
+}
+
+impl SigningKey {
+    /// Generates a new key for one of the supported algorithms and returns it
+    /// together with its PKCS#8 PEM private key.
+    pub fn generate(alg: &str) -> Result<(Self, Vec<u8>)> {
+        let private_key = match alg {
+            ALG_RS256 => PKey::from_rsa(Rsa::generate(RSA_KEY_BITS)?)?,
+            ALG_ES256 => {
+                let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+                PKey::from_ec_key(EcKey::generate(&group)?)?
+            }
+            ALG_EDDSA => PKey::generate_ed25519()?,
+            _ => return Err(unsupported(alg)),
+        };
+        let pem = private_key.private_key_to_pem_pkcs8()?;
+
+        let mut kid = [0u8; KID_LEN];
+        openssl::rand::rand_bytes(&mut kid)?;
+
+        Ok((Self::from_pem(HEXLOWER.encode(&kid), alg, &pem)?, pem))
+    }
+
+    pub fn from_rsa_pem(kid: String, private_key_pem: &[u8]) -> Result<Self> {
+        Self::from_pem(kid, ALG_RS256, private_key_pem)
+    }
+
+    pub fn from_pem(kid: String, alg: &str, private_key_pem: &[u8]) -> Result<Self> {
+        let private_key = PKey::private_key_from_pem(private_key_pem)?;
+        let public_key_pem = private_key.public_key_to_pem()?;
+
+        let (algorithm, encoding_key, decoding_key) = match alg {
+            ALG_RS256 => (
+                Algorithm::RS256,
+                EncodingKey::from_rsa_pem(private_key_pem)?,
+                DecodingKey::from_rsa_pem(&public_key_pem)?,
+            ),
+            ALG_ES256 => (
+                Algorithm::ES256,
+                EncodingKey::from_ec_pem(private_key_pem)?,
+                DecodingKey::from_ec_pem(&public_key_pem)?,
+            ),
+            ALG_EDDSA => (
+                Algorithm::EdDSA,
+                EncodingKey::from_ed_pem(private_key_pem)?,
+                DecodingKey::from_ed_pem(&public_key_pem)?,
+            ),
+            _ => return Err(unsupported(alg)),
+        };
+
+        Ok(SigningKey {
+            jwk: public_jwk(&kid, alg, &private_key)?,
+            kid,
+            alg: algorithm,
+            encoding_key,
+            decoding_key,
+        })
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+}
+
+/// The set of signing keys: one active key new tokens are signed with, plus
+/// retiring keys whose tokens must still verify during the overlap window.
+/// Pending keys are only published, so verifiers have them cached by the
+/// time they become active.
+#[derive(Clone)]
+pub struct KeyStore {
+    active_kid: String,
+    keys: Vec<SigningKey>,
+    pending: Vec<SigningKey>,
+}
+
+impl KeyStore {
+    pub fn new(active: SigningKey) -> Self {
+        Self::with_retiring(active, Vec::new())
+    }
+
+    pub fn with_retiring(active: SigningKey, retiring: Vec<SigningKey>) -> Self {
+        let mut keys = vec![active];
+        keys.extend(retiring);
+
+        KeyStore {
+            active_kid: keys[0].kid.clone(),
+            keys,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn with_pending(mut self, pending: Vec<SigningKey>) -> Self {
+        self.pending = pending;
+        self
+    }
+
+    pub fn active(&self) -> Result<&SigningKey> {
+        self.get(&self.active_kid).ok_or(Error::StringError(
+            "Active signing key is missing".to_string(),
+        ))
+    }
+
+    pub fn get(&self, kid: &str) -> Option<&SigningKey> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+
+    /// The public keys published at `/.well-known/jwks.json`.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self
+                .keys
+                .iter()
+                .chain(&self.pending)
+                .map(|key| key.jwk.clone())
+                .collect(),
+        }
+    }
+
+    /// Algorithms of the keys in the store, for the discovery document.
+    pub fn algorithms(&self) -> Vec<String> {
+        let mut algorithms: Vec<String> = Vec::new();
+        for key in self.keys.iter().chain(&self.pending) {
+            if !algorithms.contains(&key.jwk.alg) {
+                algorithms.push(key.jwk.alg.clone());
+            }
+        }
+        algorithms
+    }
+}
+
+fn public_jwk(kid: &str, alg: &str, private_key: &PKey<Private>) -> Result<Jwk> {
+    let mut jwk = Jwk {
+        kty: String::new(),
+        use_: KEY_USE_SIGNATURE.to_string(),
+        alg: alg.to_string(),
+        kid: kid.to_string(),
+        n: None,
+        e: None,
+        crv: None,
+        x: None,
+        y: None,
+    };
+
+    match alg {
+        ALG_RS256 => {
+            let rsa = private_key.rsa()?;
+            jwk.kty = KEY_TYPE_RSA.to_string();
+            jwk.n = Some(BASE64URL_NOPAD.encode(&rsa.n().to_vec()));
+            jwk.e = Some(BASE64URL_NOPAD.encode(&rsa.e().to_vec()));
+        }
+        ALG_ES256 => {
+            let ec = private_key.ec_key()?;
+            let mut x = BigNum::new()?;
+            let mut y = BigNum::new()?;
+            let mut ctx = BigNumContext::new()?;
+            ec.public_key()
+                .affine_coordinates(ec.group(), &mut x, &mut y, &mut ctx)?;
+            jwk.kty = KEY_TYPE_EC.to_string();
+            jwk.crv = Some(CURVE_P256.to_string());
+            jwk.x = Some(BASE64URL_NOPAD.encode(&x.to_vec_padded(32)?));
+            jwk.y = Some(BASE64URL_NOPAD.encode(&y.to_vec_padded(32)?));
+        }
+        ALG_EDDSA => {
+            jwk.kty = KEY_TYPE_OKP.to_string();
+            jwk.crv = Some(CURVE_ED25519.to_string());
+            jwk.x = Some(BASE64URL_NOPAD.encode(&private_key.raw_public_key()?));
+        }
+        _ => return Err(unsupported(alg)),
+    }
+
+    Ok(jwk)
+}
+
+fn unsupported(alg: &str) -> Error {
+    Error::InvalidInput(format!("Unsupported signing algorithm {}", alg))
+}
@@ -0,0 +1,6 @@
+pub mod consts;
+pub mod key_cipher;
+pub mod key_rotation;
+pub mod key_store;
+pub mod models;
+pub mod token_issuer;
@@ -0,0 +1,167 @@
+use crate::errors::{Error, Result};
+use crate::services::token::key_store::{KeyStore, SigningKey};
+use crate::services::token::models::{IssuedClaims, IssuedIdTokenClaims, JwkSet};
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
+use openssl::rsa::Rsa;
+use serde::Serialize;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard};
+
+/// Signs and verifies access tokens issued by the service itself, for login
+/// methods that do not go through Auth0 and for our own OAuth clients.
+#[derive(Clone)]
+pub struct TokenIssuer {
+    issuer: String,
+    audience: String,
+    access_token_ttl: i64,
+    // Shared between workers and swapped by the key rotation actor.
+    keys: Arc<RwLock<KeyStore>>,
+}
+
+impl TokenIssuer {
+    pub fn new(
+        issuer: String,
+        audience: String,
+        kid: String,
+        access_token_ttl: i64,
+        private_key_pem: &[u8],
+    ) -> Result<Self> {
+        Ok(Self::with_key_store(
+            issuer,
+            audience,
+            access_token_ttl,
+            KeyStore::new(SigningKey::from_rsa_pem(kid, private_key_pem)?),
+        ))
+    }
+
+    pub fn with_key_store(
+        issuer: String,
+        audience: String,
+        access_token_ttl: i64,
+        keys: KeyStore,
+    ) -> Self {
+        TokenIssuer {
+            issuer,
+            audience,
+            access_token_ttl,
+            keys: Arc::new(RwLock::new(keys)),
+        }
+    }
+
+    /// Creates an issuer with a freshly generated key. Tokens signed with it do
+    /// not survive a restart, so this is only meant for local development.
+    pub fn ephemeral(
+        issuer: String,
+        audience: String,
+        kid: String,
+        access_token_ttl: i64,
+    ) -> Result<Self> {
+        let rsa = Rsa::generate(2048)?;
+        Self::new(
+            issuer,
+            audience,
+            kid,
+            access_token_ttl,
+            &rsa.private_key_to_pem()?,
+        )
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    pub fn access_token_ttl(&self) -> i64 {
+        self.access_token_ttl
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        self.keys().jwks()
+    }
+
+    pub fn signing_algorithms(&self) -> Vec<String> {
+        self.keys().algorithms()
+    }
+
+    /// Swaps in a reloaded set of signing keys.
+    pub fn replace_keys(&self, keys: KeyStore) {
+        *self.keys.write().unwrap_or_else(PoisonError::into_inner) = keys;
+    }
+
+    fn keys(&self) -> RwLockReadGuard<'_, KeyStore> {
+        self.keys.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn issue_access_token(&self, user_id: &str) -> Result<String> {
+        self.issue_scoped_access_token(user_id, None, None, self.access_token_ttl)
+    }
+
+    /// Issues an access token on behalf of an OAuth client, recording the client
+    /// and the granted scope in the token.
+    pub fn issue_scoped_access_token(
+        &self,
+        subject: &str,
+        client_id: Option<&str>,
+        scope: Option<&str>,
+        ttl: i64,
+    ) -> Result<String> {
+        let now = chrono::Utc::now().timestamp();
+        let claims = IssuedClaims {
+            sub: subject.to_string(),
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            iat: now,
+            exp: now + ttl,
+            jti: uuid::Uuid::new_v4().to_string(),
+            client_id: client_id.map(str::to_string),
+            scope: scope.map(str::to_string),
+        };
+
+        self.sign(&claims)
+    }
+
+    pub fn issue_id_token(
+        &self,
+        user_id: &str,
+        client_id: &str,
+        nonce: Option<String>,
+        email: Option<String>,
+    ) -> Result<String> {
+        let now = chrono::Utc::now().timestamp();
+        let claims = IssuedIdTokenClaims {
+            sub: user_id.to_string(),
+            iss: self.issuer.clone(),
+            aud: client_id.to_string(),
+            iat: now,
+            exp: now + self.access_token_ttl,
+            nonce,
+            email,
+        };
+
+        self.sign(&claims)
+    }
+
+    pub fn verify(&self, token: &str) -> Result<IssuedClaims> {
+        let header = decode_header(token)?;
+        let keys = self.keys();
+        let key = match &header.kid {
+            Some(kid) => keys.get(kid).ok_or(Error::Unauthorized)?,
+            None => keys.active()?,
+        };
+
+        let mut validation = Validation::new(key.alg);
+        validation.set_audience(&[&self.audience]);
+        validation.set_issuer(&[&self.issuer]);
+
+        let token_data = decode::<IssuedClaims>(token, key.decoding_key(), &validation)?;
+        Ok(token_data.claims)
+    }
+
+    fn sign<T: Serialize>(&self, claims: &T) -> Result<String> {
+        let keys = self.keys();
+        let key = keys.active()?;
+
+        let mut header = Header::new(key.alg);
+        header.kid = Some(key.kid.clone());
+
+        Ok(encode(&header, claims, key.encoding_key())?)
+    }
+}
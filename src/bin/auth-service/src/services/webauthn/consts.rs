@@ -0,0 +1,17 @@
+pub const CHALLENGE_LEN: usize = 32;
+pub const CEREMONY_REGISTRATION: &str = "registration";
+pub const CEREMONY_AUTHENTICATION: &str = "authentication";
+pub const CLIENT_DATA_CREATE: &str = "webauthn.create";
+pub const CLIENT_DATA_GET: &str = "webauthn.get";
+pub const PUBLIC_KEY_TYPE: &str = "public-key";
+pub const ATTESTATION_NONE: &str = "none";
+pub const ATTESTATION_PACKED: &str = "packed";
+
+pub const FLAG_USER_PRESENT: u8 = 0x01;
+pub const FLAG_USER_VERIFIED: u8 = 0x04;
+pub const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+pub const COSE_ALG_ES256: i64 = -7;
+pub const COSE_ALG_EDDSA: i64 = -8;
+pub const COSE_ALG_RS256: i64 = -257;
+pub const SUPPORTED_ALGORITHMS: [i64; 3] = [COSE_ALG_ES256, COSE_ALG_EDDSA, COSE_ALG_RS256];
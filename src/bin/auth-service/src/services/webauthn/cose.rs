@@ -0,0 +1,258 @@
+use crate::errors::{Error, Result};
+use crate::services::webauthn::consts::{COSE_ALG_EDDSA, COSE_ALG_ES256, COSE_ALG_RS256};
+use ciborium::value::Value;
+use openssl::bn::BigNum;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, Public};
+use openssl::rsa::Rsa;
+use openssl::sign::Verifier;
+
+const KEY_TYPE: i128 = 1;
+const KEY_ALG: i128 = 3;
+const EC2_CURVE: i128 = -1;
+const EC2_X: i128 = -2;
+const EC2_Y: i128 = -3;
+const RSA_N: i128 = -1;
+const RSA_E: i128 = -2;
+const OKP_X: i128 = -2;
+
+const KTY_OKP: i128 = 1;
+const KTY_EC2: i128 = 2;
+const KTY_RSA: i128 = 3;
+const CRV_P256: i128 = 1;
+const CRV_ED25519: i128 = 6;
+
+/// Converts a COSE_Key (RFC 9053) into its algorithm and an OpenSSL public key.
+pub fn parse_public_key(cose_key: &Value) -> Result<(i64, PKey<Public>)> {
+    let Value::Map(entries) = cose_key else {
+        return Err(invalid("COSE key is not a map"));
+    };
+
+    let int = |label: i128| -> Option<i128> {
+        entries.iter().find_map(|(key, value)| match (key, value) {
+            (Value::Integer(key), Value::Integer(value)) if i128::from(*key) == label => {
+                Some(i128::from(*value))
+            }
+            _ => None,
+        })
+    };
+    let bytes = |label: i128| -> Result<&[u8]> {
+        entries
+            .iter()
+            .find_map(|(key, value)| match (key, value) {
+                (Value::Integer(key), Value::Bytes(value)) if i128::from(*key) == label => {
+                    Some(value.as_slice())
+                }
+                _ => None,
+            })
+            .ok_or_else(|| invalid("COSE key is missing a parameter"))
+    };
+
+    let kty = int(KEY_TYPE).ok_or_else(|| invalid("COSE key is missing kty"))?;
+    let alg = int(KEY_ALG).ok_or_else(|| invalid("COSE key is missing alg"))? as i64;
+
+    let key = match (kty, alg) {
+        (KTY_EC2, COSE_ALG_ES256) => {
+            if int(EC2_CURVE) != Some(CRV_P256) {
+                return Err(invalid("Unsupported EC curve"));
+            }
+            let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+            let x = BigNum::from_slice(bytes(EC2_X)?)?;
+            let y = BigNum::from_slice(bytes(EC2_Y)?)?;
+            let ec_key = EcKey::from_public_key_affine_coordinates(&group, &x, &y)?;
+            ec_key.check_key()?;
+            PKey::from_ec_key(ec_key)?
+        }
+        (KTY_RSA, COSE_ALG_RS256) => {
+            let n = BigNum::from_slice(bytes(RSA_N)?)?;
+            let e = BigNum::from_slice(bytes(RSA_E)?)?;
+            PKey::from_rsa(Rsa::from_public_components(n, e)?)?
+        }
+        (KTY_OKP, COSE_ALG_EDDSA) => {
+            if int(EC2_CURVE) != Some(CRV_ED25519) {
+                return Err(invalid("Unsupported OKP curve"));
+            }
+            PKey::public_key_from_raw_bytes(bytes(OKP_X)?, Id::ED25519)?
+        }
+        _ => return Err(invalid("Unsupported COSE key algorithm")),
+    };
+
+    Ok((alg, key))
+}
+
+/// Verifies a WebAuthn signature made with the given COSE algorithm.
+pub fn verify_signature(
+    alg: i64,
+    key: &PKey<Public>,
+    data: &[u8],
+    signature: &[u8],
+) -> Result<bool> {
+    let verified = match alg {
+        COSE_ALG_ES256 | COSE_ALG_RS256 => {
+            let mut verifier = Verifier::new(MessageDigest::sha256(), key)?;
+            verifier.update(data)?;
+            verifier.verify(signature).unwrap_or(false)
+        }
+        COSE_ALG_EDDSA => {
+            let mut verifier = Verifier::new_without_digest(key)?;
+            verifier.verify_oneshot(signature, data).unwrap_or(false)
+        }
+        _ => return Err(invalid("Unsupported signature algorithm")),
+    };
+
+    Ok(verified)
+}
+
+fn invalid(message: &str) -> Error {
+    Error::WebauthnVerification(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::bn::BigNumContext;
+    use openssl::pkey::Private;
+
+    fn cose_key(entries: Vec<(i128, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(label, value)| (Value::Integer((label as i64).into()), value))
+                .collect(),
+        )
+    }
+
+    fn int(value: i128) -> Value {
+        Value::Integer((value as i64).into())
+    }
+
+    fn ec2_key() -> Result<(PKey<Private>, Value)> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let ec_key = EcKey::generate(&group)?;
+        let mut x = BigNum::new()?;
+        let mut y = BigNum::new()?;
+        let mut ctx = BigNumContext::new()?;
+        ec_key
+            .public_key()
+            .affine_coordinates(&group, &mut x, &mut y, &mut ctx)?;
+
+        let cose = cose_key(vec![
+            (KEY_TYPE, int(KTY_EC2)),
+            (KEY_ALG, int(COSE_ALG_ES256 as i128)),
+            (EC2_CURVE, int(CRV_P256)),
+            (EC2_X, Value::Bytes(x.to_vec_padded(32)?)),
+            (EC2_Y, Value::Bytes(y.to_vec_padded(32)?)),
+        ]);
+        Ok((PKey::from_ec_key(ec_key)?, cose))
+    }
+
+    fn rsa_key() -> Result<(PKey<Private>, Value)> {
+        let rsa = Rsa::generate(2048)?;
+        let cose = cose_key(vec![
+            (KEY_TYPE, int(KTY_RSA)),
+            (KEY_ALG, int(COSE_ALG_RS256 as i128)),
+            (RSA_N, Value::Bytes(rsa.n().to_vec())),
+            (RSA_E, Value::Bytes(rsa.e().to_vec())),
+        ]);
+        Ok((PKey::from_rsa(rsa)?, cose))
+    }
+
+    fn okp_key() -> Result<(PKey<Private>, Value)> {
+        let key = PKey::generate_ed25519()?;
+        let cose = cose_key(vec![
+            (KEY_TYPE, int(KTY_OKP)),
+            (KEY_ALG, int(COSE_ALG_EDDSA as i128)),
+            (EC2_CURVE, int(CRV_ED25519)),
+            (OKP_X, Value::Bytes(key.raw_public_key()?)),
+        ]);
+        Ok((key, cose))
+    }
+
+    fn sign(alg: i64, key: &PKey<Private>, data: &[u8]) -> Result<Vec<u8>> {
+        let mut signer = match alg {
+            COSE_ALG_EDDSA => openssl::sign::Signer::new_without_digest(key)?,
+            _ => openssl::sign::Signer::new(MessageDigest::sha256(), key)?,
+        };
+        Ok(signer.sign_oneshot_to_vec(data)?)
+    }
+
+    #[test]
+    fn parses_supported_keys() -> Result<()> {
+        let cases = [
+            (COSE_ALG_ES256, ec2_key()?),
+            (COSE_ALG_RS256, rsa_key()?),
+            (COSE_ALG_EDDSA, okp_key()?),
+        ];
+
+        for (expected_alg, (private_key, cose)) in cases {
+            let (alg, public_key) = parse_public_key(&cose)?;
+            assert_eq!(alg, expected_alg);
+            assert!(public_key.public_eq(&private_key), "alg {}", alg);
+
+            let signature = sign(alg, &private_key, b"signed data")?;
+            assert!(verify_signature(
+                alg,
+                &public_key,
+                b"signed data",
+                &signature
+            )?);
+            assert!(!verify_signature(
+                alg,
+                &public_key,
+                b"other data",
+                &signature
+            )?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_keys() -> Result<()> {
+        let (_, ec2) = ec2_key()?;
+        let Value::Map(ec2_entries) = ec2 else {
+            unreachable!()
+        };
+        let without = |label: i128| {
+            Value::Map(
+                ec2_entries
+                    .iter()
+                    .filter(|(key, _)| key != &int(label))
+                    .cloned()
+                    .collect(),
+            )
+        };
+        let with = |label: i128, value: Value| {
+            Value::Map(
+                ec2_entries
+                    .iter()
+                    .map(|(key, old)| {
+                        let value = if key == &int(label) {
+                            value.clone()
+                        } else {
+                            old.clone()
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect(),
+            )
+        };
+
+        let cases = [
+            ("not a map", Value::Bytes(vec![1, 2, 3])),
+            ("missing kty", without(KEY_TYPE)),
+            ("missing alg", without(KEY_ALG)),
+            ("missing x", without(EC2_X)),
+            ("unsupported curve", with(EC2_CURVE, int(2))),
+            ("unsupported algorithm", with(KEY_ALG, int(-35))),
+            ("kty and alg mismatch", with(KEY_TYPE, int(KTY_RSA))),
+            ("point not on curve", with(EC2_Y, Value::Bytes(vec![1; 32]))),
+        ];
+
+        for (name, cose) in cases {
+            assert!(parse_public_key(&cose).is_err(), "{}", name);
+        }
+        Ok(())
+    }
+}
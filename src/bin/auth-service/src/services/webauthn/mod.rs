@@ -0,0 +1,4 @@
+pub mod consts;
+pub mod cose;
+pub mod models;
+pub mod webauthn_service;
@@ -0,0 +1,290 @@
+pub mod redaction;
+
+use crate::errors::{Error, Result};
+use crate::opts::cmd_opts::{LoggingOpts, TracingOpts};
+use crate::telemetry::redaction::{RedactingMakeWriter, RedactingSpanProcessor};
+use actix_web::http::header::HeaderMap;
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry::{global, Context};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{BatchSpanProcessor, Sampler, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use reqwest::header::{HeaderName, HeaderValue};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, Registry};
+
+/// Keeps the span exporter running until `shutdown` flushes it.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                tracing::error!("Failed to flush spans: {}", e);
+            }
+        }
+    }
+}
+
+/// Sets up logging and, when a collector is configured, OTLP span export.
+/// `log` records from dependencies are forwarded to the same subscriber.
+pub fn init_tracing(logging: &LoggingOpts, opts: &TracingOpts) -> Result<Telemetry> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let mut layers = vec![log_layer(logging)?];
+
+    let provider = match &opts.otlp_endpoint {
+        Some(endpoint) => Some(tracer_provider(opts, endpoint)?),
+        None => None,
+    };
+    // Only our own spans are exported, so the exporter's HTTP and gRPC
+    // clients don't trace their own exports.
+    if let Some(provider) = &provider {
+        layers.push(
+            tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer(opts.service_name.clone()))
+                .with_filter(
+                    Targets::new().with_target(env!("CARGO_CRATE_NAME"), LevelFilter::TRACE),
+                )
+                .boxed(),
+        );
+    }
+
+    tracing_subscriber::registry().with(layers).try_init()?;
+    tracing::info!(
+        "Logging at level {} in {} format",
+        logging.level,
+        logging.format
+    );
+
+    match &opts.otlp_endpoint {
+        Some(endpoint) => tracing::info!(
+            "Exporting spans over {} to {}",
+            opts.otlp_protocol,
+            endpoint
+        ),
+        None => tracing::info!("No OTLP endpoint configured, spans are not exported"),
+    }
+
+    Ok(Telemetry { provider })
+}
+
+/// The log output, filtered on its own so log levels don't decide which
+/// spans get exported.
+fn log_layer(opts: &LoggingOpts) -> Result<Box<dyn Layer<Registry> + Send + Sync>> {
+    let mut targets = Targets::new().with_default(parse_level(&opts.level)?);
+    for (module, level) in &opts.modules {
+        targets = targets.with_target(module.clone(), parse_level(level)?);
+    }
+
+    let writer = RedactingMakeWriter::new(std::io::stdout);
+    let layer = tracing_subscriber::fmt::layer()
+        .with_thread_ids(true)
+        .with_line_number(true)
+        .with_writer(writer);
+    let layer = match opts.format.as_str() {
+        "text" => layer.with_ansi(opts.ansi).with_filter(targets).boxed(),
+        "json" => layer
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_filter(targets)
+            .boxed(),
+        format => {
+            return Err(Error::InvalidInput(format!(
+                "Unsupported log format {}, expected text or json",
+                format
+            )))
+        }
+    };
+
+    Ok(layer)
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter> {
+    LevelFilter::from_str(level)
+        .map_err(|_| Error::InvalidInput(format!("Unsupported log level {}", level)))
+}
+
+fn tracer_provider(opts: &TracingOpts, endpoint: &str) -> Result<SdkTracerProvider> {
+    let timeout = Duration::from_millis(opts.export_timeout);
+    let exporter = match opts.otlp_protocol.as_str() {
+        "grpc" => SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .with_timeout(timeout)
+            .build()?,
+        "http" => SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .with_timeout(timeout)
+            .build()?,
+        protocol => {
+            return Err(Error::InvalidInput(format!(
+                "Unsupported OTLP protocol {}, expected grpc or http",
+                protocol
+            )))
+        }
+    };
+
+    // Events carry the log messages, so they're redacted like the log output.
+    let processor = RedactingSpanProcessor::new(BatchSpanProcessor::builder(exporter).build());
+    Ok(SdkTracerProvider::builder()
+        .with_span_processor(processor)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            opts.sample_ratio,
+        ))))
+        .with_resource(
+            Resource::builder()
+                .with_service_name(opts.service_name.clone())
+                .build(),
+        )
+        .build())
+}
+
+/// The W3C trace context a caller sent along with its request.
+pub fn extract_context(headers: &HeaderMap) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&RequestHeaders(headers)))
+}
+
+/// Adds the W3C trace context of the current span to an outgoing request.
+pub fn inject_context(headers: &mut reqwest::header::HeaderMap) {
+    let context = tracing::Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut OutgoingHeaders(headers))
+    });
+}
+
+struct RequestHeaders<'a>(&'a HeaderMap);
+
+impl Extractor for RequestHeaders<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+struct OutgoingHeaders<'a>(&'a mut reqwest::header::HeaderMap);
+
+impl Injector for OutgoingHeaders<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::Tracer;
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    /// The path and body of an export the collector received.
+    type Export = io::Result<(String, Vec<u8>)>;
+
+    /// Stands in for an OTLP/HTTP collector: accepts one export and hands
+    /// over its path and body.
+    fn collector() -> Result<(String, mpsc::Receiver<Export>)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr()?);
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let _ = sender.send(accept_export(&listener));
+        });
+
+        Ok((endpoint, receiver))
+    }
+
+    fn accept_export(listener: &TcpListener) -> Export {
+        let (stream, _) = listener.accept()?;
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            if line.trim_end().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value
+                        .trim()
+                        .parse()
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        reader.get_mut().write_all(
+            b"HTTP/1.1 200 OK\r\ncontent-type: application/x-protobuf\r\ncontent-length: 0\r\n\r\n",
+        )?;
+
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+        Ok((path.to_string(), body))
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    #[test]
+    fn exports_spans_over_http() -> Result<()> {
+        let (endpoint, exports) = collector()?;
+        let opts = TracingOpts {
+            service_name: "auth-service-test".to_string(),
+            otlp_protocol: "http".to_string(),
+            export_timeout: 5000,
+            ..TracingOpts::default()
+        };
+
+        let provider = tracer_provider(&opts, &endpoint)?;
+        provider.tracer("test").in_span("otlp-export-test", |_| {});
+        // Flushes the batch before stopping.
+        provider
+            .shutdown()
+            .map_err(|e| Error::StringError(e.to_string()))?;
+
+        let (path, body) = exports
+            .recv_timeout(Duration::from_secs(10))
+            .map_err(|e| Error::StringError(e.to_string()))??;
+        assert_eq!(path, "/v1/traces");
+        assert!(contains(&body, b"otlp-export-test"));
+        assert!(contains(&body, b"auth-service-test"));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_protocols() {
+        let opts = TracingOpts {
+            otlp_protocol: "udp".to_string(),
+            ..TracingOpts::default()
+        };
+        assert!(tracer_provider(&opts, "http://127.0.0.1:4318").is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use crate::services::actors::messages::Stop;
+use crate::tls::cert_resolver::ReloadingCertResolver;
+use crate::tls::load_certified_key;
+use actix::{Actor, ActorContext, AsyncContext, Context, Handler};
+use rustls::crypto::CryptoProvider;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Periodically checks whether the certificate or key file changed and, once
+/// both load and match, hands them to the resolver.
+pub struct CertificateReloader {
+    resolver: Arc<ReloadingCertResolver>,
+    cert_file: String,
+    key_file: String,
+    provider: Arc<CryptoProvider>,
+    interval: Duration,
+    modified: Option<(SystemTime, SystemTime)>,
+}
+
+impl CertificateReloader {
+    pub fn new(
+        resolver: Arc<ReloadingCertResolver>,
+        cert_file: String,
+        key_file: String,
+        provider: Arc<CryptoProvider>,
+        interval: u64,
+    ) -> Self {
+        let mut reloader = CertificateReloader {
+            resolver,
+            cert_file,
+            key_file,
+            provider,
+            interval: Duration::from_secs(interval),
+            modified: None,
+        };
+        reloader.modified = reloader.modified().ok();
+        reloader
+    }
+
+    fn modified(&self) -> std::io::Result<(SystemTime, SystemTime)> {
+        Ok((
+            std::fs::metadata(&self.cert_file)?.modified()?,
+            std::fs::metadata(&self.key_file)?.modified()?,
+        ))
+    }
+
+    fn reload_if_changed(&mut self) {
+        let modified = match self.modified() {
+            Ok(modified) => modified,
+            Err(e) => {
+                tracing::error!("Failed to check TLS certificate files: {}", e);
+                return;
+            }
+        };
+        if self.modified == Some(modified) {
+            return;
+        }
+
+        // The files are often replaced one at a time, so a mismatch is
+        // retried on the next check rather than remembered.
+        match load_certified_key(&self.cert_file, &self.key_file, &self.provider) {
+            Ok(key) => {
+                self.resolver.replace(key);
+                self.modified = Some(modified);
+                tracing::info!("Reloaded TLS certificate from {}", self.cert_file);
+            }
+            Err(e) => tracing::error!(
+                "Failed to reload TLS certificate, keeping the current one: {}",
+                e
+            ),
+        }
+    }
+}
+
+impl Actor for CertificateReloader {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(self.interval, |reloader, _| reloader.reload_if_changed());
+    }
+}
+
+impl Handler<Stop> for CertificateReloader {
+    type Result = ();
+
+    fn handle(&mut self, _: Stop, ctx: &mut Self::Context) -> Self::Result {
+        ctx.stop();
+    }
+}
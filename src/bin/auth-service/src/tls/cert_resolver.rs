@@ -0,0 +1,33 @@
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Hands every handshake the current certificate, which the reloader swaps
+/// when the files change, so new connections pick it up without a restart.
+#[derive(Debug)]
+pub struct ReloadingCertResolver {
+    key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ReloadingCertResolver {
+    pub fn new(key: CertifiedKey) -> Self {
+        ReloadingCertResolver {
+            key: RwLock::new(Arc::new(key)),
+        }
+    }
+
+    pub fn replace(&self, key: CertifiedKey) {
+        *self.key.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(key);
+    }
+}
+
+impl ResolvesServerCert for ReloadingCertResolver {
+    fn resolve(&self, _: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.key
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+        )
+    }
+}
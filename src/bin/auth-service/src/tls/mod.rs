@@ -0,0 +1,108 @@
+pub mod cert_reloader;
+pub mod cert_resolver;
+
+use crate::errors::{Error, Result};
+use crate::opts::cmd_opts::TlsOpts;
+use crate::tls::cert_reloader::CertificateReloader;
+use crate::tls::cert_resolver::ReloadingCertResolver;
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::dev::Extensions;
+use actix_web::rt::net::TcpStream;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::sign::CertifiedKey;
+use rustls::{RootCertStore, ServerConfig, SupportedProtocolVersion};
+use std::any::Any;
+use std::sync::Arc;
+
+/// Set on connections whose client presented a certificate that verified
+/// against `client_ca_file`.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientCertificate;
+
+/// Builds the rustls config from the configured files. The reloader, when
+/// reloading is enabled, still has to be started.
+pub fn load_tls(opts: &TlsOpts) -> Result<(ServerConfig, Option<CertificateReloader>)> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let resolver = Arc::new(ReloadingCertResolver::new(load_certified_key(
+        &opts.cert_file,
+        &opts.key_file,
+        &provider,
+    )?));
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(protocol_versions(&opts.min_version)?)?;
+    let builder = match &opts.client_ca_file {
+        Some(client_ca_file) => {
+            let mut roots = RootCertStore::empty();
+            for cert in CertificateDer::pem_file_iter(client_ca_file)? {
+                roots.add(cert?)?;
+            }
+            // Only the admin endpoints need a client certificate, so
+            // connections without one are still accepted.
+            let verifier =
+                WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                    .allow_unauthenticated()
+                    .build()?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    // actix-web adds `h2` and `http/1.1` to the ALPN protocols itself.
+    let config = builder.with_cert_resolver(resolver.clone());
+
+    let reloader = (opts.reload_interval > 0).then(|| {
+        CertificateReloader::new(
+            resolver,
+            opts.cert_file.clone(),
+            opts.key_file.clone(),
+            provider,
+            opts.reload_interval,
+        )
+    });
+    Ok((config, reloader))
+}
+
+/// Reads the certificate chain and key, checking that they belong together.
+pub(crate) fn load_certified_key(
+    cert_file: &str,
+    key_file: &str,
+    provider: &CryptoProvider,
+) -> Result<CertifiedKey> {
+    let certs = CertificateDer::pem_file_iter(cert_file)?.collect::<std::result::Result<_, _>>()?;
+    let key = PrivateKeyDer::from_pem_file(key_file)?;
+
+    Ok(CertifiedKey::from_der(certs, key, provider)?)
+}
+
+static TLS13_ONLY: &[&SupportedProtocolVersion] = &[&rustls::version::TLS13];
+
+fn protocol_versions(min_version: &str) -> Result<&'static [&'static SupportedProtocolVersion]> {
+    match min_version {
+        "1.2" => Ok(rustls::ALL_VERSIONS),
+        "1.3" => Ok(TLS13_ONLY),
+        _ => Err(Error::InvalidInput(format!(
+            "unsupported minimum TLS version {}",
+            min_version
+        ))),
+    }
+}
+
+/// Passed to `HttpServer::on_connect` to mark connections that presented a
+/// verified client certificate.
+pub fn record_client_certificate(connection: &dyn Any, data: &mut Extensions) {
+    let Some(tls) = connection.downcast_ref::<TlsStream<TcpStream>>() else {
+        return;
+    };
+    if tls
+        .get_ref()
+        .1
+        .peer_certificates()
+        .is_some_and(|certs| !certs.is_empty())
+    {
+        data.insert(ClientCertificate);
+    }
+}
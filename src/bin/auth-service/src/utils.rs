@@ -1,76 +1,82 @@
-use crate::errors::Result;
+use crate::consts::AUTHORIZATION;
+use crate::errors::{Error, Result};
 use crate::opts::app::AppState;
-use actix_web::web::{Data, ServiceConfig};
-use colored::Colorize;
-use log::{Level, LevelFilter};
-use std::collections::HashMap;
+use crate::services::oidc::consts::ERROR_INVALID_REQUEST;
+use actix_web::web::{Data, FormConfig, JsonConfig, PathConfig, QueryConfig, ServiceConfig};
+use actix_web::HttpRequest;
+use data_encoding::BASE64;
 use std::path::PathBuf;
-use std::str::FromStr;
-use std::thread::ThreadId;
 
-pub fn init_logging() -> Result<()> {
-    // Logging src errors and all app logs
-    let log_level = LevelFilter::Debug;
-
-    // This is the main logging dispatch
-    let mut main_logging_dispatch = fern::Dispatch::new().level(log_level);
-
-    let stdout_dispatch = fern::Dispatch::new()
-        .format(move |out, message, record| {
-            out.finish(format_args!(
-                "{}[{}][{}][{}::{}] {}",
-                chrono::Utc::now().format("[%Y-%m-%d][%H:%M:%S%.3f]"),
-                parse_thread_id(&std::thread::current().id()),
-                match record.level() {
-                    Level::Error => format!("{}", record.level()).red(),
-                    Level::Warn => format!("{}", record.level()).red().italic(),
-                    Level::Info => format!("{}", record.level()).green(),
-                    Level::Debug => format!("{}", record.level()).yellow(),
-                    Level::Trace => format!("{}", record.level()).bold(),
-                },
-                record.target(),
-                record
-                    .line()
-                    .map(|v| v.to_string())
-                    .unwrap_or_else(|| "".to_owned()),
-                message
-            ))
-        })
-        .chain(std::io::stdout());
-    // LevelFilter::from_str()
-    main_logging_dispatch = main_logging_dispatch.chain(stdout_dispatch);
-
-    let log_level_for: HashMap<String, String> = HashMap::new();
-
-    for (module, log_level) in log_level_for.into_iter() {
-        let log_level = LevelFilter::from_str(&log_level)?;
-        main_logging_dispatch = main_logging_dispatch.level_for(module, log_level);
-    }
-
-    main_logging_dispatch.apply()?;
+pub fn configure_data(app_state: AppState) -> Box<dyn FnOnce(&mut ServiceConfig)> {
+    Box::new(move |cfg: &mut ServiceConfig| {
+        cfg.app_data(Data::new(app_state.database))
+            .app_data(Data::new(app_state.auth0))
+            .app_data(Data::new(app_state.mfa))
+            .app_data(Data::new(app_state.token_issuer))
+            .app_data(Data::new(app_state.webauthn))
+            .app_data(Data::new(app_state.oidc))
+            .app_data(Data::new(app_state.admin))
+            .app_data(Data::new(app_state.api_keys))
+            .app_data(Data::new(app_state.sessions))
+            .app_data(Data::new(app_state.public_paths))
+            .app_data(Data::new(app_state.trusted_proxies))
+            .app_data(Data::new(app_state.metrics))
+            .app_data(Data::new(app_state.audit))
+            .app_data(Data::new(app_state.health))
+            .app_data(
+                JsonConfig::default()
+                    .error_handler(|err, _| Error::InvalidBody(err.to_string()).into()),
+            )
+            .app_data(
+                QueryConfig::default()
+                    .error_handler(|err, _| Error::InvalidInput(err.to_string()).into()),
+            )
+            .app_data(
+                PathConfig::default()
+                    .error_handler(|err, _| Error::InvalidInput(err.to_string()).into()),
+            )
+            // Form bodies only reach the OAuth endpoints, which answer in the
+            // RFC 6749 error format.
+            .app_data(FormConfig::default().error_handler(|err, _| {
+                Error::OAuth {
+                    error: ERROR_INVALID_REQUEST,
+                    description: err.to_string(),
+                }
+                .into()
+            }));
+    })
+}
 
-    log::info!("Logging level {} enabled", log_level);
+/// Returns the bearer token from the `Authorization` header.
+pub fn bearer_token(req: &HttpRequest) -> Result<&str> {
+    let auth_header = req
+        .headers()
+        .get(AUTHORIZATION)
+        .ok_or(Error::Unauthorized)?;
 
-    Ok(())
+    auth_header
+        .to_str()?
+        .strip_prefix("Bearer ")
+        .ok_or(Error::Unauthorized)
 }
 
-fn parse_thread_id(id: &ThreadId) -> String {
-    let id_str = format!("{:?}", id);
+/// Returns the client id and secret from an HTTP Basic `Authorization` header,
+/// or `None` when the request doesn't use Basic authentication.
+pub fn basic_credentials(req: &HttpRequest) -> Result<Option<(String, String)>> {
+    let Some(auth_header) = req.headers().get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+    let Some(encoded) = auth_header.to_str()?.strip_prefix("Basic ") else {
+        return Ok(None);
+    };
 
-    let parsed = (|| {
-        let start_idx = id_str.find('(')?;
-        let end_idx = id_str.rfind(')')?;
-        Some(id_str[start_idx + 1..end_idx].to_owned())
-    })();
-
-    parsed.unwrap_or(id_str)
-}
+    let decoded = BASE64
+        .decode(encoded.trim().as_bytes())
+        .map_err(|_| Error::Unauthorized)?;
+    let decoded = String::from_utf8(decoded).map_err(|_| Error::Unauthorized)?;
+    let (client_id, client_secret) = decoded.split_once(':').ok_or(Error::Unauthorized)?;
 
-pub fn configure_data(app_state: AppState) -> Box<dyn FnOnce(&mut ServiceConfig)> {
-    Box::new(move |cfg: &mut ServiceConfig| {
-        cfg.app_data(Data::new(app_state.database))
-            .app_data(Data::new(app_state.auth0));
-    })
+    Ok(Some((client_id.to_string(), client_secret.to_string())))
 }
 
 #[derive(Debug)]